@@ -0,0 +1,185 @@
+use std::fmt;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use crate::{AudioCommand, MediaCommand};
+
+/// Something the user asked the island to do, whether by clicking it, from the tray or
+/// with a hotkey.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", content = "payload", rename_all = "kebab-case")]
+pub enum IslandAction {
+    Expand,
+    Collapse,
+    /// Expands the island when there's something to expand, or collapses it when it already
+    /// is.
+    ToggleExpanded,
+    /// `None` dismisses the notification showing.
+    DismissNotification(Option<u64>),
+    DismissVolume,
+    ResetLoudness,
+    TogglePlayback,
+    Next,
+    Previous,
+    /// Milliseconds from the start of the track.
+    Seek(u64),
+    ToggleShuffle,
+    /// Off, then repeating the whole list, then just the track.
+    CycleRepeat,
+    /// Steps through a few common speeds.
+    CycleRate,
+    /// Added to the volume, from 0 to 1.
+    VolumeStep(f32),
+    ToggleMute,
+    ToggleMicMute,
+    ListOutputDevices,
+    SetOutputDevice(String),
+    /// Today's sensor use per app, for the privacy indicators.
+    ListPrivacyReport,
+    ToggleDoNotDisturb,
+}
+
+impl IslandAction {
+    /// The action the UI's `action(name, payload)` callback describes. The names are the
+    /// ones serde gives the variants.
+    pub fn parse(name: &str, payload: &str) -> Result<Self> {
+        let action =
+            |payload| serde_json::from_value(json!({ "action": name, "payload": payload }));
+
+        let parsed = match serde_json::from_str(payload) {
+            // the UI only passes numbers and text, and the text may look like a number
+            Ok(number @ Value::Number(_)) => action(number).or_else(|_| action(payload.into())),
+            _ if payload.is_empty() => action(Value::Null),
+            _ => action(payload.into()),
+        };
+
+        parsed.with_context(|| format!("bad action `{name}` with payload `{payload}`"))
+    }
+
+    /// The name `parse` takes, and the UI uses to tell which control an error belongs to.
+    pub fn name(&self) -> String {
+        match self.fields().remove("action") {
+            Some(Value::String(name)) => name,
+            _ => unreachable!("actions are tagged with their name"),
+        }
+    }
+
+    /// The payload `parse` takes, empty for actions without one.
+    pub fn payload(&self) -> String {
+        match self.fields().remove("payload") {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(text)) => text,
+            Some(value) => value.to_string(),
+        }
+    }
+
+    fn fields(&self) -> Map<String, Value> {
+        // through text, since a value would widen a volume step to `0.05000000074505806`
+        let json = serde_json::to_string(self).expect("actions serialize");
+        serde_json::from_str(&json).expect("actions serialize to an object")
+    }
+}
+
+impl fmt::Display for IslandAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.payload().as_str() {
+            "" => f.write_str(&self.name()),
+            payload => write!(f, "{} {payload}", self.name()),
+        }
+    }
+}
+
+impl From<MediaCommand> for IslandAction {
+    fn from(command: MediaCommand) -> Self {
+        match command {
+            MediaCommand::TogglePlayback => Self::TogglePlayback,
+            MediaCommand::Next => Self::Next,
+            MediaCommand::Previous => Self::Previous,
+            MediaCommand::Seek(position) => Self::Seek(position),
+            MediaCommand::SetShuffle(_) => Self::ToggleShuffle,
+            MediaCommand::SetRepeat(_) => Self::CycleRepeat,
+            MediaCommand::SetRate(_) => Self::CycleRate,
+        }
+    }
+}
+
+impl From<AudioCommand> for IslandAction {
+    fn from(command: AudioCommand) -> Self {
+        match command {
+            AudioCommand::StepVolume(step) => Self::VolumeStep(step),
+            AudioCommand::ToggleMute => Self::ToggleMute,
+            // the volume is only read on its own when the island expands
+            AudioCommand::ReadVolume => Self::Expand,
+            AudioCommand::ListOutputDevices => Self::ListOutputDevices,
+            AudioCommand::SetOutputDevice(id) => Self::SetOutputDevice(id),
+            AudioCommand::ToggleMicMute => Self::ToggleMicMute,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_action_survives_a_trip_through_the_ui() {
+        let actions = [
+            IslandAction::Expand,
+            IslandAction::Collapse,
+            IslandAction::ToggleExpanded,
+            IslandAction::DismissNotification(None),
+            IslandAction::DismissNotification(Some(42)),
+            IslandAction::DismissVolume,
+            IslandAction::ResetLoudness,
+            IslandAction::TogglePlayback,
+            IslandAction::Next,
+            IslandAction::Previous,
+            IslandAction::Seek(95_000),
+            IslandAction::ToggleShuffle,
+            IslandAction::CycleRepeat,
+            IslandAction::CycleRate,
+            IslandAction::VolumeStep(0.05),
+            IslandAction::VolumeStep(-0.05),
+            IslandAction::ToggleMute,
+            IslandAction::ToggleMicMute,
+            IslandAction::ListOutputDevices,
+            IslandAction::SetOutputDevice("alsa_output.pci-0000_00_1f.3.analog-stereo".into()),
+            // ids that happen to look like numbers are still text
+            IslandAction::SetOutputDevice("42".into()),
+            IslandAction::ListPrivacyReport,
+            IslandAction::ToggleDoNotDisturb,
+        ];
+
+        for action in actions {
+            let parsed = IslandAction::parse(&action.name(), &action.payload());
+            assert_eq!(parsed.unwrap(), action);
+        }
+    }
+
+    #[test]
+    fn names_and_payloads_are_what_the_ui_sends() {
+        assert_eq!(IslandAction::ToggleDoNotDisturb.name(), "toggle-do-not-disturb");
+        assert_eq!(IslandAction::Seek(5000).to_string(), "seek 5000");
+        assert_eq!(IslandAction::VolumeStep(-0.05).to_string(), "volume-step -0.05");
+        assert_eq!(IslandAction::DismissNotification(None).to_string(), "dismiss-notification");
+    }
+
+    #[test]
+    fn malformed_actions_are_rejected() {
+        for (name, payload) in [
+            ("warp", ""),
+            ("Seek", "1000"),
+            ("seek", ""),
+            ("seek", "soon"),
+            ("seek", "-5"),
+            ("volume-step", "loud"),
+            ("dismiss-notification", "latest"),
+            ("set-output-device", ""),
+            ("expand", "now"),
+        ] {
+            assert!(IslandAction::parse(name, payload).is_err(), "{name} {payload}");
+        }
+    }
+}
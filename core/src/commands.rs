@@ -0,0 +1,557 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Result;
+use tokio::{runtime::Handle, sync::Notify};
+
+use crate::{
+    CoreEvent, MediaState, RepeatMode, bus::EventSender, runtime::RuntimeState,
+    services::media::execute,
+};
+
+/// How long the player gets to carry out a command before it's given up on.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long the player has to report what a command it accepted did, before what it
+/// reports wins over what was expected.
+const CONFIRM_TIMEOUT: Duration = Duration::from_millis(1500);
+/// How far the reported position may be from where a seek should have landed.
+const POSITION_TOLERANCE_MS: u64 = 1000;
+
+/// Identifies a command from being queued until its `CoreEvent::CommandFinished`.
+pub type CommandId = u64;
+
+/// Something to ask the media player to do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaCommand {
+    TogglePlayback,
+    Next,
+    Previous,
+    /// Milliseconds from the start of the track.
+    Seek(u64),
+    SetShuffle(bool),
+    SetRepeat(RepeatMode),
+    /// 1 is normal speed.
+    SetRate(f64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    Failed(String),
+    TimedOut,
+    /// Cancelled, or replaced by a later seek before it ran.
+    Cancelled,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Failed(e) => f.write_str(e),
+            Self::TimedOut => write!(f, "the player didn't respond within {COMMAND_TIMEOUT:?}"),
+            Self::Cancelled => f.write_str("cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Media commands waiting to run on the core runtime, one at a time and in the order they
+/// were sent. What a command should do shows in `RuntimeState::media` as soon as it's
+/// queued, and is rolled back if it fails.
+pub struct CommandQueue {
+    shared: Arc<Shared>,
+}
+
+/// Carries out a command, on the player in the app and on a fake one in the tests.
+type Execute =
+    Box<dyn Fn(MediaCommand) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> + Send + Sync>;
+
+struct Shared {
+    state: Mutex<QueueState>,
+    wake: Notify,
+    execute: Execute,
+    runtime: Arc<RuntimeState>,
+    tx: EventSender,
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_id: CommandId,
+    pending: VecDeque<Queued>,
+    running: Option<(CommandId, MediaCommand, Arc<Notify>)>,
+}
+
+struct Queued {
+    id: CommandId,
+    command: MediaCommand,
+    /// The app and title of what was playing when it was sent.
+    track: Option<(String, String)>,
+}
+
+impl CommandQueue {
+    pub fn start(handle: &Handle, runtime: Arc<RuntimeState>, tx: EventSender) -> Self {
+        Self::start_with(handle, runtime, tx, Box::new(|command| Box::pin(execute(command))))
+    }
+
+    fn start_with(
+        handle: &Handle,
+        runtime: Arc<RuntimeState>,
+        tx: EventSender,
+        execute: Execute,
+    ) -> Self {
+        let shared =
+            Arc::new(Shared { state: Mutex::default(), wake: Notify::new(), execute, runtime, tx });
+
+        handle.spawn(run(shared.clone()));
+
+        Self { shared }
+    }
+
+    pub fn push(&self, command: MediaCommand) -> CommandId {
+        let track = playing_track(&self.shared.runtime);
+        let mut state = self.shared.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id;
+
+        // dragging the timeline sends a seek per frame, only the last one still matters
+        if let MediaCommand::Seek(_) = command
+            && state
+                .pending
+                .back()
+                .is_some_and(|last| matches!(last.command, MediaCommand::Seek(_)))
+            && let Some(replaced) = state.pending.pop_back()
+        {
+            self.shared.finish(replaced.id, replaced.command, Err(CommandError::Cancelled));
+        }
+
+        state.pending.push_back(Queued { id, command, track });
+        drop(state);
+
+        if self.shared.runtime.update_media(|media| media.expect(id, command)) {
+            let _ = self.shared.tx.send(CoreEvent::Arbitrary);
+        }
+
+        self.shared.wake.notify_one();
+        id
+    }
+
+    /// Drops the command if it hasn't run yet, or stops waiting for the player if it's
+    /// running.
+    pub fn cancel(&self, id: CommandId) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if let Some((running, _, stop)) = &state.running
+            && *running == id
+        {
+            stop.notify_one();
+        }
+
+        self.shared.drop_where(&mut state, |queued| queued.id == id);
+    }
+}
+
+impl Shared {
+    fn finish(&self, id: CommandId, command: MediaCommand, result: Result<(), CommandError>) {
+        let accepted = result.is_ok();
+        self.runtime.update_media(|media| media.finished(id, accepted));
+
+        let _ = self.tx.send(CoreEvent::CommandFinished { id, command, result });
+    }
+
+    /// Cancels the pending commands `cancel` picks.
+    fn drop_where(&self, state: &mut QueueState, cancel: impl Fn(&Queued) -> bool) {
+        state.pending.retain(|queued| {
+            let cancelled = cancel(queued);
+            if cancelled {
+                self.finish(queued.id, queued.command, Err(CommandError::Cancelled));
+            }
+            !cancelled
+        });
+    }
+}
+
+fn playing_track(runtime: &RuntimeState) -> Option<(String, String)> {
+    let media = runtime.media.read().unwrap();
+    media.as_ref().map(|media| (media.app_id.clone(), media.title.clone()))
+}
+
+async fn run(shared: Arc<Shared>) {
+    loop {
+        let next = {
+            let mut state = shared.state.lock().unwrap();
+
+            // a seek is only meant for the track that was playing when it was sent
+            let track = playing_track(&shared.runtime);
+            shared.drop_where(&mut state, |queued| {
+                matches!(queued.command, MediaCommand::Seek(_)) && queued.track != track
+            });
+
+            state.pending.pop_front().map(|Queued { id, command, .. }| {
+                let stop = Arc::new(Notify::new());
+                state.running = Some((id, command, stop.clone()));
+                (id, command, stop)
+            })
+        };
+
+        let Some((id, command, stop)) = next else {
+            shared.wake.notified().await;
+            continue;
+        };
+
+        let result = tokio::select! {
+            result = tokio::time::timeout(COMMAND_TIMEOUT, (shared.execute)(command)) => {
+                match result {
+                    Ok(Ok(())) => Ok(()),
+                    Ok(Err(e)) => Err(CommandError::Failed(format!("{e:#}"))),
+                    Err(_) => Err(CommandError::TimedOut),
+                }
+            }
+            _ = stop.notified() => Err(CommandError::Cancelled),
+        };
+
+        if result.is_ok() {
+            let shared = shared.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(CONFIRM_TIMEOUT).await;
+                if shared.runtime.update_media(Optimistic::expire) {
+                    let _ = shared.tx.send(CoreEvent::Arbitrary);
+                }
+            });
+        }
+
+        shared.state.lock().unwrap().running = None;
+        shared.finish(id, command, result);
+    }
+}
+
+/// The media state as the player last reported it, with what the commands it hasn't caught
+/// up with yet should do laid over it.
+#[derive(Default)]
+pub(crate) struct Optimistic {
+    reported: Option<MediaState>,
+    expected: Vec<Expected>,
+}
+
+struct Expected {
+    id: CommandId,
+    /// The app and title of the track it was meant for.
+    track: (String, String),
+    playing: Option<bool>,
+    /// Where a seek lands, and when it was sent.
+    position: Option<(u64, SystemTime)>,
+    shuffle: Option<bool>,
+    repeat: Option<RepeatMode>,
+    rate: Option<f64>,
+    /// Set once the player accepted the command.
+    confirm_by: Option<Instant>,
+}
+
+impl Expected {
+    fn confirmed_by(&self, media: &MediaState) -> bool {
+        self.playing.is_none_or(|playing| playing == media.playing)
+            && self.shuffle.is_none_or(|shuffle| media.shuffle == Some(shuffle))
+            && self.repeat.is_none_or(|repeat| media.repeat == Some(repeat))
+            && self.rate.is_none_or(|rate| media.rate.is_some_and(|r| (r - rate).abs() < 0.01))
+            && self.position.is_none_or(|(position, sent_at)| {
+                let elapsed = sent_at.elapsed().unwrap_or_default().as_millis() as u64;
+                let expected = if media.playing { position + elapsed } else { position };
+
+                media.current_position_ms().abs_diff(expected.min(media.duration_ms))
+                    < POSITION_TOLERANCE_MS
+            })
+    }
+}
+
+impl Optimistic {
+    /// What the island should show.
+    pub(crate) fn shown(&self) -> Option<MediaState> {
+        let mut media = self.reported.clone()?;
+
+        for expected in &self.expected {
+            // the position carries on from here at the new pace
+            if expected.playing.is_some_and(|playing| playing != media.playing)
+                || expected.rate.is_some_and(|rate| Some(rate) != media.rate)
+            {
+                media.position_ms = media.current_position_ms();
+                media.synced_at = SystemTime::now();
+            }
+
+            media.playing = expected.playing.unwrap_or(media.playing);
+            media.shuffle = expected.shuffle.or(media.shuffle);
+            media.repeat = expected.repeat.or(media.repeat);
+            media.rate = expected.rate.or(media.rate);
+
+            if let Some((position, sent_at)) = expected.position {
+                media.position_ms = position;
+                media.synced_at = sent_at;
+            }
+        }
+
+        Some(media)
+    }
+
+    /// Takes in what the player reports. Whatever it already did is no longer expected,
+    /// and neither is anything meant for a track that's gone.
+    pub(crate) fn report(&mut self, media: Option<MediaState>) -> bool {
+        let Some(reported) = &media else {
+            self.expected.clear();
+            self.reported = None;
+            return true;
+        };
+
+        self.expected.retain(|expected| {
+            expected.track.0 == reported.app_id && expected.track.1 == reported.title
+        });
+
+        // the player got as far as this one, so it's also past everything sent before it
+        if let Some(last) = self.expected.iter().rposition(|e| e.confirmed_by(reported)) {
+            self.expected.drain(..=last);
+        }
+
+        self.reported = media;
+        true
+    }
+
+    /// Lays what `command` should do over the media state. Skips and previous aren't
+    /// guessed, what the next track is only the player knows.
+    fn expect(&mut self, id: CommandId, command: MediaCommand) -> bool {
+        let Some(media) = self.shown() else {
+            return false;
+        };
+
+        let mut expected = Expected {
+            id,
+            track: (media.app_id, media.title),
+            playing: None,
+            position: None,
+            shuffle: None,
+            repeat: None,
+            rate: None,
+            confirm_by: None,
+        };
+
+        match command {
+            MediaCommand::TogglePlayback => expected.playing = Some(!media.playing),
+            MediaCommand::Seek(position) => {
+                expected.position = Some((position.min(media.duration_ms), SystemTime::now()));
+            }
+            MediaCommand::SetShuffle(shuffle) => expected.shuffle = Some(shuffle),
+            MediaCommand::SetRepeat(repeat) => expected.repeat = Some(repeat),
+            MediaCommand::SetRate(rate) => expected.rate = Some(rate),
+            MediaCommand::Next | MediaCommand::Previous => return false,
+        }
+
+        self.expected.push(expected);
+        true
+    }
+
+    /// Starts waiting for the player to report what an accepted command did, or rolls a
+    /// failed one back.
+    fn finished(&mut self, id: CommandId, accepted: bool) -> bool {
+        if accepted {
+            if let Some(expected) = self.expected.iter_mut().find(|e| e.id == id) {
+                expected.confirm_by = Some(Instant::now() + CONFIRM_TIMEOUT);
+            }
+            return false;
+        }
+
+        let count = self.expected.len();
+        self.expected.retain(|e| e.id != id);
+        self.expected.len() != count
+    }
+
+    /// Gives up on accepted commands the player never reported back on, and shows what it
+    /// says instead.
+    fn expire(&mut self) -> bool {
+        let now = Instant::now();
+        let count = self.expected.len();
+        self.expected.retain(|e| e.confirm_by.is_none_or(|deadline| deadline > now));
+        self.expected.len() != count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Config,
+        bus::{EventReceiver, create_bus},
+    };
+
+    /// A queue whose player accepts everything right away except skips, which it never
+    /// answers. Returns the commands it got to run.
+    fn queue(
+        runtime: Arc<RuntimeState>,
+    ) -> (CommandQueue, EventReceiver, Arc<Mutex<Vec<MediaCommand>>>) {
+        let (tx, rx) = create_bus();
+        let ran = Arc::new(Mutex::new(Vec::new()));
+
+        let log = ran.clone();
+        let execute: Execute = Box::new(move |command| {
+            log.lock().unwrap().push(command);
+            Box::pin(async move {
+                if command == MediaCommand::Next {
+                    std::future::pending::<()>().await;
+                }
+                Ok(())
+            })
+        });
+
+        (CommandQueue::start_with(&Handle::current(), runtime, tx, execute), rx, ran)
+    }
+
+    async fn finished(rx: &EventReceiver) -> (CommandId, MediaCommand, Result<(), CommandError>) {
+        loop {
+            match rx.try_recv() {
+                Ok(CoreEvent::CommandFinished { id, command, result }) => {
+                    return (id, command, result);
+                }
+                Ok(_) => {}
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+    }
+
+    fn media(title: &str) -> MediaState {
+        MediaState {
+            synced_at: SystemTime::now(),
+            app_id: "player".into(),
+            app_name: "Player".into(),
+            title: title.into(),
+            artist: "M83".into(),
+            album: String::new(),
+            album_art: None,
+            palette: None,
+            duration_ms: 240_000,
+            position_ms: 0,
+            playing: false,
+            shuffle: None,
+            repeat: None,
+            rate: None,
+            capabilities: Default::default(),
+            lyrics: None,
+            app_icon: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn seeks_waiting_their_turn_collapse_into_the_last() {
+        let (queue, rx, ran) = queue(Arc::new(RuntimeState::new(Config::default())));
+
+        let toggle = queue.push(MediaCommand::TogglePlayback);
+        let seeks = [1000, 2000, 3000].map(|ms| queue.push(MediaCommand::Seek(ms)));
+
+        assert_eq!(
+            finished(&rx).await,
+            (seeks[0], MediaCommand::Seek(1000), Err(CommandError::Cancelled))
+        );
+        assert_eq!(
+            finished(&rx).await,
+            (seeks[1], MediaCommand::Seek(2000), Err(CommandError::Cancelled))
+        );
+        assert_eq!(finished(&rx).await, (toggle, MediaCommand::TogglePlayback, Ok(())));
+        assert_eq!(finished(&rx).await, (seeks[2], MediaCommand::Seek(3000), Ok(())));
+
+        assert_eq!(*ran.lock().unwrap(), [MediaCommand::TogglePlayback, MediaCommand::Seek(3000)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_player_that_never_answers_times_out() {
+        let (queue, rx, ran) = queue(Arc::new(RuntimeState::new(Config::default())));
+
+        let next = queue.push(MediaCommand::Next);
+        let toggle = queue.push(MediaCommand::TogglePlayback);
+
+        assert_eq!(finished(&rx).await, (next, MediaCommand::Next, Err(CommandError::TimedOut)));
+        // the queue moves on to what came after it
+        assert_eq!(finished(&rx).await, (toggle, MediaCommand::TogglePlayback, Ok(())));
+        assert_eq!(*ran.lock().unwrap(), [MediaCommand::Next, MediaCommand::TogglePlayback]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn seeks_for_a_track_thats_gone_never_run() {
+        let runtime = Arc::new(RuntimeState::new(Config::default()));
+        runtime.update_media(|optimistic| optimistic.report(Some(media("Midnight City"))));
+        let (queue, rx, ran) = queue(runtime.clone());
+
+        let stale = queue.push(MediaCommand::Seek(60_000));
+        let toggle = queue.push(MediaCommand::TogglePlayback);
+        runtime.update_media(|optimistic| optimistic.report(Some(media("Wait"))));
+        let seek = queue.push(MediaCommand::Seek(1000));
+
+        assert_eq!(
+            finished(&rx).await,
+            (stale, MediaCommand::Seek(60_000), Err(CommandError::Cancelled))
+        );
+        assert_eq!(finished(&rx).await, (toggle, MediaCommand::TogglePlayback, Ok(())));
+        assert_eq!(finished(&rx).await, (seek, MediaCommand::Seek(1000), Ok(())));
+
+        assert_eq!(*ran.lock().unwrap(), [MediaCommand::TogglePlayback, MediaCommand::Seek(1000)]);
+    }
+
+    #[test]
+    fn a_report_with_what_was_expected_confirms_it() {
+        let mut optimistic = Optimistic::default();
+        optimistic.report(Some(media("Midnight City")));
+
+        assert!(optimistic.expect(1, MediaCommand::TogglePlayback));
+        assert!(optimistic.shown().unwrap().playing);
+        optimistic.finished(1, true);
+
+        let mut playing = media("Midnight City");
+        playing.playing = true;
+        optimistic.report(Some(playing));
+        assert!(optimistic.expected.is_empty());
+
+        // from here on what the player says goes, like a pause from its own window
+        optimistic.report(Some(media("Midnight City")));
+        assert!(!optimistic.shown().unwrap().playing);
+    }
+
+    #[test]
+    fn a_failed_command_rolls_back_to_what_was_reported() {
+        let mut reported = media("Midnight City");
+        reported.shuffle = Some(false);
+        reported.rate = Some(1.0);
+
+        let mut optimistic = Optimistic::default();
+        optimistic.report(Some(reported));
+
+        optimistic.expect(1, MediaCommand::SetShuffle(true));
+        optimistic.expect(2, MediaCommand::SetRate(1.5));
+        assert_eq!(optimistic.shown().unwrap().shuffle, Some(true));
+
+        assert!(optimistic.finished(1, false));
+        let shown = optimistic.shown().unwrap();
+        assert_eq!(shown.shuffle, Some(false));
+        // the one after it still stands
+        assert_eq!(shown.rate, Some(1.5));
+    }
+
+    #[test]
+    fn an_accepted_command_the_player_never_reports_expires() {
+        let mut optimistic = Optimistic::default();
+        optimistic.report(Some(media("Midnight City")));
+
+        optimistic.expect(1, MediaCommand::SetRepeat(RepeatMode::List));
+        optimistic.expect(2, MediaCommand::SetShuffle(true));
+        optimistic.finished(1, true);
+
+        // the player still reports the old mode, but has a while to catch up
+        optimistic.report(Some(media("Midnight City")));
+        assert!(!optimistic.expire());
+        assert_eq!(optimistic.shown().unwrap().repeat, Some(RepeatMode::List));
+
+        optimistic.expected[0].confirm_by = Some(Instant::now());
+        assert!(optimistic.expire());
+
+        let shown = optimistic.shown().unwrap();
+        assert_eq!(shown.repeat, None);
+        // still running, so there's nothing to wait for yet
+        assert_eq!(shown.shuffle, Some(true));
+    }
+}
@@ -0,0 +1,250 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{Hotkeys, utils::config_path};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    pub visualizer: VisualizerMode,
+    pub loudness_meter: bool,
+    pub monitor: MonitorPlacement,
+    pub anchor: Anchor,
+    pub offset: Offset,
+    /// Lets the island sit over panels and taskbars instead of inside the work area.
+    pub cover_panels: bool,
+    pub theme: ThemeSelection,
+    pub hotkeys: Hotkeys,
+    /// Where `.lrc` files are looked for; the `lyrics` folder next to `config.toml` when
+    /// unset.
+    pub lyrics_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VisualizerMode {
+    #[default]
+    Bars,
+    MirroredBars,
+    Waveform,
+    Oscilloscope,
+    Vu,
+}
+
+/// Which monitor the island goes on. Any value other than the keywords is taken as a
+/// monitor name, as the OS reports it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum MonitorPlacement {
+    #[default]
+    Primary,
+    Cursor,
+    Focused,
+    Mirrored,
+    Named(String),
+}
+
+impl From<String> for MonitorPlacement {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "primary" => Self::Primary,
+            "cursor" => Self::Cursor,
+            "focused" => Self::Focused,
+            "mirrored" => Self::Mirrored,
+            _ => Self::Named(value),
+        }
+    }
+}
+
+impl From<MonitorPlacement> for String {
+    fn from(value: MonitorPlacement) -> Self {
+        match value {
+            MonitorPlacement::Primary => "primary".into(),
+            MonitorPlacement::Cursor => "cursor".into(),
+            MonitorPlacement::Focused => "focused".into(),
+            MonitorPlacement::Mirrored => "mirrored".into(),
+            MonitorPlacement::Named(name) => name,
+        }
+    }
+}
+
+/// A preset or a file in the themes folder; `auto` follows the OS between the dark and
+/// light ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ThemeSelection {
+    #[default]
+    Auto,
+    Named(String),
+}
+
+impl From<String> for ThemeSelection {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "auto" => Self::Auto,
+            _ => Self::Named(value),
+        }
+    }
+}
+
+impl From<ThemeSelection> for String {
+    fn from(value: ThemeSelection) -> Self {
+        match value {
+            ThemeSelection::Auto => "auto".into(),
+            ThemeSelection::Named(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+    TopLeft,
+    #[default]
+    TopCenter,
+    TopRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl Anchor {
+    pub const ALL: [Anchor; 6] = [
+        Anchor::TopLeft,
+        Anchor::TopCenter,
+        Anchor::TopRight,
+        Anchor::BottomLeft,
+        Anchor::BottomCenter,
+        Anchor::BottomRight,
+    ];
+
+    pub fn is_bottom(self) -> bool {
+        matches!(self, Anchor::BottomLeft | Anchor::BottomCenter | Anchor::BottomRight)
+    }
+
+    /// Where along the edge the island sits: 0 at the left, 1 at the right.
+    pub fn align(self) -> f64 {
+        match self {
+            Anchor::TopLeft | Anchor::BottomLeft => 0.0,
+            Anchor::TopCenter | Anchor::BottomCenter => 0.5,
+            Anchor::TopRight | Anchor::BottomRight => 1.0,
+        }
+    }
+}
+
+/// Logical pixels in from the anchored edges; `x` moves a centered island to the right.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Offset {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let path = config_path();
+
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        match toml::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("[Config] Failed to parse {}: {e}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes `anchor` back to the config file. Only the `anchor` line changes, keeping its
+    /// line ending; it's added at the top when the file doesn't have one.
+    pub fn save_anchor(anchor: Anchor) -> Result<()> {
+        let path = config_path();
+
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let line = format!("anchor = {}", toml::Value::try_from(anchor)?);
+        let contents = replace_top_level(&contents, "anchor", &line);
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, contents)?;
+
+        Ok(())
+    }
+}
+
+/// `contents` with the top-level `key` line swapped for `line`, or `line` added first.
+/// Everything else, line endings included, stays as it was.
+fn replace_top_level(contents: &str, key: &str, line: &str) -> String {
+    let mut lines: Vec<&str> = contents.split_inclusive('\n').collect();
+    let content = |line: &str| line.trim_end_matches(['\r', '\n']).len();
+
+    // top-level keys can only come before the first table
+    let top_level =
+        lines.iter().position(|line| line.trim_start().starts_with('[')).unwrap_or(lines.len());
+    let existing = lines[..top_level].iter().position(|line| {
+        line.trim_start().strip_prefix(key).is_some_and(|rest| rest.trim_start().starts_with('='))
+    });
+
+    let replaced;
+    match existing {
+        Some(idx) => {
+            let ending = &lines[idx][content(lines[idx])..];
+            replaced = format!("{line}{ending}");
+            lines[idx] = &replaced;
+        }
+        None => {
+            // written the way the file already ends its lines
+            let ending = lines.first().map_or("\n", |first| &first[content(first)..]);
+            replaced = format!("{line}{}", if ending.is_empty() { "\n" } else { ending });
+            lines.insert(0, &replaced);
+        }
+    }
+
+    lines.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_the_top_level_line_only() {
+        let contents = "theme = \"dark\"\r\nanchor = \"top\"\r\n\n[hotkeys]\r\nanchor = \"x\"";
+
+        assert_eq!(
+            replace_top_level(contents, "anchor", "anchor = \"bottom\""),
+            "theme = \"dark\"\r\nanchor = \"bottom\"\r\n\n[hotkeys]\r\nanchor = \"x\"",
+        );
+    }
+
+    #[test]
+    fn keeps_a_missing_final_newline() {
+        assert_eq!(
+            replace_top_level("anchor = \"top\"", "anchor", "anchor = \"bottom\""),
+            "anchor = \"bottom\"",
+        );
+    }
+
+    #[test]
+    fn inserts_with_the_file_line_ending() {
+        assert_eq!(
+            replace_top_level("theme = \"dark\"\r\n[offset]\r\n", "anchor", "anchor = \"top\""),
+            "anchor = \"top\"\r\ntheme = \"dark\"\r\n[offset]\r\n",
+        );
+        assert_eq!(replace_top_level("", "anchor", "anchor = \"top\""), "anchor = \"top\"\n");
+        // an anchor inside a table isn't the top-level one
+        assert_eq!(
+            replace_top_level("[hotkeys]\nanchor = 1\n", "anchor", "anchor = \"top\""),
+            "anchor = \"top\"\n[hotkeys]\nanchor = 1\n",
+        );
+    }
+}
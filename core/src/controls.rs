@@ -0,0 +1,170 @@
+use std::sync::{Arc, atomic::Ordering};
+
+use anyhow::Result;
+use tokio::{
+    runtime::Handle,
+    sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+};
+#[cfg(windows)]
+use windows::Win32::System::Com::{COINIT_MULTITHREADED, CoInitializeEx};
+
+use crate::{
+    CoreEvent, VolumeState,
+    bus::EventSender,
+    runtime::RuntimeState,
+    services::{
+        audio::resolve_process,
+        microphone,
+        volume::{self, VolumeTarget},
+    },
+};
+
+/// Something to change about the system's audio, rather than the media player's.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioCommand {
+    /// Added to the volume, from 0 to 1.
+    StepVolume(f32),
+    ToggleMute,
+    /// Reports the volume without changing it, for when the volume control comes on screen.
+    ReadVolume,
+    ListOutputDevices,
+    SetOutputDevice(String),
+    /// Mutes or unmutes the default capture device. Apps keep their streams open, they just
+    /// record silence.
+    ToggleMicMute,
+}
+
+/// Audio commands waiting to run on the core runtime, in the order they were sent. Each
+/// reports back with an event: `CoreEvent::ControlledVolume` after anything that may have
+/// changed the volume, `CoreEvent::OutputDevices` for a listing, and
+/// `CoreEvent::AudioCommandFailed` when it didn't work.
+pub(crate) struct AudioControls {
+    commands: UnboundedSender<AudioCommand>,
+}
+
+impl AudioControls {
+    pub fn start(handle: &Handle, runtime: Arc<RuntimeState>, tx: EventSender) -> Self {
+        let (commands, rx) = unbounded_channel();
+        handle.spawn(run(rx, runtime, tx));
+
+        Self { commands }
+    }
+
+    pub fn push(&self, command: AudioCommand) {
+        let _ = self.commands.send(command);
+    }
+}
+
+async fn run(
+    mut commands: UnboundedReceiver<AudioCommand>,
+    runtime: Arc<RuntimeState>,
+    tx: EventSender,
+) {
+    while let Some(command) = commands.recv().await {
+        let mut batch = vec![command];
+        while let Ok(command) = commands.try_recv() {
+            batch.push(command);
+        }
+
+        let runtime = runtime.clone();
+        let tx = tx.clone();
+        let _ = tokio::task::spawn_blocking(move || {
+            #[cfg(windows)]
+            unsafe {
+                let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            }
+
+            apply(batch, &runtime, &tx);
+        })
+        .await;
+    }
+}
+
+/// Applies the commands that came in while the last ones were being applied. Volume steps
+/// and mute toggles are added up first, so each starts from where the last left the volume
+/// and a fast scroll doesn't queue up a round trip per tick.
+fn apply(batch: Vec<AudioCommand>, runtime: &RuntimeState, tx: &EventSender) {
+    let (mut step, mut toggle_mute, mut toggle_mic_mute) = (0.0, false, false);
+    let (mut read_back, mut list) = (false, false);
+
+    for command in batch {
+        match command {
+            AudioCommand::StepVolume(by) => step += by,
+            AudioCommand::ToggleMute => toggle_mute = !toggle_mute,
+            AudioCommand::ToggleMicMute => toggle_mic_mute = !toggle_mic_mute,
+            AudioCommand::ReadVolume => read_back = true,
+            AudioCommand::ListOutputDevices => list = true,
+            AudioCommand::SetOutputDevice(ref id) => {
+                report(tx, &command, volume::set_output_device(id));
+                read_back = true;
+            }
+        }
+    }
+
+    if step != 0.0 {
+        let target = volume_target(runtime);
+        let result =
+            volume::volume(target).and_then(|(level, _)| volume::set_volume(target, level + step));
+        report(tx, &AudioCommand::StepVolume(step), result);
+        read_back = true;
+    }
+
+    if toggle_mute {
+        let target = volume_target(runtime);
+        let result = volume::volume(target).and_then(|(_, muted)| volume::set_mute(target, !muted));
+        report(tx, &AudioCommand::ToggleMute, result);
+        read_back = true;
+    }
+
+    if toggle_mic_mute {
+        let muted = !runtime.mic_muted.load(Ordering::Relaxed);
+        let result = microphone::set_mic_muted(muted);
+        if result.is_ok() {
+            microphone::publish_muted(tx, runtime, muted);
+        }
+        report(tx, &AudioCommand::ToggleMicMute, result);
+    }
+
+    if list {
+        match volume::output_devices() {
+            Ok(devices) => {
+                let _ = tx.send(CoreEvent::OutputDevices(devices));
+            }
+            Err(e) => report(tx, &AudioCommand::ListOutputDevices, Err(e)),
+        }
+    }
+
+    if read_back {
+        read_back_volume(runtime, tx);
+    }
+}
+
+/// Reports what the volume commands actually left behind, since the backend may clamp a
+/// level or pick a different target than the last read.
+fn read_back_volume(runtime: &RuntimeState, tx: &EventSender) {
+    let Ok((level, muted)) = volume::volume(volume_target(runtime)) else {
+        return;
+    };
+    let device = runtime.volume.read().unwrap().as_ref().map(|v| v.device.clone());
+
+    let volume = VolumeState { level, muted, device: device.unwrap_or_default() };
+    let _ = tx.send(CoreEvent::ControlledVolume(volume));
+}
+
+fn report(tx: &EventSender, command: &AudioCommand, result: Result<()>) {
+    if let Err(e) = result {
+        let _ = tx.send(CoreEvent::AudioCommandFailed {
+            command: command.clone(),
+            error: format!("{e:#}"),
+        });
+    }
+}
+
+/// The media app's own audio, or the default output device when it has no stream open.
+fn volume_target(runtime: &RuntimeState) -> VolumeTarget {
+    runtime
+        .media_app_id()
+        .as_deref()
+        .and_then(resolve_process)
+        .map_or(VolumeTarget::Master, VolumeTarget::Process)
+}
@@ -1,18 +1,27 @@
 use std::sync::Arc;
 
 use anyhow::Result;
-use windows::Media::Control::{
-    GlobalSystemMediaTransportControlsSession, GlobalSystemMediaTransportControlsSessionManager,
-};
 
+#[cfg(windows)]
+use crate::services::{capability::LocationService, notifications::NotificationService};
 use crate::{
+    AudioCommand, CommandId, Config, MediaCommand,
     bus::{EventReceiver, EventSender, create_bus},
+    commands::CommandQueue,
+    controls::AudioControls,
+    privacy::{self, ExportFormat, UsageInterval, UsageSummary, now_ms},
     runtime::RuntimeState,
     services::{
-        Service, audio::AudioSpectrumService, camera::CameraService, media::MediaService,
-        microphone::MicrophoneService, notifications::NotificationService,
+        Service,
+        appearance::AppearanceService,
+        audio::AudioSpectrumService,
+        capability::{CameraService, ScreenCaptureService},
+        lyrics::LyricsService,
+        media::MediaService,
+        microphone::MicrophoneService,
+        volume::VolumeService,
     },
-    utils::{artwork_dir, cache_dir, icons_dir},
+    utils::{artwork_dir, cache_dir, data_dir, icons_dir},
 };
 
 pub struct IslandCore {
@@ -20,6 +29,8 @@ pub struct IslandCore {
     rx: EventReceiver,
     runtime: Arc<RuntimeState>,
     executor: tokio::runtime::Runtime,
+    commands: CommandQueue,
+    controls: AudioControls,
 }
 
 impl IslandCore {
@@ -29,13 +40,14 @@ impl IslandCore {
         let _ = std::fs::create_dir_all(cache_dir());
         let _ = std::fs::create_dir_all(artwork_dir());
         let _ = std::fs::create_dir_all(icons_dir());
+        let _ = std::fs::create_dir_all(data_dir());
 
-        Self {
-            tx,
-            rx,
-            runtime: Arc::new(RuntimeState::new()),
-            executor: tokio::runtime::Runtime::new().unwrap(),
-        }
+        let runtime = Arc::new(RuntimeState::new(Config::load()));
+        let executor = tokio::runtime::Runtime::new().unwrap();
+        let commands = CommandQueue::start(executor.handle(), runtime.clone(), tx.clone());
+        let controls = AudioControls::start(executor.handle(), runtime.clone(), tx.clone());
+
+        Self { tx, rx, runtime, executor, commands, controls }
     }
 
     pub fn subscribe(&self) -> EventReceiver {
@@ -46,6 +58,10 @@ impl IslandCore {
         self.runtime.clone()
     }
 
+    pub fn config(&self) -> &Config {
+        &self.runtime.config
+    }
+
     pub fn sender(&self) -> EventSender {
         self.tx.clone()
     }
@@ -56,11 +72,30 @@ impl IslandCore {
 
         let handle = &self.executor.handle();
 
+        // services recording sensor use wait for this, so nothing is written in between
+        let pruned = runtime.clone();
+        std::thread::spawn(move || {
+            let log = pruned.privacy.lock().unwrap();
+            if let Err(e) =
+                log.prune(now_ms().saturating_sub(privacy::RETENTION.as_millis() as u64))
+            {
+                eprintln!("[Privacy] Failed to prune the log: {e}");
+            }
+        });
+
         run_service::<MediaService>(handle, tx.clone(), runtime.clone());
-        run_service::<NotificationService>(handle, tx.clone(), runtime.clone());
+        #[cfg(windows)]
+        {
+            run_service::<NotificationService>(handle, tx.clone(), runtime.clone());
+            run_service::<LocationService>(handle, tx.clone(), runtime.clone());
+        }
         run_service::<CameraService>(handle, tx.clone(), runtime.clone());
+        run_service::<ScreenCaptureService>(handle, tx.clone(), runtime.clone());
         run_service::<MicrophoneService>(handle, tx.clone(), runtime.clone());
         run_service::<AudioSpectrumService>(handle, tx.clone(), runtime.clone());
+        run_service::<VolumeService>(handle, tx.clone(), runtime.clone());
+        run_service::<AppearanceService>(handle, tx.clone(), runtime.clone());
+        run_service::<LyricsService>(handle, tx.clone(), runtime.clone());
     }
 
     pub fn dismiss_notification(&self, id: u64) {
@@ -69,38 +104,80 @@ impl IslandCore {
         let _ = self.tx.send(crate::CoreEvent::Arbitrary);
     }
 
-    async fn current_session(&self) -> Result<GlobalSystemMediaTransportControlsSession> {
-        let manager = GlobalSystemMediaTransportControlsSessionManager::RequestAsync()?.await?;
+    /// Stops new notifications from showing and clears the ones still waiting.
+    pub fn set_do_not_disturb(&self, enabled: bool) {
+        self.runtime.do_not_disturb.store(enabled, std::sync::atomic::Ordering::Relaxed);
+        if enabled {
+            self.runtime.notifications.lock().unwrap().clear();
+        }
+
+        let _ = self.tx.send(crate::CoreEvent::Arbitrary);
+    }
 
-        Ok(manager.GetCurrentSession()?)
+    pub fn do_not_disturb(&self) -> bool {
+        self.runtime.do_not_disturb.load(std::sync::atomic::Ordering::Relaxed)
     }
 
-    pub async fn toggle_playback(&self) -> Result<()> {
-        let session = self.current_session().await?;
-        session.TryTogglePlayPauseAsync()?.await?;
+    pub fn dismiss_volume(&self) {
+        self.runtime.volume_osd.store(false, std::sync::atomic::Ordering::Relaxed);
 
-        Ok(())
+        let _ = self.tx.send(crate::CoreEvent::Arbitrary);
     }
 
-    pub async fn next(&self) -> Result<()> {
-        let session = self.current_session().await?;
-        session.TrySkipNextAsync()?.await?;
+    pub fn set_visualizer_visible(&self, visible: bool) {
+        let previous =
+            self.runtime.visualizer_visible.swap(visible, std::sync::atomic::Ordering::Relaxed);
+        if previous != visible {
+            self.runtime.wake_analyzer();
+        }
+    }
+
+    pub fn reset_loudness(&self) {
+        self.runtime.loudness_reset.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Queues `command` for the audio controls. Commands run in order on the core runtime,
+    /// and report back with events; see [`AudioCommand`].
+    pub fn send_audio_command(&self, command: AudioCommand) {
+        self.controls.push(command);
+    }
+
+    /// Microphone and camera use that was still running at or after `since_ms` (Unix
+    /// milliseconds), oldest first. Reads the log file, so keep it off the UI thread.
+    pub fn privacy_log(&self, since_ms: u64) -> Result<Vec<UsageInterval>> {
+        let log = self.runtime.privacy.lock().unwrap().clone();
+        log.intervals(since_ms)
+    }
+
+    /// Reads the log file, so keep it off the UI thread.
+    pub fn privacy_report(&self, since_ms: u64) -> Result<Vec<UsageSummary>> {
+        let log = self.runtime.privacy.lock().unwrap().clone();
+        log.report(since_ms, now_ms())
+    }
 
-        Ok(())
+    pub fn export_privacy_log(&self, since_ms: u64, format: ExportFormat) -> Result<String> {
+        privacy::export(&self.privacy_log(since_ms)?, format)
     }
 
-    pub async fn previous(&self) -> Result<()> {
-        let session = self.current_session().await?;
-        session.TrySkipPreviousAsync()?.await?;
+    /// Changes whenever an app starts or stops using a sensor.
+    pub fn privacy_revision(&self) -> u64 {
+        self.runtime.privacy.lock().unwrap().revision()
+    }
 
-        Ok(())
+    /// Queues `command` for the media player. Commands run one at a time on the core
+    /// runtime, and each reports how it went with a `CoreEvent::CommandFinished`.
+    pub fn send_media_command(&self, command: MediaCommand) -> CommandId {
+        self.commands.push(command)
     }
 
-    pub async fn seek(&self, position_ms: u64) -> Result<()> {
-        let session = self.current_session().await?;
-        session.TryChangePlaybackPositionAsync((position_ms * 10_000) as i64)?.await?;
+    pub fn cancel_command(&self, id: CommandId) {
+        self.commands.cancel(id);
+    }
+}
 
-        Ok(())
+impl Default for IslandCore {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
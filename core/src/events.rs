@@ -1,4 +1,6 @@
-use std::time::SystemTime;
+use std::{sync::Arc, time::SystemTime};
+
+use crate::{AudioCommand, CommandError, CommandId, MediaCommand, Palette};
 
 #[derive(Debug, Clone)]
 pub enum CoreEvent {
@@ -8,12 +10,35 @@ pub enum CoreEvent {
 
     NotificationReceived(NotificationState),
 
-    MicrophoneActive,
+    MicrophoneActive(Vec<AppUsage>),
     MicrophoneInactive,
+    MicrophoneMuted(bool),
 
-    CameraActive,
+    CameraActive(Vec<AppUsage>),
     CameraInactive,
 
+    ScreenCaptureActive(Vec<AppUsage>),
+    ScreenCaptureInactive,
+
+    LocationActive(Vec<AppUsage>),
+    LocationInactive,
+
+    VolumeChanged { level: f32, muted: bool, device: String },
+    OutputDeviceChanged { device: String },
+
+    // the OS switched between dark and light mode, or the theme file was edited
+    ThemeChanged,
+    // the track changed and came with lyrics, or the last one had them
+    LyricsChanged,
+
+    // a media command finished, one way or another
+    CommandFinished { id: CommandId, command: MediaCommand, result: Result<(), CommandError> },
+
+    // what the volume commands act on, read back after one ran
+    ControlledVolume(VolumeState),
+    OutputDevices(Vec<OutputDevice>),
+    AudioCommandFailed { command: AudioCommand, error: String },
+
     Arbitrary,
 }
 
@@ -28,10 +53,83 @@ pub struct NotificationState {
     pub body: String,
 }
 
+/// An app currently using the microphone, camera, screen capture or location.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppUsage {
+    /// 0 when the platform only reports the app, not the process (packaged apps on Windows).
+    pub pid: u32,
+    /// Empty for packaged apps.
+    pub exe_path: String,
+
+    pub name: String,
+    pub icon: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChannelLevels {
+    pub rms: f32,
+    pub peak: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioLevels {
+    pub left: ChannelLevels,
+    pub right: ChannelLevels,
+}
+
+/// Loudness in LUFS and true-peak in dBTP; `f32::NEG_INFINITY` until enough audio was measured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessState {
+    pub momentary: f32,
+    pub short_term: f32,
+    pub integrated: f32,
+    pub true_peak: f32,
+}
+
+impl Default for LoudnessState {
+    fn default() -> Self {
+        Self {
+            momentary: f32::NEG_INFINITY,
+            short_term: f32::NEG_INFINITY,
+            integrated: f32::NEG_INFINITY,
+            true_peak: f32::NEG_INFINITY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VolumeState {
+    /// Master volume of the default output device, `0.0..=1.0`.
+    pub level: f32,
+    pub muted: bool,
+    /// Display name of the default output device.
+    pub device: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputDevice {
+    /// Platform identifier, passed back in `AudioCommand::SetOutputDevice`.
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnalyzerState {
+    /// Capture is stopped: nothing is playing or the visualizer isn't on screen.
+    #[default]
+    Suspended,
+    /// Capturing, but only polling slowly because the output has been silent.
+    Idle,
+    Active,
+}
+
 #[derive(Debug, Clone)]
 pub struct MediaState {
     pub synced_at: SystemTime,
 
+    /// Platform identifier of the player (the AUMID on Windows).
+    pub app_id: String,
     pub app_name: String,
 
     pub title: String,
@@ -39,16 +137,93 @@ pub struct MediaState {
     pub album: String,
 
     pub album_art: Option<String>,
+    /// Colors picked from `album_art`.
+    pub palette: Option<Palette>,
 
     pub duration_ms: u64,
     pub position_ms: u64,
 
     pub playing: bool,
+    /// `None` when the player doesn't say, which usually means it has no such setting.
+    pub shuffle: Option<bool>,
+    pub repeat: Option<RepeatMode>,
+    /// How fast the track plays, 1 being normal speed.
+    pub rate: Option<f64>,
+    pub capabilities: MediaCapabilities,
+    /// Lyrics the player hands over with the track, as LRC when they're synced.
+    pub lyrics: Option<Arc<str>>,
 
     pub app_icon: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    Track,
+    List,
+}
+
+impl RepeatMode {
+    /// The mode a click on the repeat button goes to: off, then the whole list, then just
+    /// the track.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::List,
+            Self::List => Self::Track,
+            Self::Track => Self::Off,
+        }
+    }
+}
+
+/// The speeds the speed button steps through.
+const RATES: [f64; 6] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0];
+
+/// Which controls the player takes right now. Players turn some off along the way, like
+/// skipping during an ad.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaCapabilities {
+    pub play_pause: bool,
+    pub next: bool,
+    pub previous: bool,
+    pub seek: bool,
+    pub shuffle: bool,
+    pub repeat: bool,
+    pub rate: bool,
+    /// The slowest and fastest the player plays. `None` when it doesn't say, which the
+    /// Windows media session never does.
+    pub rate_range: Option<(f64, f64)>,
+}
+
+impl Default for MediaCapabilities {
+    /// Everything, for players that don't say.
+    fn default() -> Self {
+        Self {
+            play_pause: true,
+            next: true,
+            previous: true,
+            seek: true,
+            shuffle: true,
+            repeat: true,
+            rate: true,
+            rate_range: None,
+        }
+    }
+}
+
 impl MediaState {
+    /// The speed a click on the speed button goes to: the next of a few common ones the
+    /// player takes, then back to the slowest.
+    pub fn next_rate(&self) -> Option<f64> {
+        let rate = self.rate?;
+        let (slowest, fastest) = self.capabilities.rate_range.unwrap_or((0.0, f64::INFINITY));
+        let mut rates =
+            RATES.into_iter().filter(|&r| r > slowest - 0.01 && r < fastest + 0.01).peekable();
+
+        let first = rates.peek().copied();
+        rates.find(|&r| r > rate + 0.01).or(first)
+    }
+
     pub fn current_position_ms(&self) -> u64 {
         if !self.playing {
             return self.position_ms;
@@ -56,7 +231,8 @@ impl MediaState {
 
         match SystemTime::now().duration_since(self.synced_at) {
             Ok(elapsed) => {
-                let local_elapsed_ms = elapsed.as_millis() as u64;
+                let local_elapsed_ms =
+                    (elapsed.as_millis() as f64 * self.rate.unwrap_or(1.0)) as u64;
                 (self.position_ms + local_elapsed_ms).min(self.duration_ms)
             }
             Err(_) => self.position_ms,
@@ -66,18 +242,63 @@ impl MediaState {
 
 impl PartialEq for MediaState {
     fn eq(&self, other: &Self) -> bool {
-        self.app_name == other.app_name
+        self.app_id == other.app_id
+            && self.app_name == other.app_name
             && self.title == other.title
             && self.artist == other.artist
             && self.album == other.album
             && self.album_art == other.album_art
             && self.duration_ms == other.duration_ms
             && self.playing == other.playing
+            && self.shuffle == other.shuffle
+            && self.repeat == other.repeat
+            && self.rate == other.rate
+            && self.capabilities == other.capabilities
+            && self.lyrics == other.lyrics
             && self.app_icon == other.app_icon
             && self.position_ms.abs_diff(other.position_ms) < 15
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playing_at(rate: Option<f64>, rate_range: Option<(f64, f64)>) -> MediaState {
+        MediaState {
+            synced_at: SystemTime::now(),
+            app_id: "player".into(),
+            app_name: "Player".into(),
+            title: "Midnight City".into(),
+            artist: "M83".into(),
+            album: String::new(),
+            album_art: None,
+            palette: None,
+            duration_ms: 240_000,
+            position_ms: 0,
+            playing: true,
+            shuffle: None,
+            repeat: None,
+            rate,
+            capabilities: MediaCapabilities { rate_range, ..Default::default() },
+            lyrics: None,
+            app_icon: None,
+        }
+    }
+
+    #[test]
+    fn the_speed_steps_through_what_the_player_takes() {
+        assert_eq!(playing_at(Some(1.0), None).next_rate(), Some(1.25));
+        assert_eq!(playing_at(Some(2.0), None).next_rate(), Some(0.5));
+        // a speed set elsewhere goes on to the next common one
+        assert_eq!(playing_at(Some(1.1), None).next_rate(), Some(1.25));
+
+        let range = Some((0.75, 1.5));
+        assert_eq!(playing_at(Some(1.25), range).next_rate(), Some(1.5));
+        assert_eq!(playing_at(Some(1.5), range).next_rate(), Some(0.75));
 
-    fn ne(&self, other: &Self) -> bool {
-        !self.eq(other)
+        assert_eq!(playing_at(Some(1.0), Some((1.0, 1.0))).next_rate(), Some(1.0));
+        assert_eq!(playing_at(Some(1.0), Some((2.5, 4.0))).next_rate(), None);
+        assert_eq!(playing_at(None, None).next_rate(), None);
     }
 }
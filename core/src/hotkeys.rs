@@ -0,0 +1,294 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::IslandAction;
+
+/// Global hotkeys, each a combination like `Ctrl+Alt+Space`, or `none` to leave it unbound.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Hotkeys {
+    #[serde(deserialize_with = "binding")]
+    pub expand: Option<Hotkey>,
+    #[serde(deserialize_with = "binding")]
+    pub toggle_playback: Option<Hotkey>,
+    #[serde(deserialize_with = "binding")]
+    pub next: Option<Hotkey>,
+    #[serde(deserialize_with = "binding")]
+    pub previous: Option<Hotkey>,
+    #[serde(deserialize_with = "binding")]
+    pub dismiss_notification: Option<Hotkey>,
+    #[serde(deserialize_with = "binding")]
+    pub do_not_disturb: Option<Hotkey>,
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        let ctrl_alt = |key| Some(Hotkey { modifiers: Modifiers::CTRL_ALT, key });
+
+        Self {
+            expand: ctrl_alt(Key::Space),
+            toggle_playback: ctrl_alt(Key::Letter('P')),
+            next: ctrl_alt(Key::Right),
+            previous: ctrl_alt(Key::Left),
+            dismiss_notification: ctrl_alt(Key::Letter('D')),
+            do_not_disturb: ctrl_alt(Key::Letter('N')),
+        }
+    }
+}
+
+impl Hotkeys {
+    pub fn bindings(&self) -> Vec<(Hotkey, IslandAction)> {
+        [
+            (self.expand, IslandAction::ToggleExpanded),
+            (self.toggle_playback, IslandAction::TogglePlayback),
+            (self.next, IslandAction::Next),
+            (self.previous, IslandAction::Previous),
+            (self.dismiss_notification, IslandAction::DismissNotification(None)),
+            (self.do_not_disturb, IslandAction::ToggleDoNotDisturb),
+        ]
+        .into_iter()
+        .filter_map(|(hotkey, action)| Some((hotkey?, action)))
+        .collect()
+    }
+}
+
+fn binding<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Hotkey>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    if value.is_empty() || value.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+
+    value.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// The Windows or Command key.
+    pub logo: bool,
+}
+
+impl Modifiers {
+    const CTRL_ALT: Modifiers = Modifiers { ctrl: true, alt: true, shift: false, logo: false };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// `A` to `Z`.
+    Letter(char),
+    /// `0` to `9`.
+    Digit(u8),
+    /// `F1` to `F24`.
+    F(u8),
+    Space,
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Left,
+    Right,
+    Up,
+    Down,
+    PlayPause,
+    NextTrack,
+    PreviousTrack,
+}
+
+const NAMED_KEYS: [(&str, Key); 18] = [
+    ("Space", Key::Space),
+    ("Enter", Key::Enter),
+    ("Escape", Key::Escape),
+    ("Tab", Key::Tab),
+    ("Backspace", Key::Backspace),
+    ("Insert", Key::Insert),
+    ("Delete", Key::Delete),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("PlayPause", Key::PlayPause),
+    ("NextTrack", Key::NextTrack),
+    ("PreviousTrack", Key::PreviousTrack),
+];
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some((_, key)) = NAMED_KEYS.iter().find(|(name, _)| name.eq_ignore_ascii_case(s)) {
+            return Ok(*key);
+        }
+
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_ascii_alphabetic() {
+                return Ok(Key::Letter(c.to_ascii_uppercase()));
+            }
+            if let Some(digit) = c.to_digit(10) {
+                return Ok(Key::Digit(digit as u8));
+            }
+        }
+
+        if let Some(n) = s.strip_prefix(['F', 'f']).and_then(|n| n.parse::<u8>().ok())
+            && (1..=24).contains(&n)
+        {
+            return Ok(Key::F(n));
+        }
+
+        bail!("unknown key `{s}`")
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Letter(c) => write!(f, "{c}"),
+            Key::Digit(d) => write!(f, "{d}"),
+            Key::F(n) => write!(f, "F{n}"),
+            key => {
+                let (name, _) = NAMED_KEYS.iter().find(|(_, named)| named == key).unwrap();
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+/// A key pressed together with some modifiers, written like `Ctrl+Shift+F5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Hotkey {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl FromStr for Hotkey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        // `Ctrl++` binds the plus key's spot, which isn't a key we know
+        let key = parts.pop().filter(|key| !key.is_empty()).ok_or_else(|| anyhow!("no key"))?;
+
+        let mut modifiers = Modifiers::default();
+        for part in parts {
+            let modifier = match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => &mut modifiers.ctrl,
+                "alt" | "option" => &mut modifiers.alt,
+                "shift" => &mut modifiers.shift,
+                "super" | "win" | "meta" | "cmd" | "logo" => &mut modifiers.logo,
+                _ => bail!("unknown modifier `{part}` in `{s}`"),
+            };
+            *modifier = true;
+        }
+
+        Ok(Self { modifiers, key: key.parse()? })
+    }
+}
+
+impl TryFrom<String> for Hotkey {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Modifiers { ctrl, alt, shift, logo } = self.modifiers;
+
+        for (held, name) in [(ctrl, "Ctrl"), (alt, "Alt"), (shift, "Shift"), (logo, "Super")] {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl From<Hotkey> for String {
+    fn from(value: Hotkey) -> Self {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hotkey(modifiers: Modifiers, key: Key) -> Hotkey {
+        Hotkey { modifiers, key }
+    }
+
+    #[test]
+    fn modifiers_take_any_case_and_their_other_names() {
+        let ctrl_shift = Modifiers { ctrl: true, shift: true, ..Default::default() };
+
+        assert_eq!("Ctrl+Shift+F5".parse::<Hotkey>().unwrap(), hotkey(ctrl_shift, Key::F(5)));
+        assert_eq!(
+            " control + SHIFT + f5 ".parse::<Hotkey>().unwrap(),
+            hotkey(ctrl_shift, Key::F(5))
+        );
+        assert_eq!(
+            "Win+Option+x".parse::<Hotkey>().unwrap(),
+            hotkey(Modifiers { logo: true, alt: true, ..Default::default() }, Key::Letter('X'))
+        );
+        assert_eq!("Space".parse::<Hotkey>().unwrap(), hotkey(Modifiers::default(), Key::Space));
+    }
+
+    #[test]
+    fn keys_are_letters_digits_function_keys_or_named() {
+        assert_eq!("q".parse::<Key>().unwrap(), Key::Letter('Q'));
+        assert_eq!("7".parse::<Key>().unwrap(), Key::Digit(7));
+        assert_eq!("F1".parse::<Key>().unwrap(), Key::F(1));
+        assert_eq!("f24".parse::<Key>().unwrap(), Key::F(24));
+        assert_eq!("pageup".parse::<Key>().unwrap(), Key::PageUp);
+        assert_eq!("PlayPause".parse::<Key>().unwrap(), Key::PlayPause);
+
+        for unknown in ["F0", "F25", "é", "Plus", "", "ab"] {
+            assert!(unknown.parse::<Key>().is_err(), "{unknown}");
+        }
+    }
+
+    #[test]
+    fn malformed_combinations_are_rejected() {
+        for malformed in ["", "Ctrl+", "Ctrl++", "Hyper+A", "Ctrl+Alt+Nope"] {
+            assert!(malformed.parse::<Hotkey>().is_err(), "{malformed}");
+        }
+    }
+
+    #[test]
+    fn hotkeys_are_written_the_way_they_are_read() {
+        for written in ["Ctrl+Alt+Space", "Ctrl+Shift+F12", "Super+PageDown", "Alt+0", "NextTrack"]
+        {
+            assert_eq!(written.parse::<Hotkey>().unwrap().to_string(), written);
+        }
+        assert_eq!("shift+cmd+ctrl+a".parse::<Hotkey>().unwrap().to_string(), "Ctrl+Shift+Super+A");
+    }
+
+    #[test]
+    fn bindings_can_be_left_out_or_turned_off() {
+        let hotkeys: Hotkeys =
+            toml::from_str("next = 'none'\nprevious = ''\nexpand = 'Alt+E'").unwrap();
+
+        assert_eq!(hotkeys.next, None);
+        assert_eq!(hotkeys.previous, None);
+        assert_eq!(hotkeys.expand, Some("Alt+E".parse().unwrap()));
+        assert_eq!(hotkeys.toggle_playback, Hotkeys::default().toggle_playback);
+        assert!(toml::from_str::<Hotkeys>("next = 'Ctrl+Nope'").is_err());
+    }
+}
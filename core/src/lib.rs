@@ -1,11 +1,43 @@
+mod actions;
 mod bus;
+mod commands;
+mod config;
+mod controls;
 mod core;
 mod events;
+mod hotkeys;
+mod lyrics;
+mod privacy;
 mod runtime;
 mod services;
+mod theme;
 mod utils;
 
+pub use actions::IslandAction;
+pub use commands::{CommandError, CommandId, MediaCommand};
+pub use config::{Anchor, Config, MonitorPlacement, Offset, ThemeSelection, VisualizerMode};
+pub use controls::AudioCommand;
 pub use core::IslandCore;
 pub use events::*;
+pub use hotkeys::{Hotkey, Hotkeys, Key, Modifiers};
+pub use lyrics::{LyricLine, LyricWord, Lyrics, LyricsIndex, find_lyrics};
+pub use privacy::{
+    ExportFormat, PrivacyLog, Sensor, UsageInterval, UsageSummary, export, start_of_today_ms,
+};
 pub use runtime::RuntimeState;
-pub use utils::{artwork_dir, cache_dir, icons_dir};
+pub use services::audio::{
+    AnalyzerScheduler, AudioSource, OfflineSource, SourceFormat, SpectrumAnalyzer, run_analyzer,
+};
+#[cfg(target_os = "linux")]
+pub use services::{
+    capability::{camera_processes, screencast_processes},
+    microphone::mic_processes,
+};
+pub use theme::{
+    Curve, Rgba, Theme, ThemeColors, ThemeEffects, ThemeMetrics, ThemeMotion, system_prefers_dark,
+};
+pub use utils::{
+    artwork_dir, cache_dir, config_dir, data_dir, icons_dir,
+    palette::{Palette, extract_palette},
+    privacy_log_path, themes_dir,
+};
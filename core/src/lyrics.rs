@@ -0,0 +1,564 @@
+use std::{
+    cell::OnceCell,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// How many folders deep `find_lyrics` looks, for libraries sorted like
+/// `Artist/Album/01 - Title.lrc`.
+const MAX_DEPTH: usize = 3;
+
+/// Time-synced lyrics, from an LRC file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lyrics {
+    /// From the `[ti:]` and `[ar:]` tags.
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// In the order they're sung.
+    pub lines: Vec<LyricLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyricLine {
+    pub start_ms: u64,
+    /// Empty for the gaps between verses.
+    pub text: String,
+    /// Each word's timing, for enhanced LRC. The words put together make up `text`.
+    pub words: Vec<LyricWord>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyricWord {
+    pub start_ms: u64,
+    /// With the space after it, if any.
+    pub text: String,
+}
+
+impl Lyrics {
+    /// Reads LRC and enhanced LRC. Lines without a timestamp and tags it doesn't know are
+    /// skipped, so a malformed file gives fewer lines rather than none.
+    pub fn parse(source: &str) -> Self {
+        let mut lyrics = Lyrics::default();
+        let mut offset_ms = 0i64;
+
+        for line in source.lines() {
+            let mut rest = line.trim();
+            let mut stamps = Vec::new();
+
+            while let Some(tag) = rest.strip_prefix('[')
+                && let Some((tag, after)) = tag.split_once(']')
+            {
+                rest = after;
+
+                if let Some(ms) = parse_timestamp(tag) {
+                    stamps.push(ms);
+                    continue;
+                }
+
+                if let Some((key, value)) = tag.split_once(':') {
+                    let value = value.trim();
+                    match key.trim().to_ascii_lowercase().as_str() {
+                        "ti" => lyrics.title = Some(value.to_string()),
+                        "ar" => lyrics.artist = Some(value.to_string()),
+                        "offset" => offset_ms = value.parse().unwrap_or(0),
+                        _ => {}
+                    }
+                }
+                break;
+            }
+
+            // a line sung more than once can carry a timestamp for each time
+            let words = if stamps.len() == 1 { parse_words(rest, stamps[0]) } else { Vec::new() };
+            let text = if words.is_empty() {
+                rest.trim().to_string()
+            } else {
+                words.iter().map(|word| word.text.as_str()).collect()
+            };
+
+            for start_ms in stamps {
+                lyrics.lines.push(LyricLine { start_ms, text: text.clone(), words: words.clone() });
+            }
+        }
+
+        // a positive offset shows the lyrics sooner
+        if offset_ms != 0 {
+            let shift = |ms: &mut u64| *ms = ms.saturating_add_signed(offset_ms.saturating_neg());
+            for line in &mut lyrics.lines {
+                shift(&mut line.start_ms);
+                line.words.iter_mut().for_each(|word| shift(&mut word.start_ms));
+            }
+        }
+
+        lyrics.lines.sort_by_key(|line| line.start_ms);
+        lyrics
+    }
+
+    /// The line being sung at `position_ms`, `None` before the first one.
+    pub fn line_at(&self, position_ms: u64) -> Option<usize> {
+        self.lines.partition_point(|line| line.start_ms <= position_ms).checked_sub(1)
+    }
+}
+
+impl LyricLine {
+    /// The part of the line already sung at `position_ms`, all of it for lines without
+    /// word timings.
+    pub fn sung(&self, position_ms: u64) -> &str {
+        if self.words.is_empty() {
+            return &self.text;
+        }
+
+        let len = self
+            .words
+            .iter()
+            .take_while(|word| word.start_ms <= position_ms)
+            .map(|word| word.text.len())
+            .sum::<usize>();
+        &self.text[..len.min(self.text.len())]
+    }
+}
+
+/// `mm:ss`, with optional hundredths (or tenths, or milliseconds) after a `.` or `:`.
+fn parse_timestamp(stamp: &str) -> Option<u64> {
+    let (minutes, rest) = stamp.trim().split_once(':')?;
+    let (seconds, fraction) = rest.split_once(['.', ':']).unwrap_or((rest, ""));
+
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !all_digits(minutes)
+        || !all_digits(seconds)
+        || !(fraction.is_empty() || all_digits(fraction))
+    {
+        return None;
+    }
+
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: u64 = seconds.parse().ok()?;
+    let millis = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<u64>().ok()? * 100,
+        2 => fraction.parse::<u64>().ok()? * 10,
+        _ => fraction[..3].parse().ok()?,
+    };
+
+    // a timestamp too far out to be real is junk, not a line that never comes
+    minutes.checked_mul(60)?.checked_add(seconds)?.checked_mul(1000)?.checked_add(millis)
+}
+
+/// Splits `<mm:ss.xx>word <mm:ss.xx>word` into words. Text before the first word
+/// timestamp starts with the line.
+fn parse_words(text: &str, line_start_ms: u64) -> Vec<LyricWord> {
+    if !text.contains('<') {
+        return Vec::new();
+    }
+
+    let mut words = Vec::new();
+    let mut start_ms = line_start_ms;
+    let mut timed = false;
+    let mut rest = text.trim_start();
+
+    loop {
+        let (word, next) = match rest.find('<') {
+            Some(open) => rest.split_at(open),
+            None => (rest, ""),
+        };
+
+        if !word.is_empty() {
+            words.push(LyricWord { start_ms, text: word.to_string() });
+        }
+
+        let Some(stamp) = next.strip_prefix('<') else {
+            break;
+        };
+        let Some((stamp, after)) = stamp.split_once('>') else {
+            // a stray `<` is part of the words
+            if let Some(last) = words.last_mut() {
+                last.text.push_str(next);
+            } else {
+                words.push(LyricWord { start_ms, text: next.to_string() });
+            }
+            break;
+        };
+
+        match parse_timestamp(stamp) {
+            Some(ms) => {
+                start_ms = ms;
+                timed = true;
+            }
+            None => words.push(LyricWord { start_ms, text: format!("<{stamp}>") }),
+        }
+        rest = after;
+    }
+
+    // angle brackets that were never timestamps leave the line as it was
+    if !timed {
+        return Vec::new();
+    }
+
+    // a timestamp at the very end only marks when the last word stops
+    if let Some(last) = words.last_mut() {
+        last.text.truncate(last.text.trim_end().len());
+    }
+    words.retain(|word| !word.text.is_empty());
+    words
+}
+
+/// The `.lrc` file in `dir` for a track. File names like `Artist - Title.lrc`, or
+/// `Title.lrc` inside a folder named after the artist, are checked first; then the `[ar:]`
+/// and `[ti:]` tags inside the files.
+pub fn find_lyrics(dir: &Path, artist: &str, title: &str) -> Option<PathBuf> {
+    LyricsIndex::build(dir).find(artist, title).map(Path::to_path_buf)
+}
+
+/// The `.lrc` files under a folder, so looking up a track doesn't walk it again. Their
+/// tags are read the first time a lookup by name comes up empty.
+#[derive(Debug, Default)]
+pub struct LyricsIndex {
+    /// Every folder walked, with when it last changed. Adding, removing or renaming a file
+    /// changes its folder's.
+    dirs: Vec<(PathBuf, Option<SystemTime>)>,
+    files: Vec<LrcFile>,
+}
+
+#[derive(Debug)]
+struct LrcFile {
+    /// The path from the root without the extension, folders joined with ` - `.
+    name: String,
+    path: PathBuf,
+    /// `[ti:]` and `[ar:]`.
+    tags: OnceCell<(Option<String>, Option<String>)>,
+}
+
+impl LyricsIndex {
+    pub fn build(dir: &Path) -> Self {
+        let mut index = Self::default();
+        index.collect(dir, dir, 0);
+        index
+    }
+
+    /// Whether files were added, removed or renamed since the index was built.
+    pub fn is_stale(&self) -> bool {
+        self.dirs.iter().any(|(dir, modified)| modified_time(dir) != *modified)
+    }
+
+    /// See [`find_lyrics`].
+    pub fn find(&self, artist: &str, title: &str) -> Option<&Path> {
+        let by_name = self
+            .files
+            .iter()
+            .filter_map(|file| Some((match_score(&file.name, artist, title)?, file)))
+            .max_by_key(|(score, _)| *score);
+        if let Some((_, file)) = by_name {
+            return Some(&file.path);
+        }
+
+        let by_tags = self.files.iter().find(|file| {
+            let (ti, ar) = file.tags.get_or_init(|| {
+                let source = std::fs::read_to_string(&file.path).unwrap_or_default();
+                let lyrics = Lyrics::parse(&source);
+                (lyrics.title, lyrics.artist)
+            });
+
+            ti.as_deref().is_some_and(|ti| same_title(ti, title))
+                && ar.as_deref().is_none_or(|ar| normalize(ar) == normalize(artist))
+        });
+        by_tags.map(|file| file.path.as_path())
+    }
+
+    /// Adds every `.lrc` file under `dir`.
+    fn collect(&mut self, root: &Path, dir: &Path, depth: usize) {
+        // a missing folder is watched too, for when it's created
+        self.dirs.push((dir.to_path_buf(), modified_time(dir)));
+
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path.is_dir() {
+                if depth < MAX_DEPTH {
+                    self.collect(root, &path, depth + 1);
+                }
+            } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("lrc")) {
+                let name = path.strip_prefix(root).unwrap_or(&path).with_extension("");
+                let name = name.iter().map(|part| part.to_string_lossy()).collect::<Vec<_>>();
+                self.files.push(LrcFile { name: name.join(" - "), path, tags: OnceCell::new() });
+            }
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// How well a file named `name` fits the track, `None` when it doesn't.
+fn match_score(name: &str, artist: &str, title: &str) -> Option<u8> {
+    let name = normalize(name);
+    let artist = normalize(artist);
+
+    [normalize(title), normalize(&bare_title(title))]
+        .into_iter()
+        .filter(|title| !title.is_empty())
+        .filter_map(|title| {
+            let has_artist = !artist.is_empty();
+
+            if has_artist
+                && (name == format!("{artist} {title}") || name == format!("{title} {artist}"))
+            {
+                Some(3)
+            } else if has_artist
+                && name.starts_with(&format!("{artist} "))
+                && ends_with_words(&name, &title)
+            {
+                // the album and track number sit between them
+                Some(2)
+            } else if name == title || without_track_number(&name) == title {
+                Some(1)
+            } else {
+                None
+            }
+        })
+        .max()
+}
+
+fn same_title(a: &str, b: &str) -> bool {
+    normalize(a) == normalize(b) || normalize(&bare_title(a)) == normalize(&bare_title(b))
+}
+
+/// Lowercase words of letters and digits, so punctuation and spacing don't matter.
+fn normalize(s: &str) -> String {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The title without what's tacked on in brackets or after a dash, like
+/// `(Remastered 2011)` or `- Live`.
+fn bare_title(title: &str) -> String {
+    let mut bare = String::new();
+    let mut depth = 0usize;
+
+    for c in title.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => bare.push(c),
+            _ => {}
+        }
+    }
+
+    match bare.split_once(" - ") {
+        Some((bare, _)) => bare.to_string(),
+        None => bare,
+    }
+}
+
+fn ends_with_words(name: &str, words: &str) -> bool {
+    name == words || name.ends_with(&format!(" {words}"))
+}
+
+/// `01 title` → `title`.
+fn without_track_number(name: &str) -> &str {
+    match name.split_once(' ') {
+        Some((number, rest)) if number.bytes().all(|b| b.is_ascii_digit()) => rest,
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(start_ms: u64, text: &str) -> LyricLine {
+        LyricLine { start_ms, text: text.into(), words: Vec::new() }
+    }
+
+    fn word(start_ms: u64, text: &str) -> LyricWord {
+        LyricWord { start_ms, text: text.into() }
+    }
+
+    #[test]
+    fn timestamps_take_any_precision() {
+        assert_eq!(parse_timestamp("01:02"), Some(62_000));
+        assert_eq!(parse_timestamp("01:02.5"), Some(62_500));
+        assert_eq!(parse_timestamp("01:02.50"), Some(62_500));
+        assert_eq!(parse_timestamp("01:02.505"), Some(62_505));
+        assert_eq!(parse_timestamp("01:02:50"), Some(62_500));
+        assert_eq!(parse_timestamp("75:00.00"), Some(4_500_000));
+        assert_eq!(parse_timestamp("ar:M83"), None);
+        assert_eq!(parse_timestamp("1:x"), None);
+        assert_eq!(parse_timestamp(""), None);
+        assert_eq!(parse_timestamp("307445734561825860:00"), None);
+        assert_eq!(parse_timestamp("99999999999999999999:00"), None);
+    }
+
+    #[test]
+    fn lines_are_parsed_in_the_order_they_are_sung() {
+        let lyrics = Lyrics::parse(
+            "[ti:Midnight City]\n\
+             [ar:M83]\n\
+             [al:Hurry Up, We're Dreaming]\n\
+             \n\
+             [00:12.00]Waiting in a car\n\
+             [00:15.30][01:10.00]Waiting for a ride in the dark\n\
+             [00:20.00]\n\
+             not a lyric\n",
+        );
+
+        assert_eq!(lyrics.title.as_deref(), Some("Midnight City"));
+        assert_eq!(lyrics.artist.as_deref(), Some("M83"));
+        assert_eq!(
+            lyrics.lines,
+            [
+                line(12_000, "Waiting in a car"),
+                line(15_300, "Waiting for a ride in the dark"),
+                line(20_000, ""),
+                line(70_000, "Waiting for a ride in the dark"),
+            ]
+        );
+    }
+
+    #[test]
+    fn offsets_shift_every_line() {
+        let sooner = Lyrics::parse("[offset:+500]\n[00:01.00]a\n[00:00.20]b");
+        assert_eq!(sooner.lines, [line(0, "b"), line(500, "a")]);
+
+        let later = Lyrics::parse("[offset:-250]\n[00:01.00]a");
+        assert_eq!(later.lines, [line(1250, "a")]);
+
+        let furthest = Lyrics::parse("[offset:-9223372036854775808]\n[00:01.00]a");
+        assert_eq!(furthest.lines, [line(1000 + i64::MAX as u64, "a")]);
+    }
+
+    #[test]
+    fn enhanced_lrc_times_each_word() {
+        let lyrics =
+            Lyrics::parse("[00:12.00]<00:12.00>Waiting <00:12.40>in a <00:13.10>car<00:14.00>");
+        let line = &lyrics.lines[0];
+
+        assert_eq!(line.text, "Waiting in a car");
+        assert_eq!(
+            line.words,
+            [word(12_000, "Waiting "), word(12_400, "in a "), word(13_100, "car")]
+        );
+
+        assert_eq!(line.sung(11_000), "");
+        assert_eq!(line.sung(12_500), "Waiting in a ");
+        assert_eq!(line.sung(20_000), "Waiting in a car");
+    }
+
+    #[test]
+    fn words_before_the_first_stamp_start_with_the_line() {
+        let lyrics = Lyrics::parse("[00:05.00]Oh <00:06.00>yeah");
+
+        assert_eq!(lyrics.lines[0].text, "Oh yeah");
+        assert_eq!(lyrics.lines[0].words, [word(5_000, "Oh "), word(6_000, "yeah")]);
+    }
+
+    #[test]
+    fn plain_lines_are_sung_all_at_once() {
+        let lyrics = Lyrics::parse("[00:05.00]Some <b>bold</b> words");
+
+        assert_eq!(lyrics.lines[0].text, "Some <b>bold</b> words");
+        assert_eq!(lyrics.lines[0].sung(5_000), "Some <b>bold</b> words");
+    }
+
+    #[test]
+    fn the_current_line_follows_the_position() {
+        let lyrics = Lyrics::parse("[00:01.00]a\n[00:03.00]b\n[00:03.00]c\n[00:05.00]d");
+
+        assert_eq!(lyrics.line_at(0), None);
+        assert_eq!(lyrics.line_at(1_000), Some(0));
+        assert_eq!(lyrics.line_at(2_999), Some(0));
+        assert_eq!(lyrics.line_at(3_000), Some(2));
+        assert_eq!(lyrics.line_at(60_000), Some(3));
+        assert_eq!(Lyrics::default().line_at(1_000), None);
+    }
+
+    #[test]
+    fn file_names_match_by_artist_and_title() {
+        let score = |name| match_score(name, "M83", "Midnight City");
+
+        assert_eq!(score("M83 - Midnight City"), Some(3));
+        assert_eq!(score("m83_midnight-city"), Some(3));
+        assert_eq!(score("Midnight City - M83"), Some(3));
+        assert_eq!(score("M83 - Hurry Up, We're Dreaming - 02 Midnight City"), Some(2));
+        assert_eq!(score("Midnight City"), Some(1));
+        assert_eq!(score("02. Midnight City"), Some(1));
+
+        assert_eq!(score("M83 - Wait"), None);
+        assert_eq!(score("Someone Else - Midnight City Remix"), None);
+        assert_eq!(match_score("Midnight City", "M83", ""), None);
+    }
+
+    #[test]
+    fn tacked_on_versions_still_match() {
+        assert_eq!(
+            match_score(
+                "Queen - Bohemian Rhapsody",
+                "Queen",
+                "Bohemian Rhapsody (Remastered 2011)"
+            ),
+            Some(3)
+        );
+        assert_eq!(
+            match_score("Queen - Bohemian Rhapsody", "Queen", "Bohemian Rhapsody - Live"),
+            Some(3)
+        );
+        assert_eq!(normalize(&bare_title("Song [Radio Edit] (feat. Someone)")), "song");
+    }
+
+    #[test]
+    fn lyrics_are_found_by_file_name_then_by_tags() {
+        let dir = std::env::temp_dir().join(format!("lumen-lyrics-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("M83")).unwrap();
+
+        std::fs::write(dir.join("Midnight City.lrc"), "[00:01.00]title only").unwrap();
+        std::fs::write(dir.join("M83").join("Midnight City.LRC"), "[00:01.00]in the artist folder")
+            .unwrap();
+        std::fs::write(dir.join("4f2a.lrc"), "[ti:Wait]\n[ar:M83]\n[00:01.00]tagged").unwrap();
+        std::fs::write(dir.join("notes.txt"), "M83 - Midnight City").unwrap();
+
+        assert_eq!(
+            find_lyrics(&dir, "M83", "Midnight City"),
+            Some(dir.join("M83").join("Midnight City.LRC"))
+        );
+        assert_eq!(
+            find_lyrics(&dir, "Someone Else", "Midnight City"),
+            Some(dir.join("Midnight City.lrc"))
+        );
+        assert_eq!(find_lyrics(&dir, "M83", "Wait"), Some(dir.join("4f2a.lrc")));
+        assert_eq!(find_lyrics(&dir, "M83", "Outro"), None);
+        assert_eq!(find_lyrics(&dir.join("missing"), "M83", "Wait"), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn the_index_goes_stale_when_files_come_and_go() {
+        let dir = std::env::temp_dir().join(format!("lumen-lyrics-index-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let index = LyricsIndex::build(&dir);
+        assert!(!index.is_stale());
+        std::fs::create_dir_all(dir.join("M83")).unwrap();
+        assert!(index.is_stale());
+
+        let index = LyricsIndex::build(&dir);
+        assert_eq!(index.find("M83", "Midnight City"), None);
+        std::fs::write(dir.join("M83").join("Midnight City.lrc"), "[00:01.00]added").unwrap();
+        assert!(index.is_stale());
+
+        let index = LyricsIndex::build(&dir);
+        assert_eq!(
+            index.find("M83", "Midnight City"),
+            Some(dir.join("M83").join("Midnight City.lrc").as_path())
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,488 @@
+use std::{
+    collections::{HashMap, HashSet, hash_map::Entry},
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::{AppUsage, utils::privacy_log_path};
+
+/// How long intervals are kept once they're over.
+pub(crate) const RETENTION: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sensor {
+    Microphone,
+    Camera,
+    ScreenCapture,
+    Location,
+}
+
+impl Sensor {
+    pub fn label(self) -> &'static str {
+        match self {
+            Sensor::Microphone => "Microphone",
+            Sensor::Camera => "Camera",
+            Sensor::ScreenCapture => "Screen capture",
+            Sensor::Location => "Location",
+        }
+    }
+}
+
+/// One stretch of an app using a sensor. Times are Unix milliseconds; `end_ms` is `None`
+/// while the app is still using it, or when Lumen exited before it stopped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct UsageInterval {
+    pub sensor: Sensor,
+    pub app: String,
+    pub exe_path: String,
+    pub pid: u32,
+    pub start_ms: u64,
+    pub end_ms: Option<u64>,
+}
+
+/// What tells the lines written for one interval apart from those of another.
+type IntervalKey = (Sensor, u32, String, String, u64);
+
+impl UsageInterval {
+    fn key(&self) -> IntervalKey {
+        (self.sensor, self.pid, self.exe_path.clone(), self.app.clone(), self.start_ms)
+    }
+
+    fn is_app(&self, sensor: Sensor, app: &AppUsage) -> bool {
+        self.sensor == sensor
+            && self.pid == app.pid
+            && self.exe_path == app.exe_path
+            && self.app == app.name
+    }
+}
+
+/// Total time one app spent on one sensor.
+#[derive(Debug, Clone)]
+pub struct UsageSummary {
+    pub sensor: Sensor,
+    pub app: String,
+    pub total: Duration,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Append-only record of sensor use, stored as JSON lines. An interval is written once when
+/// it opens and again when it closes, so a crash still leaves its start behind; readers keep
+/// the last line for each interval.
+///
+/// Reading only needs the file and the intervals open right now, so callers clone the log
+/// out of the runtime's lock before reading it.
+#[derive(Clone)]
+pub struct PrivacyLog {
+    path: PathBuf,
+    open: Vec<UsageInterval>,
+    revision: u64,
+}
+
+impl PrivacyLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, open: Vec::new(), revision: 0 }
+    }
+
+    /// Opens and closes intervals so the ones open for `sensor` match `apps`.
+    pub fn update(&mut self, sensor: Sensor, apps: &[AppUsage], now_ms: u64) {
+        let mut changed = Vec::new();
+
+        self.open.retain_mut(|interval| {
+            if interval.sensor != sensor || apps.iter().any(|app| interval.is_app(sensor, app)) {
+                return true;
+            }
+
+            interval.end_ms = Some(now_ms);
+            changed.push(interval.clone());
+            false
+        });
+
+        for app in apps {
+            if self.open.iter().any(|interval| interval.is_app(sensor, app)) {
+                continue;
+            }
+
+            let interval = UsageInterval {
+                sensor,
+                app: app.name.clone(),
+                exe_path: app.exe_path.clone(),
+                pid: app.pid,
+                start_ms: now_ms,
+                end_ms: None,
+            };
+            changed.push(interval.clone());
+            self.open.push(interval);
+        }
+
+        if changed.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self.append(&changed) {
+            eprintln!("[Privacy] Failed to write {}: {e}", self.path.display());
+        }
+        self.revision += 1;
+    }
+
+    /// Bumped whenever an interval opens or closes.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Every interval that was still running at or after `since_ms`, oldest first.
+    pub fn intervals(&self, since_ms: u64) -> Result<Vec<UsageInterval>> {
+        let mut intervals = self.read()?;
+
+        intervals.retain(|interval| interval.end_ms.unwrap_or(u64::MAX) >= since_ms);
+        intervals.sort_by_key(|interval| interval.start_ms);
+
+        Ok(intervals)
+    }
+
+    /// Per-app totals since `since_ms`, longest first. Intervals left open by an earlier run
+    /// have no known end and count as zero.
+    pub fn report(&self, since_ms: u64, now_ms: u64) -> Result<Vec<UsageSummary>> {
+        let open: HashSet<IntervalKey> = self.open.iter().map(UsageInterval::key).collect();
+        let mut summaries: Vec<UsageSummary> = Vec::new();
+        let mut index: HashMap<(Sensor, String), usize> = HashMap::new();
+
+        for interval in self.intervals(since_ms)? {
+            let active = open.contains(&interval.key());
+            let end = match interval.end_ms {
+                Some(end) => end,
+                None if active => now_ms,
+                None => interval.start_ms,
+            };
+            let used = Duration::from_millis(end.saturating_sub(interval.start_ms.max(since_ms)));
+
+            match index.entry((interval.sensor, interval.app.clone())) {
+                Entry::Occupied(entry) => {
+                    let summary = &mut summaries[*entry.get()];
+                    summary.total += used;
+                    summary.active |= active;
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(summaries.len());
+                    summaries.push(UsageSummary {
+                        sensor: interval.sensor,
+                        app: interval.app,
+                        total: used,
+                        active,
+                    });
+                }
+            }
+        }
+
+        summaries.sort_by_key(|s| std::cmp::Reverse(s.total));
+        Ok(summaries)
+    }
+
+    /// Rewrites the file with a line per interval, leaving out the ones over before
+    /// `before_ms`. An interval an earlier run left open is over once it started before then.
+    /// Hold the runtime's lock while this runs, so no interval is written in between.
+    pub fn prune(&self, before_ms: u64) -> Result<()> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+
+        let open: HashSet<IntervalKey> = self.open.iter().map(UsageInterval::key).collect();
+
+        let mut lines = String::new();
+        for interval in self.read()? {
+            let kept = match interval.end_ms {
+                Some(end) => end >= before_ms,
+                None => interval.start_ms >= before_ms || open.contains(&interval.key()),
+            };
+            if kept {
+                lines.push_str(&serde_json::to_string(&interval)?);
+                lines.push('\n');
+            }
+        }
+
+        let temp = self.path.with_extension("jsonl.tmp");
+        std::fs::write(&temp, lines)?;
+        std::fs::rename(&temp, &self.path)?;
+
+        Ok(())
+    }
+
+    /// Every interval in the file as its last line has it, in the order they opened.
+    fn read(&self) -> Result<Vec<UsageInterval>> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut intervals: Vec<UsageInterval> = Vec::new();
+        let mut index: HashMap<IntervalKey, usize> = HashMap::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let Ok(interval) = serde_json::from_str::<UsageInterval>(line) else {
+                continue;
+            };
+
+            match index.entry(interval.key()) {
+                Entry::Occupied(entry) => intervals[*entry.get()] = interval,
+                Entry::Vacant(entry) => {
+                    entry.insert(intervals.len());
+                    intervals.push(interval);
+                }
+            }
+        }
+
+        Ok(intervals)
+    }
+
+    fn append(&self, intervals: &[UsageInterval]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let mut lines = String::new();
+        for interval in intervals {
+            lines.push_str(&serde_json::to_string(interval)?);
+            lines.push('\n');
+        }
+        file.write_all(lines.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+impl Default for PrivacyLog {
+    fn default() -> Self {
+        Self::new(privacy_log_path())
+    }
+}
+
+pub fn export(intervals: &[UsageInterval], format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(intervals)?),
+        ExportFormat::Csv => {
+            let mut csv = String::from("sensor,app,exe_path,pid,start,end\n");
+            for interval in intervals {
+                let row = [
+                    interval.sensor.label().to_string(),
+                    interval.app.clone(),
+                    interval.exe_path.clone(),
+                    interval.pid.to_string(),
+                    format_time(interval.start_ms),
+                    interval.end_ms.map(format_time).unwrap_or_default(),
+                ];
+                let row: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+                csv.push_str(&row.join(","));
+                csv.push('\n');
+            }
+            Ok(csv)
+        }
+    }
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Local midnight at the start of today, in Unix milliseconds.
+pub fn start_of_today_ms() -> u64 {
+    let midnight = Local::now().date_naive().and_hms_opt(0, 0, 0).unwrap();
+
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map_or_else(now_ms, |time| time.timestamp_millis().max(0) as u64)
+}
+
+fn format_time(ms: u64) -> String {
+    DateTime::from_timestamp_millis(ms as i64)
+        .map(|time| time.with_timezone(&Local).to_rfc3339())
+        .unwrap_or_default()
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(pid: u32, name: &str) -> AppUsage {
+        AppUsage {
+            pid,
+            exe_path: format!("/usr/bin/{}", name.to_lowercase()),
+            name: name.into(),
+            icon: None,
+        }
+    }
+
+    fn log(name: &str) -> PrivacyLog {
+        let dir = std::env::temp_dir().join(format!("lumen-privacy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{name}.jsonl"));
+        let _ = std::fs::remove_file(&path);
+
+        PrivacyLog::new(path)
+    }
+
+    fn totals(report: &[UsageSummary]) -> Vec<(Sensor, &str, u64, bool)> {
+        report
+            .iter()
+            .map(|s| (s.sensor, s.app.as_str(), s.total.as_millis() as u64, s.active))
+            .collect()
+    }
+
+    #[test]
+    fn intervals_open_and_close_with_the_apps() {
+        let mut log = log("update");
+        let (zoom, obs) = (|| app(10, "Zoom"), || app(20, "OBS"));
+
+        log.update(Sensor::Microphone, &[zoom()], 1_000);
+        log.update(Sensor::Microphone, &[zoom(), obs()], 2_000);
+        log.update(Sensor::Camera, &[obs()], 2_500);
+        let revision = log.revision();
+        // nothing changed
+        log.update(Sensor::Microphone, &[obs(), zoom()], 3_000);
+        assert_eq!(log.revision(), revision);
+        log.update(Sensor::Microphone, &[], 5_000);
+
+        let intervals = log.intervals(0).unwrap();
+        let spans: Vec<_> =
+            intervals.iter().map(|i| (i.sensor, i.app.as_str(), i.start_ms, i.end_ms)).collect();
+        assert_eq!(
+            spans,
+            [
+                (Sensor::Microphone, "Zoom", 1_000, Some(5_000)),
+                (Sensor::Microphone, "OBS", 2_000, Some(5_000)),
+                (Sensor::Camera, "OBS", 2_500, None),
+            ]
+        );
+        // written when they open and again when they close
+        assert_eq!(std::fs::read_to_string(&log.path).unwrap().lines().count(), 5);
+        // ended before then
+        assert_eq!(log.intervals(5_001).unwrap().len(), 1);
+
+        assert_eq!(
+            totals(&log.report(0, 6_000).unwrap()),
+            [
+                (Sensor::Microphone, "Zoom", 4_000, false),
+                (Sensor::Camera, "OBS", 3_500, true),
+                (Sensor::Microphone, "OBS", 3_000, false),
+            ]
+        );
+        // only the time since counts
+        assert_eq!(
+            totals(&log.report(3_000, 6_000).unwrap()),
+            [
+                (Sensor::Camera, "OBS", 3_000, true),
+                (Sensor::Microphone, "Zoom", 2_000, false),
+                (Sensor::Microphone, "OBS", 2_000, false),
+            ]
+        );
+
+        // the next run doesn't know when the camera was let go
+        let restarted = PrivacyLog::new(log.path.clone());
+        assert_eq!(
+            totals(&restarted.report(0, 6_000).unwrap())[2],
+            (Sensor::Camera, "OBS", 0, false)
+        );
+    }
+
+    #[test]
+    fn uses_of_one_app_add_up() {
+        let mut log = log("report");
+        let zoom = || app(10, "Zoom");
+
+        log.update(Sensor::Microphone, &[zoom()], 0);
+        log.update(Sensor::Microphone, &[], 60_000);
+        log.update(Sensor::Microphone, &[zoom()], 120_000);
+        // a new process of the same app
+        log.update(Sensor::Microphone, &[app(11, "Zoom")], 150_000);
+        log.update(Sensor::Microphone, &[], 180_000);
+
+        assert_eq!(
+            totals(&log.report(0, 200_000).unwrap()),
+            [(Sensor::Microphone, "Zoom", 120_000, false)]
+        );
+    }
+
+    #[test]
+    fn pruning_drops_old_intervals_and_duplicate_lines() {
+        let mut log = log("prune");
+        let (zoom, obs, teams) = (|| app(10, "Zoom"), || app(20, "OBS"), || app(30, "Teams"));
+
+        log.update(Sensor::Microphone, &[zoom()], 1_000);
+        log.update(Sensor::Microphone, &[], 2_000);
+        log.update(Sensor::Camera, &[obs()], 3_000);
+        log.update(Sensor::Camera, &[], 9_000);
+        log.update(Sensor::ScreenCapture, &[teams()], 4_000);
+
+        // an earlier run that never closed its interval
+        let mut stale = PrivacyLog::new(log.path.clone());
+        stale.update(Sensor::Location, &[zoom()], 1_500);
+
+        log.prune(5_000).unwrap();
+
+        let kept: Vec<_> =
+            log.intervals(0).unwrap().into_iter().map(|i| (i.sensor, i.app)).collect();
+        assert_eq!(
+            kept,
+            [(Sensor::Camera, "OBS".to_string()), (Sensor::ScreenCapture, "Teams".to_string())]
+        );
+        assert_eq!(std::fs::read_to_string(&log.path).unwrap().lines().count(), 2);
+
+        // and writing carries on where it left off
+        log.update(Sensor::ScreenCapture, &[], 10_000);
+        assert_eq!(log.intervals(0).unwrap()[1].end_ms, Some(10_000));
+
+        assert!(log.prune(0).is_ok());
+        assert!(PrivacyLog::new(log.path.with_extension("missing")).prune(0).is_ok());
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_they_need_to_be() {
+        let intervals = [UsageInterval {
+            sensor: Sensor::ScreenCapture,
+            app: "Zoom, \"Meetings\"".into(),
+            exe_path: "/opt/zoom\nbin".into(),
+            pid: 10,
+            start_ms: 0,
+            end_ms: None,
+        }];
+
+        let csv = export(&intervals, ExportFormat::Csv).unwrap();
+        let mut lines = csv.split('\n');
+        assert_eq!(lines.next(), Some("sensor,app,exe_path,pid,start,end"));
+
+        let row = csv.split_once('\n').unwrap().1;
+        let expected = format!(
+            "Screen capture,\"Zoom, \"\"Meetings\"\"\",\"/opt/zoom\nbin\",10,{},\n",
+            csv_field(&format_time(0))
+        );
+        assert_eq!(row, expected);
+
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("carriage\rreturn"), "\"carriage\rreturn\"");
+
+        let json = export(&intervals, ExportFormat::Json).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<UsageInterval>>(&json).unwrap(), intervals);
+    }
+}
@@ -1,28 +1,156 @@
 use std::{
     collections::VecDeque,
-    sync::{Arc, Mutex, RwLock, atomic::AtomicBool},
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
 };
 
-use crate::{MediaState, NotificationState};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{
+    AnalyzerState, AppUsage, AudioLevels, Config, LoudnessState, Lyrics, MediaState,
+    NotificationState, Theme, VolumeState,
+    commands::Optimistic,
+    privacy::{PrivacyLog, Sensor, now_ms},
+    theme::system_prefers_dark,
+};
 
 pub struct RuntimeState {
+    pub config: Config,
+    pub theme: RwLock<Theme>,
+
+    /// What the island shows, which runs ahead of the player while media commands are
+    /// underway.
     pub media: Arc<RwLock<Option<MediaState>>>,
+    optimistic: Mutex<Optimistic>,
+    /// For the track in `media`, when there's a lyrics file for it.
+    pub lyrics: RwLock<Option<Arc<Lyrics>>>,
     pub notifications: Arc<Mutex<VecDeque<NotificationState>>>,
+    pub do_not_disturb: AtomicBool,
 
     pub mic: AtomicBool,
+    pub mic_muted: AtomicBool,
     pub camera: AtomicBool,
+    pub screen_capture: AtomicBool,
+    pub location: AtomicBool,
+    pub mic_apps: Arc<RwLock<Vec<AppUsage>>>,
+    pub camera_apps: Arc<RwLock<Vec<AppUsage>>>,
+    pub screen_capture_apps: Arc<RwLock<Vec<AppUsage>>>,
+    pub location_apps: Arc<RwLock<Vec<AppUsage>>>,
+    pub privacy: Mutex<PrivacyLog>,
+
+    pub volume: Arc<RwLock<Option<VolumeState>>>,
+    pub volume_osd: AtomicBool,
 
     pub spectrum: Arc<RwLock<[f32; 24]>>,
+    pub waveform: Arc<RwLock<[f32; 32]>>,
+    pub scope: Arc<RwLock<[[f32; 2]; 96]>>,
+    pub levels: Arc<RwLock<AudioLevels>>,
+
+    pub loudness: Arc<RwLock<LoudnessState>>,
+    pub loudness_reset: AtomicBool,
+
+    pub analyzer: Arc<RwLock<AnalyzerState>>,
+    pub visualizer_visible: AtomicBool,
+    analyzer_wake_tx: Sender<()>,
+    pub(crate) analyzer_wake_rx: Receiver<()>,
+    lyrics_wake_tx: Sender<()>,
+    pub(crate) lyrics_wake_rx: Receiver<()>,
 }
 
 impl RuntimeState {
-    pub fn new() -> Self {
+    pub fn new(config: Config) -> Self {
+        let (analyzer_wake_tx, analyzer_wake_rx) = crossbeam_channel::bounded(1);
+        let (lyrics_wake_tx, lyrics_wake_rx) = crossbeam_channel::bounded(1);
+
+        let theme = Theme::load(&config.theme, system_prefers_dark().unwrap_or(true));
+
         Self {
+            config,
+            theme: RwLock::new(theme),
             media: Arc::new(RwLock::new(None)),
+            optimistic: Mutex::new(Optimistic::default()),
+            lyrics: RwLock::new(None),
             notifications: Arc::new(Mutex::new(VecDeque::new())),
+            do_not_disturb: AtomicBool::new(false),
             mic: AtomicBool::new(false),
+            mic_muted: AtomicBool::new(false),
             camera: AtomicBool::new(false),
+            screen_capture: AtomicBool::new(false),
+            location: AtomicBool::new(false),
+            mic_apps: Arc::new(RwLock::new(Vec::new())),
+            camera_apps: Arc::new(RwLock::new(Vec::new())),
+            screen_capture_apps: Arc::new(RwLock::new(Vec::new())),
+            location_apps: Arc::new(RwLock::new(Vec::new())),
+            privacy: Mutex::new(PrivacyLog::default()),
+            volume: Arc::new(RwLock::new(None)),
+            volume_osd: AtomicBool::new(false),
             spectrum: Arc::new(RwLock::new([0.0; 24])),
+            waveform: Arc::new(RwLock::new([0.0; 32])),
+            scope: Arc::new(RwLock::new([[0.0; 2]; 96])),
+            levels: Arc::new(RwLock::new(AudioLevels::default())),
+            loudness: Arc::new(RwLock::new(LoudnessState::default())),
+            loudness_reset: AtomicBool::new(false),
+            analyzer: Arc::new(RwLock::new(AnalyzerState::default())),
+            visualizer_visible: AtomicBool::new(true),
+            analyzer_wake_tx,
+            analyzer_wake_rx,
+            lyrics_wake_tx,
+            lyrics_wake_rx,
         }
     }
+
+    /// Whether the audio analyzer has to capture at all: always for the loudness meter,
+    /// which measures whatever is playing, and otherwise for the visualizer.
+    pub fn wants_analysis(&self) -> bool {
+        self.config.loudness_meter || self.wants_visualizer()
+    }
+
+    /// Whether there's a visualizer on screen for the analyzer to feed.
+    pub fn wants_visualizer(&self) -> bool {
+        let playing = self.media.read().map(|m| m.as_ref().is_some_and(|m| m.playing));
+
+        playing.unwrap_or(false) && self.visualizer_visible.load(Ordering::Relaxed)
+    }
+
+    pub fn media_app_id(&self) -> Option<String> {
+        self.media.read().ok()?.as_ref().map(|m| m.app_id.clone())
+    }
+
+    /// Publishes which apps are using `sensor` and records the change in the privacy log.
+    pub fn set_sensor_apps(&self, sensor: Sensor, apps: Vec<AppUsage>) {
+        let (active, list) = match sensor {
+            Sensor::Microphone => (&self.mic, &self.mic_apps),
+            Sensor::Camera => (&self.camera, &self.camera_apps),
+            Sensor::ScreenCapture => (&self.screen_capture, &self.screen_capture_apps),
+            Sensor::Location => (&self.location, &self.location_apps),
+        };
+
+        active.store(!apps.is_empty(), Ordering::Relaxed);
+        self.privacy.lock().unwrap().update(sensor, &apps, now_ms());
+        *list.write().unwrap() = apps;
+    }
+
+    /// Changes what's expected of the media, or takes in what the player reported, and
+    /// updates `media` to match. Returns what `change` does, whether anything changed.
+    pub(crate) fn update_media(&self, change: impl FnOnce(&mut Optimistic) -> bool) -> bool {
+        let mut optimistic = self.optimistic.lock().unwrap();
+        if !change(&mut optimistic) {
+            return false;
+        }
+
+        *self.media.write().unwrap() = optimistic.shown();
+        drop(optimistic);
+
+        self.wake_analyzer();
+        let _ = self.lyrics_wake_tx.try_send(());
+        true
+    }
+
+    /// Cuts the analyzer's current wait short so it reacts to media or visibility changes
+    /// right away.
+    pub fn wake_analyzer(&self) {
+        let _ = self.analyzer_wake_tx.try_send(());
+    }
 }
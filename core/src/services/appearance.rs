@@ -0,0 +1,82 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use crossbeam_channel::RecvTimeoutError;
+
+use crate::{
+    CoreEvent, Theme,
+    bus::EventSender,
+    runtime::RuntimeState,
+    services::Service,
+    theme::{system_prefers_dark, watch_system_theme},
+};
+
+/// The theme file may be edited at any time, and checking it is only a stat.
+const THEME_FILE_POLL: Duration = Duration::from_secs(2);
+
+/// Without a settings store to watch the watcher can't start at all, so it retries less
+/// and less often.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Follows the OS dark mode setting and edits to the theme file.
+pub struct AppearanceService;
+
+#[async_trait]
+impl Service for AppearanceService {
+    fn new() -> Self {
+        Self
+    }
+
+    async fn run(self, tx: EventSender, runtime: Arc<RuntimeState>) {
+        let (changed_tx, changed_rx) = crossbeam_channel::bounded(1);
+
+        std::thread::spawn(move || {
+            let mut delay = Duration::from_secs(1);
+
+            loop {
+                let started = Instant::now();
+                if let Err(e) = watch_system_theme(&changed_tx) {
+                    eprintln!("[AppearanceService] Reinitializing after: {e}");
+                }
+
+                if started.elapsed() > MAX_RETRY_DELAY {
+                    delay = Duration::from_secs(1);
+                }
+                std::thread::sleep(delay);
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+        });
+
+        std::thread::spawn(move || {
+            let selection = &runtime.config.theme;
+            let mut dark = system_prefers_dark().unwrap_or(true);
+            // the runtime starts out with the theme already loaded
+            let mut seen = (dark, Theme::modified(selection, dark));
+
+            loop {
+                // the setting is only read again once the OS says it changed
+                match changed_rx.recv_timeout(THEME_FILE_POLL) {
+                    Ok(()) => dark = system_prefers_dark().unwrap_or(true),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // the watcher only goes away if it panicked
+                    Err(RecvTimeoutError::Disconnected) => std::thread::sleep(THEME_FILE_POLL),
+                }
+
+                let current = (dark, Theme::modified(selection, dark));
+                if current != seen {
+                    let theme = Theme::load(selection, dark);
+                    let changed = *runtime.theme.read().unwrap() != theme;
+
+                    if changed {
+                        *runtime.theme.write().unwrap() = theme;
+                        let _ = tx.send(CoreEvent::ThemeChanged);
+                    }
+                }
+                seen = current;
+            }
+        });
+    }
+}
@@ -0,0 +1,404 @@
+use std::{
+    collections::VecDeque,
+    f32::consts::PI,
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
+
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+
+use crate::{
+    AudioLevels,
+    runtime::RuntimeState,
+    services::audio::{KineticBand, source::SourceFormat},
+    utils::{
+        loudness::LoudnessMeter,
+        simd_audio::{simd_apply_spatial_filter, simd_extract_magnitudes, simd_window_and_cast},
+    },
+};
+
+const FFT_SIZE: usize = 2048;
+pub(super) const NUM_BANDS: usize = 24;
+
+const WAVEFORM_POINTS: usize = 32;
+const WAVEFORM_WINDOW: usize = 2048;
+const SCOPE_POINTS: usize = 96;
+const SCOPE_WINDOW: usize = 768;
+
+const VU_INTEGRATION_SECS: f32 = 0.3;
+const PEAK_RELEASE_SECS: f32 = 0.6;
+
+const STIFFNESS: f32 = 260.0;
+const DAMPNESS: f32 = 4.0;
+
+/// Turns interleaved PCM into everything the visualizers read from `RuntimeState`.
+pub struct SpectrumAnalyzer {
+    filterbank: ConstantQFilterBank,
+    fft: Arc<dyn Fft<f32>>,
+    window_coefficients: Vec<f32>,
+
+    sample_ring_buffer: VecDeque<f32>,
+    fft_input_buffer: Vec<Complex<f32>>,
+    magnitude_bins: Vec<f32>,
+
+    kinetic_bands: [KineticBand; NUM_BANDS],
+    band_smoothing_cache: [f32; NUM_BANDS],
+
+    display_buffers: DisplayBuffers,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(format: SourceFormat, loudness_meter: bool) -> Self {
+        let mut planner = FftPlanner::<f32>::new();
+
+        Self {
+            filterbank: ConstantQFilterBank::new(format.sample_rate),
+            fft: planner.plan_fft_forward(FFT_SIZE),
+            window_coefficients: (0..FFT_SIZE)
+                .map(|i| 0.5 * (1.0 - (2.0 * PI * i as f32 / (FFT_SIZE as f32)).cos()))
+                .collect(),
+
+            sample_ring_buffer: VecDeque::with_capacity(FFT_SIZE * 2),
+            fft_input_buffer: vec![Complex { re: 0.0f32, im: 0.0f32 }; FFT_SIZE],
+            magnitude_bins: vec![0.0f32; FFT_SIZE / 2],
+
+            kinetic_bands: [KineticBand::default(); NUM_BANDS],
+            band_smoothing_cache: [0.0f32; NUM_BANDS],
+
+            display_buffers: DisplayBuffers::new(
+                loudness_meter.then(|| LoudnessMeter::new(format.sample_rate, format.channels)),
+            ),
+        }
+    }
+
+    /// Feeds interleaved samples to the visualizers; `samples.len()` must be a multiple of
+    /// `channels`.
+    pub fn push(&mut self, samples: &[f32], channels: usize) {
+        for frame in samples.chunks_exact(channels) {
+            self.sample_ring_buffer.push_back(frame.iter().sum::<f32>() / channels as f32);
+
+            let left = frame[0];
+            let right = if channels > 1 { frame[1] } else { left };
+            self.display_buffers.push([left, right]);
+        }
+    }
+
+    /// Feeds interleaved samples to the loudness meter, if it's on. It hears everything
+    /// captured, silence included, whether or not the visualizers get it too.
+    pub fn measure(&mut self, samples: &[f32], channels: usize) {
+        for frame in samples.chunks_exact(channels) {
+            self.display_buffers.push_loudness(frame.iter().copied());
+        }
+    }
+
+    /// Publishes the loudness meter's readings, without touching the visualizers.
+    pub fn publish_loudness(&mut self, runtime: &RuntimeState) {
+        self.display_buffers.publish_loudness(runtime);
+    }
+
+    /// Runs the FFT over everything buffered since the last call and publishes the results.
+    pub fn publish(&mut self, runtime: &RuntimeState, elapsed: Duration) {
+        if self.sample_ring_buffer.len() > FFT_SIZE * 2 {
+            self.sample_ring_buffer.drain(..self.sample_ring_buffer.len() - FFT_SIZE);
+        }
+
+        let mut state_changed = false;
+
+        self.sample_ring_buffer.make_contiguous();
+        while self.sample_ring_buffer.len() >= FFT_SIZE {
+            let (front_slice, _) = self.sample_ring_buffer.as_slices();
+
+            unsafe {
+                simd_window_and_cast(
+                    front_slice,
+                    &self.window_coefficients,
+                    &mut self.fft_input_buffer,
+                );
+            }
+
+            self.fft.process(&mut self.fft_input_buffer);
+
+            unsafe {
+                simd_extract_magnitudes(&self.fft_input_buffer, &mut self.magnitude_bins);
+            }
+
+            let mut raw_db_targets = [0.0f32; NUM_BANDS];
+            let magnitudes_array: &[f32; FFT_SIZE / 2] =
+                self.magnitude_bins[..FFT_SIZE / 2].try_into().unwrap();
+            self.filterbank.compute_targets(magnitudes_array, &mut raw_db_targets);
+
+            let dt = 1.0 / 60.0;
+            for (&target, band) in raw_db_targets.iter().zip(&mut self.kinetic_bands) {
+                let error = target - band.current_height;
+                let spring_force = (STIFFNESS * error) - (DAMPNESS * band.velocity);
+
+                band.velocity += spring_force * dt;
+                let next_height = band.current_height + band.velocity * dt;
+
+                band.current_height = next_height.clamp(0.15, 1.0);
+
+                if band.current_height != next_height {
+                    band.velocity = 0.0;
+                }
+            }
+
+            unsafe {
+                simd_apply_spatial_filter(&self.kinetic_bands, &mut self.band_smoothing_cache);
+            }
+
+            state_changed = true;
+            self.sample_ring_buffer.drain(..FFT_SIZE);
+        }
+
+        if state_changed && let Ok(mut lock) = runtime.spectrum.write() {
+            *lock = self.band_smoothing_cache;
+        }
+
+        self.display_buffers.publish(runtime, elapsed);
+    }
+
+    /// Drops buffered audio and publishes a resting state, so nothing stays frozen on screen
+    /// while analysis is paused.
+    pub fn rest(&mut self, runtime: &RuntimeState) {
+        self.sample_ring_buffer.clear();
+        self.kinetic_bands = [KineticBand::default(); NUM_BANDS];
+        self.band_smoothing_cache = [0.0; NUM_BANDS];
+        self.display_buffers.clear();
+
+        publish_rest(runtime);
+    }
+}
+
+pub(super) fn publish_rest(runtime: &RuntimeState) {
+    if let Ok(mut lock) = runtime.spectrum.write() {
+        *lock = [0.0; NUM_BANDS];
+    }
+    if let Ok(mut lock) = runtime.waveform.write() {
+        *lock = [0.0; WAVEFORM_POINTS];
+    }
+    if let Ok(mut lock) = runtime.scope.write() {
+        *lock = [[0.0; 2]; SCOPE_POINTS];
+    }
+    if let Ok(mut lock) = runtime.levels.write() {
+        *lock = AudioLevels::default();
+    }
+}
+
+struct DisplayBuffers {
+    frames: VecDeque<[f32; 2]>,
+    meter: LevelMeter,
+    loudness: Option<LoudnessMeter>,
+    dirty: bool,
+}
+
+impl DisplayBuffers {
+    fn new(loudness: Option<LoudnessMeter>) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(WAVEFORM_WINDOW * 2),
+            meter: LevelMeter::default(),
+            loudness,
+            dirty: false,
+        }
+    }
+
+    #[inline(always)]
+    fn push(&mut self, frame: [f32; 2]) {
+        self.frames.push_back(frame);
+        self.meter.push(frame);
+        self.dirty = true;
+    }
+
+    #[inline(always)]
+    fn push_loudness(&mut self, frame: impl IntoIterator<Item = f32>) {
+        if let Some(loudness) = &mut self.loudness {
+            loudness.push_frame(frame);
+        }
+    }
+
+    fn publish_loudness(&mut self, runtime: &RuntimeState) {
+        if let Some(loudness) = &mut self.loudness {
+            if runtime.loudness_reset.swap(false, Ordering::Relaxed) {
+                loudness.reset();
+            }
+            if let Ok(mut lock) = runtime.loudness.write() {
+                *lock = loudness.state();
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.frames.clear();
+        self.meter = LevelMeter::default();
+        self.dirty = false;
+    }
+
+    fn publish(&mut self, runtime: &RuntimeState, elapsed: Duration) {
+        if let Ok(mut lock) = runtime.levels.write() {
+            *lock = self.meter.finish(elapsed);
+        }
+        self.publish_loudness(runtime);
+
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+
+        if self.frames.len() > WAVEFORM_WINDOW {
+            self.frames.drain(..self.frames.len() - WAVEFORM_WINDOW);
+        }
+
+        let frames = self.frames.make_contiguous();
+
+        let mut waveform = [0.0f32; WAVEFORM_POINTS];
+        let bucket_size = (frames.len() / WAVEFORM_POINTS).max(1);
+        for (point, bucket) in waveform.iter_mut().zip(frames.chunks(bucket_size)) {
+            // keep the sign of the loudest sample so the envelope still reads as a waveform
+            *point = bucket
+                .iter()
+                .map(|[l, r]| (l + r) * 0.5)
+                .fold(0.0f32, |acc, sample| if sample.abs() > acc.abs() { sample } else { acc });
+        }
+
+        let mut scope = [[0.0f32; 2]; SCOPE_POINTS];
+        let scope_frames = &frames[frames.len().saturating_sub(SCOPE_WINDOW)..];
+        let stride = scope_frames.len() as f32 / SCOPE_POINTS as f32;
+        if !scope_frames.is_empty() {
+            for (idx, point) in scope.iter_mut().enumerate() {
+                *point = scope_frames[((idx as f32 * stride) as usize).min(scope_frames.len() - 1)];
+            }
+        }
+
+        if let Ok(mut lock) = runtime.waveform.write() {
+            *lock = waveform;
+        }
+        if let Ok(mut lock) = runtime.scope.write() {
+            *lock = scope;
+        }
+    }
+}
+
+#[derive(Default)]
+struct LevelMeter {
+    sum_squares: [f32; 2],
+    peaks: [f32; 2],
+    frames: usize,
+    levels: AudioLevels,
+}
+
+impl LevelMeter {
+    #[inline(always)]
+    fn push(&mut self, frame: [f32; 2]) {
+        for (ch, sample) in frame.into_iter().enumerate() {
+            self.sum_squares[ch] += sample * sample;
+            self.peaks[ch] = self.peaks[ch].max(sample.abs());
+        }
+        self.frames += 1;
+    }
+
+    fn finish(&mut self, elapsed: Duration) -> AudioLevels {
+        let secs = elapsed.as_secs_f32();
+        let rms_coefficient = 1.0 - (-secs / VU_INTEGRATION_SECS).exp();
+        let peak_decay = (-secs / PEAK_RELEASE_SECS).exp();
+
+        for (ch, level) in [&mut self.levels.left, &mut self.levels.right].into_iter().enumerate() {
+            let block_rms = if self.frames > 0 {
+                (self.sum_squares[ch] / self.frames as f32).sqrt()
+            } else {
+                0.0
+            };
+
+            level.rms = (level.rms + (block_rms - level.rms) * rms_coefficient).min(1.0);
+            level.peak = self.peaks[ch].max(level.peak * peak_decay).min(1.0);
+        }
+
+        self.sum_squares = [0.0; 2];
+        self.peaks = [0.0; 2];
+        self.frames = 0;
+
+        self.levels
+    }
+}
+
+struct ConstantQFilterBank {
+    band_mappings: Vec<Vec<(usize, f32)>>,
+}
+
+impl ConstantQFilterBank {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut band_mappings = vec![Vec::new(); NUM_BANDS];
+
+        let f_min = 27.5;
+        let bins_per_octave = 3.0;
+        let bin_resolution = (sample_rate / 2.0) / (FFT_SIZE as f32 / 2.0);
+
+        let q = 1.0 / (2.0_f32.powf(1.0 / bins_per_octave) - 1.0);
+
+        for (i, mappings) in band_mappings.iter_mut().enumerate() {
+            let center_freq = f_min * 2.0_f32.powf(i as f32 / bins_per_octave);
+
+            let bandwidth = center_freq / q;
+            let f_low = center_freq - (bandwidth / 2.0);
+            let f_high = center_freq + (bandwidth / 2.0);
+
+            let bin_start = (f_low / bin_resolution).floor() as usize;
+            let bin_end = ((f_high / bin_resolution).ceil() as usize).min(FFT_SIZE / 2);
+
+            let mut weight_sum = 0.0;
+            let mut temp_weights = Vec::new();
+
+            for bin in bin_start..bin_end {
+                let bin_freq = bin as f32 * bin_resolution;
+
+                let distance = (bin_freq - center_freq).abs();
+                if distance < (bandwidth / 2.0) {
+                    let weight = 1.0 - (distance / (bandwidth / 2.0));
+                    temp_weights.push((bin, weight));
+                    weight_sum += weight;
+                }
+            }
+
+            if weight_sum > 0.0 {
+                for (bin, weight) in temp_weights {
+                    mappings.push((bin, weight / weight_sum));
+                }
+            }
+        }
+
+        Self { band_mappings }
+    }
+
+    pub fn compute_targets(
+        &self,
+        fft_magnitudes: &[f32; FFT_SIZE / 2],
+        raw_db_targets: &mut [f32; NUM_BANDS],
+    ) {
+        let mut max_tracked_db = -100.0f32;
+        let mut temp_dbs = [0.0f32; NUM_BANDS];
+
+        for (mappings, temp_db) in self.band_mappings.iter().zip(&mut temp_dbs) {
+            let mut energy = 0.0;
+            for &(bin, weight) in mappings {
+                energy += fft_magnitudes[bin] * weight;
+            }
+
+            let db = 20.0 * (energy + 1e-6).log10();
+            *temp_db = db;
+
+            if db > max_tracked_db {
+                max_tracked_db = db;
+            }
+        }
+
+        let dynamic_ceiling = max_tracked_db.max(-18.0);
+        let dynamic_floor = dynamic_ceiling - 26.0;
+        for i in 0..NUM_BANDS {
+            let db = temp_dbs[i];
+
+            raw_db_targets[i] = if db < dynamic_floor {
+                0.0
+            } else {
+                ((db - dynamic_floor) / (dynamic_ceiling - dynamic_floor)).clamp(0.0, 1.0)
+            };
+        }
+    }
+}
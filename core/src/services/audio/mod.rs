@@ -0,0 +1,355 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+#[cfg(windows)]
+use windows::Win32::System::Com::{COINIT_MULTITHREADED, CoInitializeEx};
+
+use crate::{AnalyzerState, bus::EventSender, runtime::RuntimeState, services::Service};
+
+mod analyzer;
+#[cfg(target_os = "linux")]
+mod pulse;
+mod source;
+#[cfg(windows)]
+mod wasapi;
+
+use analyzer::publish_rest;
+#[cfg(target_os = "linux")]
+use pulse::open_source;
+#[cfg(target_os = "linux")]
+pub(crate) use pulse::resolve_process;
+#[cfg(windows)]
+use wasapi::open_source;
+#[cfg(windows)]
+pub(crate) use wasapi::resolve_process;
+
+pub use analyzer::SpectrumAnalyzer;
+pub use source::{AudioSource, OfflineSource, SourceFormat, SourceTarget};
+
+/// Anything quieter than this (about -80 dBFS) counts as silence.
+const SILENCE_THRESHOLD: f32 = 1e-4;
+const SILENCE_HOLD: Duration = Duration::from_secs(2);
+
+const IDLE_POLL: Duration = Duration::from_millis(250);
+const SUSPENDED_POLL: Duration = Duration::from_secs(1);
+
+pub struct AudioSpectrumService;
+
+#[async_trait]
+impl Service for AudioSpectrumService {
+    fn new() -> Self {
+        Self
+    }
+
+    async fn run(self, _tx: EventSender, runtime: Arc<RuntimeState>) {
+        std::thread::spawn(move || {
+            #[cfg(windows)]
+            unsafe {
+                let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            }
+
+            loop {
+                let target = current_target(&runtime);
+
+                match open_source(target).and_then(|source| run_analyzer(source, &runtime)) {
+                    Ok(_) => continue,
+                    Err(e) => {
+                        eprintln!("[AudioSpectrum] Reinitializing after: {e}");
+                        publish_rest(&runtime);
+                        std::thread::sleep(Duration::from_millis(500));
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct KineticBand {
+    pub current_height: f32,
+    pub velocity: f32,
+}
+
+/// Decides how much work the analyzer does on each wakeup: full analysis while something
+/// audible is playing on a visible island, a slow poll through silence, and nothing at all
+/// (capture stopped) when there's no playing media or the visualizer is hidden. The loudness
+/// meter, when it's on, keeps the capture going at the slow poll regardless.
+pub struct AnalyzerScheduler<S: AudioSource> {
+    source: S,
+    analyzer: SpectrumAnalyzer,
+    state: AnalyzerState,
+    /// Whether the visualizers were fed on the last step.
+    visualizing: bool,
+    silent_for: Duration,
+    samples: Vec<f32>,
+}
+
+impl<S: AudioSource> AnalyzerScheduler<S> {
+    /// `source` is expected to start out paused; the first step resumes it if there's
+    /// anything to analyze.
+    pub fn new(source: S, runtime: &RuntimeState) -> Self {
+        let analyzer = SpectrumAnalyzer::new(source.format(), runtime.config.loudness_meter);
+
+        Self {
+            source,
+            analyzer,
+            state: AnalyzerState::Suspended,
+            visualizing: false,
+            silent_for: Duration::ZERO,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> AnalyzerState {
+        self.state
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Runs one scheduling step and returns how long to wait before the next one.
+    pub fn step(&mut self, runtime: &RuntimeState) -> Result<Duration> {
+        if !runtime.wants_analysis() {
+            if self.state != AnalyzerState::Suspended {
+                self.source.pause()?;
+                self.analyzer.rest(runtime);
+                self.visualizing = false;
+                self.set_state(runtime, AnalyzerState::Suspended);
+            }
+            return Ok(SUSPENDED_POLL);
+        }
+
+        if self.state == AnalyzerState::Suspended {
+            self.source.resume()?;
+            self.silent_for = Duration::ZERO;
+            self.set_state(runtime, AnalyzerState::Active);
+        }
+
+        // the loudness meter alone doesn't need the audio any sooner
+        let visualize = runtime.wants_visualizer();
+        let interval = match self.state {
+            AnalyzerState::Active if visualize => self.source.period(),
+            _ => IDLE_POLL,
+        };
+
+        self.samples.clear();
+        self.source.read(&mut self.samples)?;
+        self.analyzer.measure(&self.samples, self.source.format().channels);
+
+        if self.samples.iter().any(|sample| sample.abs() > SILENCE_THRESHOLD) {
+            self.silent_for = Duration::ZERO;
+            if self.state == AnalyzerState::Idle {
+                self.set_state(runtime, AnalyzerState::Active);
+            }
+        } else {
+            self.silent_for += interval;
+            if self.state == AnalyzerState::Active && self.silent_for >= SILENCE_HOLD {
+                self.set_state(runtime, AnalyzerState::Idle);
+            }
+        }
+
+        if self.state != AnalyzerState::Active || !visualize {
+            // nothing stays frozen on the visualizer once it stops being fed
+            if self.visualizing {
+                self.analyzer.rest(runtime);
+                self.visualizing = false;
+            }
+            self.analyzer.publish_loudness(runtime);
+            return Ok(IDLE_POLL);
+        }
+
+        self.visualizing = true;
+        self.analyzer.push(&self.samples, self.source.format().channels);
+        self.analyzer.publish(runtime, interval);
+
+        Ok(self.source.period())
+    }
+
+    fn set_state(&mut self, runtime: &RuntimeState, state: AnalyzerState) {
+        self.state = state;
+        if let Ok(mut lock) = runtime.analyzer.write() {
+            *lock = state;
+        }
+    }
+}
+
+/// Drives `source` until it fails, sleeping between steps unless woken through
+/// [`RuntimeState::wake_analyzer`]. Returns `Ok` once the playing app changes and the
+/// capture should be reopened to follow it.
+pub fn run_analyzer(source: impl AudioSource, runtime: &RuntimeState) -> Result<()> {
+    let app_id = runtime.media_app_id();
+    let mut scheduler = AnalyzerScheduler::new(source, runtime);
+
+    loop {
+        let wait = scheduler.step(runtime)?;
+
+        if runtime.analyzer_wake_rx.recv_timeout(wait).is_ok()
+            && should_retarget(scheduler.source().target(), &app_id, runtime)
+        {
+            return Ok(());
+        }
+    }
+}
+
+fn current_target(runtime: &RuntimeState) -> SourceTarget {
+    runtime
+        .media_app_id()
+        .as_deref()
+        .and_then(resolve_process)
+        .map_or(SourceTarget::Mix, SourceTarget::Process)
+}
+
+fn should_retarget(target: SourceTarget, app_id: &Option<String>, runtime: &RuntimeState) -> bool {
+    if runtime.media_app_id() != *app_id {
+        return true;
+    }
+
+    // the app may only have opened its audio stream after its media session showed up
+    target == SourceTarget::Mix && current_target(runtime) != SourceTarget::Mix
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::TAU, sync::atomic::Ordering, time::SystemTime};
+
+    use super::*;
+    use crate::{Config, MediaState};
+
+    const FORMAT: SourceFormat = SourceFormat { sample_rate: 48_000.0, channels: 1 };
+    const PERIOD: Duration = Duration::from_millis(10);
+
+    fn playing(runtime: &RuntimeState, playing: bool) {
+        *runtime.media.write().unwrap() = Some(MediaState {
+            synced_at: SystemTime::now(),
+            app_id: "player".into(),
+            app_name: "Player".into(),
+            title: "Midnight City".into(),
+            artist: "M83".into(),
+            album: String::new(),
+            album_art: None,
+            palette: None,
+            duration_ms: 240_000,
+            position_ms: 0,
+            playing,
+            shuffle: None,
+            repeat: None,
+            rate: None,
+            capabilities: Default::default(),
+            lyrics: None,
+            app_icon: None,
+        });
+    }
+
+    fn sine(seconds: f32) -> Vec<f32> {
+        let frames = (FORMAT.sample_rate * seconds) as usize;
+        (0..frames).map(|i| 0.5 * (TAU * 440.0 * i as f32 / FORMAT.sample_rate).sin()).collect()
+    }
+
+    fn silence(seconds: f32) -> Vec<f32> {
+        vec![0.0; (FORMAT.sample_rate * seconds) as usize]
+    }
+
+    fn published(runtime: &RuntimeState) -> AnalyzerState {
+        *runtime.analyzer.read().unwrap()
+    }
+
+    #[test]
+    fn silence_idles_the_analyzer_until_the_signal_returns() {
+        let runtime = RuntimeState::new(Config::default());
+        playing(&runtime, true);
+
+        let samples = [sine(0.5), silence(3.0), sine(0.5)].concat();
+        let mut scheduler =
+            AnalyzerScheduler::new(OfflineSource::new(FORMAT, PERIOD, samples), &runtime);
+
+        for _ in 0..50 {
+            assert_eq!(scheduler.step(&runtime).unwrap(), PERIOD);
+            assert_eq!(scheduler.state(), AnalyzerState::Active);
+        }
+        assert!(runtime.spectrum.read().unwrap().iter().any(|&band| band > 0.0));
+
+        // a pause shorter than the hold keeps the visualizer going
+        let silent_steps = SILENCE_HOLD.as_millis() / PERIOD.as_millis();
+        for _ in 1..silent_steps {
+            assert_eq!(scheduler.step(&runtime).unwrap(), PERIOD);
+            assert_eq!(scheduler.state(), AnalyzerState::Active);
+        }
+
+        assert_eq!(scheduler.step(&runtime).unwrap(), IDLE_POLL);
+        assert_eq!(scheduler.state(), AnalyzerState::Idle);
+        assert_eq!(published(&runtime), AnalyzerState::Idle);
+
+        // the rest of the silence
+        for _ in 0..100 {
+            assert_eq!(scheduler.step(&runtime).unwrap(), IDLE_POLL);
+            assert_eq!(scheduler.state(), AnalyzerState::Idle);
+        }
+
+        // the first period with sound in it brings it straight back
+        assert_eq!(scheduler.step(&runtime).unwrap(), PERIOD);
+        assert_eq!(scheduler.state(), AnalyzerState::Active);
+        assert_eq!(published(&runtime), AnalyzerState::Active);
+    }
+
+    #[test]
+    fn nothing_is_captured_without_playing_media_or_a_visible_visualizer() {
+        let runtime = RuntimeState::new(Config::default());
+        let mut scheduler =
+            AnalyzerScheduler::new(OfflineSource::new(FORMAT, PERIOD, sine(0.02)), &runtime);
+
+        // no media
+        assert_eq!(scheduler.step(&runtime).unwrap(), SUSPENDED_POLL);
+        assert_eq!(scheduler.state(), AnalyzerState::Suspended);
+
+        // paused media
+        playing(&runtime, false);
+        assert_eq!(scheduler.step(&runtime).unwrap(), SUSPENDED_POLL);
+        assert_eq!(scheduler.state(), AnalyzerState::Suspended);
+
+        // playing, but the visualizer is hidden
+        playing(&runtime, true);
+        runtime.visualizer_visible.store(false, Ordering::Relaxed);
+        assert_eq!(scheduler.step(&runtime).unwrap(), SUSPENDED_POLL);
+        assert_eq!(scheduler.state(), AnalyzerState::Suspended);
+
+        runtime.visualizer_visible.store(true, Ordering::Relaxed);
+        assert_eq!(scheduler.step(&runtime).unwrap(), PERIOD);
+        assert_eq!(scheduler.state(), AnalyzerState::Active);
+        assert_eq!(published(&runtime), AnalyzerState::Active);
+
+        // hiding it again stops the capture
+        runtime.visualizer_visible.store(false, Ordering::Relaxed);
+        assert_eq!(scheduler.step(&runtime).unwrap(), SUSPENDED_POLL);
+        assert_eq!(scheduler.state(), AnalyzerState::Suspended);
+        assert_eq!(published(&runtime), AnalyzerState::Suspended);
+
+        // only the one active step read from the source, leaving the second period
+        assert!(!scheduler.source().is_exhausted());
+        runtime.visualizer_visible.store(true, Ordering::Relaxed);
+        scheduler.step(&runtime).unwrap();
+        assert!(scheduler.source().is_exhausted());
+    }
+
+    #[test]
+    fn the_loudness_meter_keeps_measuring_without_media_or_a_visualizer() {
+        let runtime = RuntimeState::new(Config { loudness_meter: true, ..Config::default() });
+        runtime.visualizer_visible.store(false, Ordering::Relaxed);
+        let mut scheduler =
+            AnalyzerScheduler::new(OfflineSource::new(FORMAT, PERIOD, sine(1.0)), &runtime);
+
+        // no media at all, like a DAW that doesn't publish a session
+        for _ in 0..100 {
+            assert_eq!(scheduler.step(&runtime).unwrap(), IDLE_POLL);
+            assert_eq!(scheduler.state(), AnalyzerState::Active);
+        }
+        assert!(runtime.loudness.read().unwrap().integrated.is_finite());
+        assert!(runtime.spectrum.read().unwrap().iter().all(|&band| band == 0.0));
+
+        // the visualizer picks up from there once it has something to show
+        playing(&runtime, true);
+        runtime.visualizer_visible.store(true, Ordering::Relaxed);
+        assert_eq!(scheduler.step(&runtime).unwrap(), PERIOD);
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    io::Read,
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+
+use crate::{
+    services::audio::source::{AudioSource, SourceFormat, SourceTarget},
+    utils::pactl::pactl_json,
+};
+
+const FORMAT: SourceFormat = SourceFormat { sample_rate: 48000.0, channels: 2 };
+const PERIOD: Duration = Duration::from_millis(20);
+
+/// Caps what piles up between reads, so a stalled analyzer doesn't buffer forever.
+const MAX_PENDING_SAMPLES: usize = 48000 * 2;
+
+/// Opens a capture for `target`, falling back to the default sink's monitor when the
+/// process has no playback stream.
+pub fn open_source(target: SourceTarget) -> Result<ParecSource> {
+    match target {
+        SourceTarget::Mix => Ok(ParecSource::new(target, None)),
+        SourceTarget::Process(pid) => match sink_input_for_pid(pid) {
+            Ok(index) => Ok(ParecSource::new(target, Some(index))),
+            Err(e) => {
+                eprintln!("[AudioSpectrum] Falling back to full mix for {pid}: {e}");
+                Ok(ParecSource::new(SourceTarget::Mix, None))
+            }
+        },
+    }
+}
+
+/// Finds the process behind the playback stream of `app_id` (an MPRIS identity or desktop
+/// entry) by matching it against the application properties of the sound server's streams.
+pub fn resolve_process(app_id: &str) -> Option<u32> {
+    let app_id = app_id.to_lowercase();
+
+    let mut fallback = None;
+    for input in list_sink_inputs().ok()? {
+        let Some(pid) = input.pid() else {
+            continue;
+        };
+
+        let matches = ["application.name", "application.process.binary", "application.id"]
+            .iter()
+            .filter_map(|key| input.properties.get(*key))
+            .any(|value| {
+                let value = value.to_lowercase();
+                !value.is_empty() && (app_id.contains(&value) || value.contains(&app_id))
+            });
+        if !matches {
+            continue;
+        }
+
+        if !input.corked {
+            return Some(pid);
+        }
+        fallback.get_or_insert(pid);
+    }
+
+    fallback
+}
+
+#[derive(Deserialize)]
+struct SinkInput {
+    index: u32,
+    #[serde(default)]
+    corked: bool,
+    #[serde(default)]
+    properties: std::collections::HashMap<String, String>,
+}
+
+impl SinkInput {
+    fn pid(&self) -> Option<u32> {
+        self.properties.get("application.process.id")?.parse().ok()
+    }
+}
+
+fn list_sink_inputs() -> Result<Vec<SinkInput>> {
+    pactl_json(&["list", "sink-inputs"])
+}
+
+fn sink_input_for_pid(pid: u32) -> Result<u32> {
+    list_sink_inputs()?
+        .into_iter()
+        .find(|input| input.pid() == Some(pid))
+        .map(|input| input.index)
+        .ok_or_else(|| anyhow!("No playback stream for process {pid}"))
+}
+
+/// Records through `parec`, which works against both PulseAudio and PipeWire's pulse
+/// server. With a sink input index it monitors just that stream, otherwise the default
+/// sink's monitor. The recorder is only running while capture is resumed.
+pub struct ParecSource {
+    target: SourceTarget,
+    sink_input: Option<u32>,
+    child: Option<Child>,
+    pending: Arc<Mutex<Vec<f32>>>,
+}
+
+impl ParecSource {
+    fn new(target: SourceTarget, sink_input: Option<u32>) -> Self {
+        Self { target, sink_input, child: None, pending: Arc::new(Mutex::new(Vec::new())) }
+    }
+
+    fn spawn(&mut self) -> Result<()> {
+        let mut command = Command::new("parec");
+        command.args([
+            "--raw",
+            "--format=float32le",
+            &format!("--rate={}", FORMAT.sample_rate as u32),
+            &format!("--channels={}", FORMAT.channels),
+            &format!("--latency-msec={}", PERIOD.as_millis()),
+        ]);
+
+        match self.sink_input {
+            Some(index) => command.arg(format!("--monitor-stream={index}")),
+            None => command.arg("--device=@DEFAULT_MONITOR@"),
+        };
+
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to run parec")?;
+
+        let mut stdout = child.stdout.take().ok_or_else(|| anyhow!("parec has no stdout"))?;
+        let pending = self.pending.clone();
+
+        std::thread::spawn(move || {
+            // only whole frames are handed on, so the channels can't slip out of step
+            let frame_bytes = FORMAT.channels * 4;
+            let mut buffer = [0u8; 4096];
+            let mut carry = Vec::with_capacity(frame_bytes);
+
+            while let Ok(read) = stdout.read(&mut buffer) {
+                if read == 0 {
+                    break;
+                }
+
+                carry.extend_from_slice(&buffer[..read]);
+                let whole = carry.len() / frame_bytes * frame_bytes;
+
+                if let Ok(mut pending) = pending.lock() {
+                    pending.extend(
+                        carry[..whole].chunks_exact(4).map(|bytes| {
+                            f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                        }),
+                    );
+
+                    if pending.len() > MAX_PENDING_SAMPLES {
+                        let excess =
+                            (pending.len() - MAX_PENDING_SAMPLES).next_multiple_of(FORMAT.channels);
+                        pending.drain(..excess);
+                    }
+                }
+
+                carry.drain(..whole);
+            }
+        });
+
+        self.child = Some(child);
+        Ok(())
+    }
+
+    fn kill(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl AudioSource for ParecSource {
+    fn format(&self) -> SourceFormat {
+        FORMAT
+    }
+
+    fn target(&self) -> SourceTarget {
+        self.target
+    }
+
+    fn period(&self) -> Duration {
+        PERIOD
+    }
+
+    fn read(&mut self, out: &mut Vec<f32>) -> Result<()> {
+        if let Some(child) = &mut self.child
+            && let Some(status) = child.try_wait()?
+        {
+            self.child = None;
+            bail!("parec exited with {status}");
+        }
+
+        if let Ok(mut pending) = self.pending.lock() {
+            out.append(&mut pending);
+        }
+
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.kill();
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.clear();
+        }
+        self.spawn()
+    }
+}
+
+impl Drop for ParecSource {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use anyhow::Result;
+
+/// What a capture source listens to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceTarget {
+    /// Everything playing on the default output device.
+    Mix,
+    /// Only the audio of this process (and its children, where the platform allows).
+    Process(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourceFormat {
+    pub sample_rate: f32,
+    pub channels: usize,
+}
+
+/// A stream of interleaved `f32` PCM the spectrum analyzer can run off.
+pub trait AudioSource {
+    fn format(&self) -> SourceFormat;
+
+    fn target(&self) -> SourceTarget {
+        SourceTarget::Mix
+    }
+
+    /// How often new audio becomes available while the source is running.
+    fn period(&self) -> Duration;
+
+    /// Appends everything captured since the last call to `out`. An error means the source
+    /// is unusable and has to be recreated.
+    fn read(&mut self, out: &mut Vec<f32>) -> Result<()>;
+
+    fn pause(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Plays back a fixed buffer one period at a time, then reports silence.
+pub struct OfflineSource {
+    format: SourceFormat,
+    period: Duration,
+    samples: Vec<f32>,
+    position: usize,
+}
+
+impl OfflineSource {
+    pub fn new(format: SourceFormat, period: Duration, samples: Vec<f32>) -> Self {
+        Self { format, period, samples, position: 0 }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.position >= self.samples.len()
+    }
+}
+
+impl AudioSource for OfflineSource {
+    fn format(&self) -> SourceFormat {
+        self.format
+    }
+
+    fn period(&self) -> Duration {
+        self.period
+    }
+
+    fn read(&mut self, out: &mut Vec<f32>) -> Result<()> {
+        let frames = (self.format.sample_rate as f64 * self.period.as_secs_f64()).ceil() as usize;
+        let end = (self.position + frames * self.format.channels).min(self.samples.len());
+
+        out.extend_from_slice(&self.samples[self.position..end]);
+        self.position = end;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,462 @@
+use std::{path::Path, sync::mpsc, time::Duration};
+
+use anyhow::{Result, anyhow, bail};
+use windows::Win32::{
+    Media::Audio::{
+        AUDCLNT_BUFFERFLAGS_SILENT, AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM,
+        AUDCLNT_STREAMFLAGS_EVENTCALLBACK, AUDCLNT_STREAMFLAGS_LOOPBACK,
+        AUDIOCLIENT_ACTIVATION_PARAMS, AUDIOCLIENT_ACTIVATION_PARAMS_0,
+        AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK, AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS,
+        ActivateAudioInterfaceAsync, AudioSessionStateActive, Endpoints::IAudioEndpointVolume,
+        IActivateAudioInterfaceAsyncOperation, IActivateAudioInterfaceCompletionHandler,
+        IActivateAudioInterfaceCompletionHandler_Impl, IAudioCaptureClient, IAudioClient,
+        IAudioSessionControl2, IAudioSessionManager2, IMMDeviceEnumerator, IMMNotificationClient,
+        IMMNotificationClient_Impl, MMDeviceEnumerator,
+        PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE, VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+        WAVEFORMATEX, eConsole, eRender,
+    },
+    System::{
+        Com::{
+            BLOB, CLSCTX_ALL, CoCreateInstance, CoTaskMemFree,
+            StructuredStorage::{PROPVARIANT, PROPVARIANT_0, PROPVARIANT_0_0, PROPVARIANT_0_0_0},
+        },
+        Threading::CreateEventW,
+        Variant::VT_BLOB,
+    },
+};
+use windows_core::{HRESULT, Interface, PCWSTR, Ref, implement};
+
+use crate::{
+    services::audio::source::{AudioSource, SourceFormat, SourceTarget},
+    utils::process::{OwnedHandle, process_path},
+};
+
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+
+/// Process loopback clients can't report a mix format or device period, so we pick our own.
+const PROCESS_LOOPBACK_FORMAT: SourceFormat = SourceFormat { sample_rate: 48000.0, channels: 2 };
+const PROCESS_LOOPBACK_PERIOD: Duration = Duration::from_millis(10);
+
+/// Opens a capture for `target`, falling back to the full mix when the process can't be
+/// captured on its own (older Windows builds, or the process already exited).
+pub fn open_source(target: SourceTarget) -> Result<WasapiSource> {
+    match target {
+        SourceTarget::Mix => WasapiSource::system_loopback(),
+        SourceTarget::Process(pid) => WasapiSource::process_loopback(pid).or_else(|e| {
+            eprintln!("[AudioSpectrum] Falling back to full mix for {pid}: {e}");
+            WasapiSource::system_loopback()
+        }),
+    }
+}
+
+/// Finds the process rendering audio for the GSMTC session `app_id` by matching its
+/// AUMID against the executables behind the default endpoint's audio sessions.
+pub fn resolve_process(app_id: &str) -> Option<u32> {
+    let app_id = app_id.to_lowercase();
+
+    let sessions = unsafe {
+        let enumerator: IMMDeviceEnumerator =
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL).ok()?;
+        let device = enumerator.GetDefaultAudioEndpoint(eRender, eConsole).ok()?;
+        let manager: IAudioSessionManager2 = device.Activate(CLSCTX_ALL, None).ok()?;
+        manager.GetSessionEnumerator().ok()?
+    };
+
+    let count = unsafe { sessions.GetCount().ok()? };
+    let mut fallback = None;
+
+    for idx in 0..count {
+        let Ok(session) = (unsafe { sessions.GetSession(idx) }) else {
+            continue;
+        };
+        let Ok(session) = session.cast::<IAudioSessionControl2>() else {
+            continue;
+        };
+
+        let pid = unsafe { session.GetProcessId().unwrap_or(0) };
+        if pid == 0 {
+            continue;
+        }
+
+        let Some(stem) = process_stem(pid) else {
+            continue;
+        };
+        if !app_id.contains(&stem) {
+            continue;
+        }
+
+        // a browser can own several sessions; the one actually playing is the one we want
+        if unsafe { session.GetState() }.is_ok_and(|state| state == AudioSessionStateActive) {
+            return Some(pid);
+        }
+        fallback.get_or_insert(pid);
+    }
+
+    fallback
+}
+
+fn process_stem(pid: u32) -> Option<String> {
+    let path = process_path(pid)?;
+    let stem = Path::new(&path).file_stem()?.to_string_lossy().to_lowercase();
+
+    (!stem.is_empty()).then_some(stem)
+}
+
+/// Shared-mode loopback capture, either of the default render endpoint (normalized for the
+/// endpoint volume so the visualizers don't shrink with the volume slider) or of a single
+/// process tree.
+pub struct WasapiSource {
+    target: SourceTarget,
+
+    audio_client: IAudioClient,
+    capture_client: IAudioCaptureClient,
+    volume_control: Option<IAudioEndpointVolume>,
+    device_watch: Option<DeviceWatch>,
+    _event: Option<OwnedHandle>,
+
+    format: SourceFormat,
+    is_float: bool,
+    period: Duration,
+}
+
+struct DeviceWatch {
+    enumerator: IMMDeviceEnumerator,
+    notifier: IMMNotificationClient,
+    rx: mpsc::Receiver<()>,
+}
+
+impl Drop for DeviceWatch {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.enumerator.UnregisterEndpointNotificationCallback(&self.notifier);
+        }
+    }
+}
+
+impl WasapiSource {
+    pub fn system_loopback() -> Result<Self> {
+        let enumerator: IMMDeviceEnumerator =
+            unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? };
+        let device = unsafe { enumerator.GetDefaultAudioEndpoint(eRender, eConsole)? };
+
+        let audio_client: IAudioClient = unsafe { device.Activate(CLSCTX_ALL, None)? };
+        let volume_control: IAudioEndpointVolume = unsafe { device.Activate(CLSCTX_ALL, None)? };
+
+        let pwfx_ptr = unsafe { audio_client.GetMixFormat()? };
+        let mix_format: WAVEFORMATEX = unsafe { *pwfx_ptr };
+
+        let format = SourceFormat {
+            sample_rate: mix_format.nSamplesPerSec as f32,
+            channels: mix_format.nChannels as usize,
+        };
+        let is_float = mix_format.wBitsPerSample == 32;
+
+        let mut default_period: i64 = 0;
+        let mut minimum_period: i64 = 0;
+        unsafe {
+            audio_client.GetDevicePeriod(Some(&mut default_period), Some(&mut minimum_period))?;
+        }
+
+        unsafe {
+            audio_client.Initialize(
+                AUDCLNT_SHAREMODE_SHARED,
+                AUDCLNT_STREAMFLAGS_LOOPBACK,
+                default_period,
+                0,
+                pwfx_ptr,
+                None,
+            )?;
+
+            CoTaskMemFree(Some(pwfx_ptr as *const _));
+        }
+
+        // capture starts stopped; the scheduler resumes it once there's something to analyze
+        let capture_client: IAudioCaptureClient = unsafe { audio_client.GetService()? };
+
+        let (device_change_tx, device_change_rx) = mpsc::sync_channel::<()>(1);
+        let notifier: IMMNotificationClient = DeviceChangeNotifier { tx: device_change_tx }.into();
+        unsafe {
+            enumerator.RegisterEndpointNotificationCallback(&notifier)?;
+        }
+
+        Ok(Self {
+            target: SourceTarget::Mix,
+            audio_client,
+            capture_client,
+            volume_control: Some(volume_control),
+            device_watch: Some(DeviceWatch { enumerator, notifier, rx: device_change_rx }),
+            _event: None,
+            format,
+            is_float,
+            period: Duration::from_nanos((default_period * 100) as u64),
+        })
+    }
+
+    /// Captures `pid` and its children through the process loopback virtual device
+    /// (Windows 10 2004+). This taps the streams before the endpoint volume is applied,
+    /// and follows default device changes on its own.
+    pub fn process_loopback(pid: u32) -> Result<Self> {
+        let mut activation_params = AUDIOCLIENT_ACTIVATION_PARAMS {
+            ActivationType: AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK,
+            Anonymous: AUDIOCLIENT_ACTIVATION_PARAMS_0 {
+                ProcessLoopbackParams: AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS {
+                    TargetProcessId: pid,
+                    ProcessLoopbackMode: PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE,
+                },
+            },
+        };
+
+        let prop_variant = PROPVARIANT {
+            Anonymous: PROPVARIANT_0 {
+                Anonymous: std::mem::ManuallyDrop::new(PROPVARIANT_0_0 {
+                    vt: VT_BLOB,
+                    Anonymous: PROPVARIANT_0_0_0 {
+                        blob: BLOB {
+                            cbSize: size_of::<AUDIOCLIENT_ACTIVATION_PARAMS>() as u32,
+                            pBlobData: &mut activation_params as *mut _ as *mut u8,
+                        },
+                    },
+                    ..Default::default()
+                }),
+            },
+        };
+
+        let (done_tx, done_rx) = mpsc::sync_channel::<()>(1);
+        let handler: IActivateAudioInterfaceCompletionHandler =
+            ActivationHandler { tx: done_tx }.into();
+
+        let operation = unsafe {
+            ActivateAudioInterfaceAsync(
+                VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+                &IAudioClient::IID,
+                Some(&prop_variant as *const _),
+                &handler,
+            )?
+        };
+
+        done_rx
+            .recv_timeout(Duration::from_secs(2))
+            .map_err(|_| anyhow!("Process loopback activation timed out"))?;
+
+        let mut activate_result = HRESULT(0);
+        let mut activated = None;
+        unsafe {
+            operation.GetActivateResult(&mut activate_result, &mut activated)?;
+        }
+        activate_result.ok()?;
+
+        let audio_client: IAudioClient =
+            activated.ok_or_else(|| anyhow!("No audio client activated"))?.cast()?;
+
+        let format = PROCESS_LOOPBACK_FORMAT;
+        let block_align = (format.channels * size_of::<f32>()) as u16;
+        let wave_format = WAVEFORMATEX {
+            wFormatTag: WAVE_FORMAT_IEEE_FLOAT,
+            nChannels: format.channels as u16,
+            nSamplesPerSec: format.sample_rate as u32,
+            nAvgBytesPerSec: format.sample_rate as u32 * block_align as u32,
+            nBlockAlign: block_align,
+            wBitsPerSample: 32,
+            cbSize: 0,
+        };
+
+        // the virtual device refuses to start without an event handle, even though we poll
+        let event = OwnedHandle(unsafe { CreateEventW(None, false, false, PCWSTR::null())? });
+
+        unsafe {
+            audio_client.Initialize(
+                AUDCLNT_SHAREMODE_SHARED,
+                AUDCLNT_STREAMFLAGS_LOOPBACK
+                    | AUDCLNT_STREAMFLAGS_EVENTCALLBACK
+                    | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM,
+                (PROCESS_LOOPBACK_PERIOD.as_nanos() / 100) as i64 * 2,
+                0,
+                &wave_format,
+                None,
+            )?;
+            audio_client.SetEventHandle(event.0)?;
+        }
+
+        let capture_client: IAudioCaptureClient = unsafe { audio_client.GetService()? };
+
+        Ok(Self {
+            target: SourceTarget::Process(pid),
+            audio_client,
+            capture_client,
+            volume_control: None,
+            device_watch: None,
+            _event: Some(event),
+            format,
+            is_float: true,
+            period: PROCESS_LOOPBACK_PERIOD,
+        })
+    }
+}
+
+impl AudioSource for WasapiSource {
+    fn format(&self) -> SourceFormat {
+        self.format
+    }
+
+    fn target(&self) -> SourceTarget {
+        self.target
+    }
+
+    fn period(&self) -> Duration {
+        self.period
+    }
+
+    fn read(&mut self, out: &mut Vec<f32>) -> Result<()> {
+        if let Some(watch) = &self.device_watch
+            && watch.rx.try_recv().is_ok()
+        {
+            while watch.rx.try_recv().is_ok() {}
+            bail!("Default audio device changed");
+        }
+
+        let volume_multiplier = match &self.volume_control {
+            Some(volume_control) => {
+                let is_muted = unsafe { volume_control.GetMute()?.as_bool() };
+                let system_volume = unsafe { volume_control.GetMasterVolumeLevelScalar()? };
+
+                if is_muted || system_volume < 0.01 { 0.0f32 } else { 1.0f32 / system_volume }
+            }
+            None => 1.0,
+        };
+
+        let channels = self.format.channels;
+
+        let mut packet_size = unsafe { self.capture_client.GetNextPacketSize()? };
+        let mut loop_fuse = 0;
+
+        while packet_size > 0 {
+            loop_fuse += 1;
+            if loop_fuse > 64 {
+                break;
+            }
+
+            let mut data_ptr: *mut u8 = std::ptr::null_mut();
+            let mut frames: u32 = 0;
+            let mut flags: u32 = 0;
+
+            unsafe {
+                self.capture_client.GetBuffer(
+                    &mut data_ptr,
+                    &mut frames,
+                    &mut flags,
+                    None,
+                    None,
+                )?;
+            }
+
+            if frames == 0 {
+                unsafe {
+                    let _ = self.capture_client.ReleaseBuffer(0);
+                }
+                break;
+            }
+
+            let total_samples = frames as usize * channels;
+
+            if (flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32) != 0 || data_ptr.is_null() {
+                out.extend(std::iter::repeat_n(0.0, total_samples));
+            } else if self.is_float {
+                let samples =
+                    unsafe { std::slice::from_raw_parts(data_ptr as *const f32, total_samples) };
+                out.extend(samples.iter().map(|&sample| sample * volume_multiplier));
+            } else {
+                let samples =
+                    unsafe { std::slice::from_raw_parts(data_ptr as *const i16, total_samples) };
+                out.extend(
+                    samples.iter().map(|&sample| sample as f32 / 32768.0 * volume_multiplier),
+                );
+            }
+
+            unsafe {
+                self.capture_client.ReleaseBuffer(frames)?;
+            }
+            packet_size = unsafe { self.capture_client.GetNextPacketSize()? };
+        }
+
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        unsafe {
+            self.audio_client.Stop()?;
+        }
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        unsafe {
+            // drop whatever queued up while stopped so analysis picks up from "now"
+            self.audio_client.Reset()?;
+            self.audio_client.Start()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WasapiSource {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.audio_client.Stop();
+        }
+    }
+}
+
+#[implement(IActivateAudioInterfaceCompletionHandler)]
+struct ActivationHandler {
+    tx: mpsc::SyncSender<()>,
+}
+
+impl IActivateAudioInterfaceCompletionHandler_Impl for ActivationHandler_Impl {
+    fn ActivateCompleted(
+        &self,
+        _: Ref<IActivateAudioInterfaceAsyncOperation>,
+    ) -> windows_core::Result<()> {
+        let _ = self.tx.try_send(());
+        Ok(())
+    }
+}
+
+#[implement(IMMNotificationClient)]
+struct DeviceChangeNotifier {
+    tx: mpsc::SyncSender<()>,
+}
+
+impl IMMNotificationClient_Impl for DeviceChangeNotifier_Impl {
+    fn OnDefaultDeviceChanged(
+        &self,
+        flow: windows::Win32::Media::Audio::EDataFlow,
+        _: windows::Win32::Media::Audio::ERole,
+        _: &windows_core::PCWSTR,
+    ) -> windows_core::Result<()> {
+        if flow == eRender {
+            let _ = self.tx.try_send(());
+        }
+        Ok(())
+    }
+
+    fn OnDeviceAdded(&self, _: &windows_core::PCWSTR) -> windows_core::Result<()> {
+        Ok(())
+    }
+    fn OnDeviceRemoved(&self, _: &windows_core::PCWSTR) -> windows_core::Result<()> {
+        Ok(())
+    }
+    fn OnDeviceStateChanged(
+        &self,
+        _: &windows_core::PCWSTR,
+        _: windows::Win32::Media::Audio::DEVICE_STATE,
+    ) -> windows_core::Result<()> {
+        Ok(())
+    }
+    fn OnPropertyValueChanged(
+        &self,
+        _: &windows_core::PCWSTR,
+        _: &windows::Win32::Foundation::PROPERTYKEY,
+    ) -> windows_core::Result<()> {
+        Ok(())
+    }
+}
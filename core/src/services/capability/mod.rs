@@ -0,0 +1,75 @@
+#[cfg(target_os = "linux")]
+mod pipewire;
+#[cfg(target_os = "linux")]
+mod procfs;
+#[cfg(windows)]
+mod registry;
+
+#[cfg(target_os = "linux")]
+pub use pipewire::{ScreenCaptureService, screencast_processes};
+#[cfg(target_os = "linux")]
+pub use procfs::{CameraService, camera_processes};
+#[cfg(windows)]
+pub use registry::CapabilityService;
+
+use crate::{AppUsage, CoreEvent, bus::EventSender, privacy::Sensor, runtime::RuntimeState};
+
+/// A privacy-sensitive capability whose users get an indicator.
+pub trait Capability: Send + Sync + 'static {
+    /// Key under `CapabilityAccessManager\ConsentStore` on Windows.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    const NAME: &'static str;
+    const SENSOR: Sensor;
+
+    fn event(apps: Vec<AppUsage>) -> CoreEvent;
+}
+
+pub struct Camera;
+pub struct ScreenCapture;
+// nothing reports location use on Linux without talking to GeoClue over D-Bus
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct Location;
+
+impl Capability for Camera {
+    const NAME: &'static str = "webcam";
+    const SENSOR: Sensor = Sensor::Camera;
+
+    fn event(apps: Vec<AppUsage>) -> CoreEvent {
+        if apps.is_empty() { CoreEvent::CameraInactive } else { CoreEvent::CameraActive(apps) }
+    }
+}
+
+impl Capability for ScreenCapture {
+    const NAME: &'static str = "graphicsCaptureProgrammatic";
+    const SENSOR: Sensor = Sensor::ScreenCapture;
+
+    fn event(apps: Vec<AppUsage>) -> CoreEvent {
+        if apps.is_empty() {
+            CoreEvent::ScreenCaptureInactive
+        } else {
+            CoreEvent::ScreenCaptureActive(apps)
+        }
+    }
+}
+
+impl Capability for Location {
+    const NAME: &'static str = "location";
+    const SENSOR: Sensor = Sensor::Location;
+
+    fn event(apps: Vec<AppUsage>) -> CoreEvent {
+        if apps.is_empty() { CoreEvent::LocationInactive } else { CoreEvent::LocationActive(apps) }
+    }
+}
+
+#[cfg(windows)]
+pub type CameraService = CapabilityService<Camera>;
+#[cfg(windows)]
+pub type ScreenCaptureService = CapabilityService<ScreenCapture>;
+#[cfg(windows)]
+pub type LocationService = CapabilityService<Location>;
+
+/// Stores the apps now using `C` and tells the UI.
+fn publish<C: Capability>(tx: &EventSender, runtime: &RuntimeState, apps: Vec<AppUsage>) {
+    runtime.set_sensor_apps(C::SENSOR, apps.clone());
+    let _ = tx.send(C::event(apps));
+}
@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    process::{Command, Stdio},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{ScreenCapture, publish};
+use crate::{
+    bus::EventSender,
+    runtime::RuntimeState,
+    services::Service,
+    utils::{child::ChildLines, procfs::describe_process},
+};
+
+/// Without PipeWire the watcher can't start at all, so it retries less and less often.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Watches for apps reading a screencast stream that a desktop portal set up.
+pub struct ScreenCaptureService {
+    active: Vec<u32>,
+}
+
+#[async_trait]
+impl Service for ScreenCaptureService {
+    fn new() -> Self {
+        Self { active: Vec::new() }
+    }
+
+    async fn run(mut self, tx: EventSender, runtime: Arc<RuntimeState>) {
+        std::thread::spawn(move || {
+            let mut delay = Duration::from_secs(1);
+
+            loop {
+                let started = Instant::now();
+                if let Err(e) = self.watch(&tx, &runtime) {
+                    eprintln!("[ScreenCaptureService] Reinitializing after: {e}");
+                }
+
+                if started.elapsed() > MAX_RETRY_DELAY {
+                    delay = Duration::from_secs(1);
+                }
+                std::thread::sleep(delay);
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+        });
+    }
+}
+
+impl ScreenCaptureService {
+    /// Rescans whenever PipeWire's object graph changes. Only returns on error.
+    fn watch(&mut self, tx: &EventSender, runtime: &RuntimeState) -> Result<()> {
+        let changes = ChildLines::spawn(Command::new("pw-dump").arg("--monitor"))?;
+        let mut initial = true;
+
+        // the full graph first, then an array of the changed objects per change, each
+        // closed by a bracket on a line of its own. Changes only carry what changed, so
+        // rather than patching a copy of the graph, take a fresh dump.
+        for line in changes {
+            if line? == "]" {
+                self.evaluate(screencast_clients()?, tx, runtime, initial);
+                initial = false;
+            }
+        }
+
+        bail!("pw-dump --monitor exited")
+    }
+
+    fn evaluate(
+        &mut self,
+        clients: Vec<(u32, Option<String>)>,
+        tx: &EventSender,
+        runtime: &RuntimeState,
+        initial: bool,
+    ) {
+        let pids: Vec<u32> = clients.iter().map(|(pid, _)| *pid).collect();
+        if pids == self.active && !initial {
+            return;
+        }
+
+        let apps = clients
+            .into_iter()
+            .map(|(pid, name)| describe_process(Path::new("/proc"), pid, name))
+            .collect();
+
+        publish::<ScreenCapture>(tx, runtime, apps);
+        self.active = pids;
+    }
+}
+
+#[derive(Deserialize)]
+struct Object {
+    id: u32,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    info: Option<Info>,
+}
+
+#[derive(Deserialize)]
+struct Info {
+    #[serde(default)]
+    props: HashMap<String, Value>,
+    #[serde(rename = "output-node-id")]
+    output_node: Option<u32>,
+    #[serde(rename = "input-node-id")]
+    input_node: Option<u32>,
+}
+
+impl Object {
+    fn prop(&self, key: &str) -> Option<&Value> {
+        self.info.as_ref()?.props.get(key)
+    }
+
+    fn prop_str(&self, key: &str) -> Option<&str> {
+        self.prop(key)?.as_str()
+    }
+
+    fn pid(&self) -> Option<u32> {
+        match self.prop("application.process.id")? {
+            Value::Number(pid) => pid.as_u64()?.try_into().ok(),
+            Value::String(pid) => pid.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Portal screencasts are video sources that, unlike cameras, aren't backed by a device.
+    fn is_screencast(&self) -> bool {
+        self.kind == "PipeWire:Interface:Node"
+            && self.prop_str("media.class") == Some("Video/Source")
+            && self.prop("device.id").is_none()
+            && self.prop("device.api").is_none()
+    }
+}
+
+/// Processes currently linked to a screencast node, sorted by pid, with their
+/// `application.name`.
+fn screencast_clients() -> Result<Vec<(u32, Option<String>)>> {
+    let output =
+        Command::new("pw-dump").stderr(Stdio::null()).output().context("Failed to run pw-dump")?;
+
+    if !output.status.success() {
+        bail!("pw-dump exited with {}", output.status);
+    }
+
+    let objects: Vec<Object> = serde_json::from_slice(&output.stdout)?;
+    Ok(linked_clients(&objects))
+}
+
+fn linked_clients(objects: &[Object]) -> Vec<(u32, Option<String>)> {
+    let sources: Vec<u32> =
+        objects.iter().filter(|object| object.is_screencast()).map(|object| object.id).collect();
+
+    let mut clients: Vec<(u32, Option<String>)> = objects
+        .iter()
+        .filter(|object| object.kind == "PipeWire:Interface:Link")
+        .filter_map(|link| {
+            let info = link.info.as_ref()?;
+            if !sources.contains(&info.output_node?) {
+                return None;
+            }
+
+            let input = objects.iter().find(|object| Some(object.id) == info.input_node)?;
+            Some((input.pid()?, input.prop_str("application.name").map(str::to_string)))
+        })
+        .collect();
+
+    clients.sort_by_key(|(pid, _)| *pid);
+    clients.dedup_by_key(|(pid, _)| *pid);
+    clients
+}
+
+/// Pids of the processes currently receiving a portal screencast, sorted.
+pub fn screencast_processes() -> Result<Vec<u32>> {
+    Ok(screencast_clients()?.into_iter().map(|(pid, _)| pid).collect())
+}
@@ -0,0 +1,227 @@
+use std::{
+    ffi::CString,
+    fs, io,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+use super::{Camera, publish};
+use crate::{
+    bus::EventSender, runtime::RuntimeState, services::Service, utils::procfs::describe_process,
+};
+
+/// How often to rescan without inotify, and how long to trust it before a safety rescan.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+/// Opens and closes come in bursts while an app probes devices; settle before scanning.
+const SETTLE: Duration = Duration::from_millis(80);
+
+pub struct CameraService {
+    proc_root: PathBuf,
+    dev_root: PathBuf,
+    active: Vec<u32>,
+}
+
+#[async_trait]
+impl Service for CameraService {
+    fn new() -> Self {
+        Self {
+            proc_root: PathBuf::from("/proc"),
+            dev_root: PathBuf::from("/dev"),
+            active: Vec::new(),
+        }
+    }
+
+    async fn run(mut self, tx: EventSender, runtime: Arc<RuntimeState>) {
+        std::thread::spawn(move || {
+            let watcher = DeviceWatcher::new(&self.dev_root)
+                .inspect_err(|e| eprintln!("[CameraService] Polling, inotify unavailable: {e}"))
+                .ok();
+
+            self.evaluate(&tx, &runtime, true);
+
+            loop {
+                match &watcher {
+                    Some(watcher) => {
+                        if let Ok(true) = watcher.wait(RESCAN_INTERVAL) {
+                            std::thread::sleep(SETTLE);
+                            watcher.drain();
+                        }
+                    }
+                    None => std::thread::sleep(RESCAN_INTERVAL),
+                }
+
+                self.evaluate(&tx, &runtime, false);
+            }
+        });
+    }
+}
+
+impl CameraService {
+    fn evaluate(&mut self, tx: &EventSender, runtime: &RuntimeState, initial: bool) {
+        let pids = camera_processes(&self.proc_root, &self.dev_root);
+        if pids == self.active && !initial {
+            return;
+        }
+
+        let apps: Vec<_> =
+            pids.iter().map(|&pid| describe_process(&self.proc_root, pid, None)).collect();
+
+        publish::<Camera>(tx, runtime, apps);
+        self.active = pids;
+    }
+}
+
+/// Processes under `proc_root` holding a `<dev_root>/video*` descriptor, sorted. Processes
+/// we aren't allowed to inspect are skipped.
+pub fn camera_processes(proc_root: &Path, dev_root: &Path) -> Vec<u32> {
+    let Ok(entries) = fs::read_dir(proc_root) else {
+        return Vec::new();
+    };
+
+    let mut pids: Vec<u32> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .filter(|pid: &u32| {
+            holds_video_device(&proc_root.join(pid.to_string()).join("fd"), dev_root)
+        })
+        .collect();
+
+    pids.sort_unstable();
+    pids
+}
+
+fn holds_video_device(fd_dir: &Path, dev_root: &Path) -> bool {
+    let Ok(fds) = fs::read_dir(fd_dir) else {
+        return false;
+    };
+
+    fds.flatten().any(|fd| {
+        fs::read_link(fd.path()).is_ok_and(|target| {
+            target.file_name().is_some_and(|name| name.as_bytes().starts_with(b"video"))
+                && target.parent() == Some(dev_root)
+        })
+    })
+}
+
+/// inotify on the device directory; opening or closing a video device wakes the scan up.
+struct DeviceWatcher {
+    fd: OwnedFd,
+}
+
+impl DeviceWatcher {
+    fn new(dev_root: &Path) -> io::Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let path = CString::new(dev_root.as_os_str().as_bytes())?;
+        let mask = libc::IN_OPEN | libc::IN_CLOSE | libc::IN_CREATE | libc::IN_DELETE;
+        if unsafe { libc::inotify_add_watch(fd.as_raw_fd(), path.as_ptr(), mask) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { fd })
+    }
+
+    /// Waits up to `timeout` for activity on a video device.
+    fn wait(&self, timeout: Duration) -> io::Result<bool> {
+        let mut pollfd = libc::pollfd { fd: self.fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+
+        loop {
+            let ready = unsafe { libc::poll(&mut pollfd, 1, timeout.as_millis() as i32) };
+            if ready < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if ready == 0 {
+                return Ok(false);
+            }
+
+            // the rest of /dev is busy too (ttys, /dev/null), so only wake for video devices
+            if self.drain() {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Reads all queued events, returning whether any were about a video device.
+    fn drain(&self) -> bool {
+        let mut buffer = [0u8; 4096];
+        let mut touched = false;
+
+        loop {
+            let read = unsafe {
+                libc::read(self.fd.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len())
+            };
+            if read <= 0 {
+                return touched;
+            }
+
+            let mut offset = 0;
+            while offset + size_of::<libc::inotify_event>() <= read as usize {
+                let event: libc::inotify_event =
+                    unsafe { std::ptr::read_unaligned(buffer[offset..].as_ptr().cast()) };
+                let name_start = offset + size_of::<libc::inotify_event>();
+                let name = &buffer[name_start..name_start + event.len as usize];
+
+                touched |= name.starts_with(b"video");
+                offset = name_start + event.len as usize;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+
+    #[test]
+    fn processes_holding_a_video_device_are_found() {
+        let root = std::env::temp_dir().join(format!("lumen-procfs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (proc_root, dev_root) = (root.join("proc"), root.join("dev"));
+        fs::create_dir_all(&dev_root).unwrap();
+
+        let process = |pid: u32, comm: &str, fds: &[PathBuf]| {
+            let dir = proc_root.join(pid.to_string());
+            fs::create_dir_all(dir.join("fd")).unwrap();
+            fs::write(dir.join("comm"), format!("{comm}\n")).unwrap();
+            symlink(format!("/usr/bin/{comm}"), dir.join("exe")).unwrap();
+            for (fd, target) in fds.iter().enumerate() {
+                symlink(target, dir.join("fd").join(fd.to_string())).unwrap();
+            }
+        };
+
+        process(4211, "cheese", &[dev_root.join("null"), dev_root.join("video0")]);
+        process(17, "obs", &[dev_root.join("video2")]);
+        process(980, "bash", &[dev_root.join("null"), dev_root.join("tty1")]);
+        // a video device somewhere other than the device directory doesn't count
+        process(3301, "ffmpeg", &[root.join("video0")]);
+        // neither do the process-less entries of /proc
+        fs::create_dir_all(proc_root.join("self").join("fd")).unwrap();
+        symlink(dev_root.join("video0"), proc_root.join("self").join("fd").join("0")).unwrap();
+        // nor processes without descriptors we can read
+        fs::create_dir_all(proc_root.join("1")).unwrap();
+
+        assert_eq!(camera_processes(&proc_root, &dev_root), vec![17, 4211]);
+        assert_eq!(camera_processes(&proc_root, Path::new("/dev")), Vec::<u32>::new());
+        assert_eq!(camera_processes(&root.join("missing"), &dev_root), Vec::<u32>::new());
+
+        let app = describe_process(&proc_root, 4211, None);
+        assert_eq!((app.name.as_str(), app.exe_path.as_str()), ("cheese", "/usr/bin/cheese"));
+        assert_eq!(describe_process(&proc_root, 4211, Some("Cheese".into())).name, "Cheese");
+        assert_eq!(describe_process(&proc_root, 1, None).name, "1");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
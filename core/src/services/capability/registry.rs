@@ -0,0 +1,260 @@
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tokio::sync::watch;
+use windows::Win32::{
+    Foundation::{HANDLE, WIN32_ERROR},
+    System::{
+        Registry::{
+            HKEY, HKEY_CURRENT_USER, REG_NOTIFY_CHANGE_LAST_SET, REG_NOTIFY_CHANGE_NAME,
+            RegCloseKey, RegNotifyChangeKeyValue,
+        },
+        Threading::{CreateEventW, ResetEvent},
+    },
+};
+use windows_core::HSTRING;
+use winreg::RegKey;
+
+use super::{Capability, publish};
+use crate::{
+    AppUsage,
+    bus::EventSender,
+    runtime::RuntimeState,
+    services::Service,
+    utils::{
+        icon::resolve_app_icon,
+        name::resolve_name_from_aumid,
+        process::{describe_process, find_process},
+    },
+};
+
+const CONSENT_STORE: &str =
+    "Software\\Microsoft\\Windows\\CurrentVersion\\CapabilityAccessManager\\ConsentStore";
+/// How often to rescan when the consent store can't be watched.
+const FALLBACK_POLL: Duration = Duration::from_secs(5);
+
+/// Watches the apps Windows records as using capability `C` under `ConsentStore`.
+pub struct CapabilityService<C: Capability> {
+    active: Vec<ConsentApp>,
+    capability: PhantomData<C>,
+}
+
+#[async_trait]
+impl<C: Capability> Service for CapabilityService<C> {
+    fn new() -> Self {
+        Self { active: Vec::new(), capability: PhantomData }
+    }
+
+    async fn run(mut self, tx: EventSender, runtime: Arc<RuntimeState>) {
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to build capability runtime");
+
+            rt.block_on(async move {
+                if let Err(e) = run_event_driven(&mut self, tx, runtime).await {
+                    eprintln!("[CapabilityService] Fatal error watching {}: {e:?}", C::NAME);
+                }
+            })
+        });
+    }
+}
+
+struct SessionHandlers {
+    hkcu_root: HKEY,
+    event_handle: HANDLE,
+}
+
+unsafe impl Send for SessionHandlers {}
+unsafe impl Sync for SessionHandlers {}
+
+impl SessionHandlers {
+    fn new(notify_tx: watch::Sender<()>, capability: &str) -> Option<Self> {
+        unsafe {
+            let path = HSTRING::from(format!("{CONSENT_STORE}\\{capability}"));
+            let mut hkcu_root = HKEY::default();
+
+            let status = windows::Win32::System::Registry::RegOpenKeyExW(
+                HKEY_CURRENT_USER,
+                &path,
+                Some(0),
+                windows::Win32::System::Registry::KEY_NOTIFY,
+                &mut hkcu_root,
+            );
+
+            if status != WIN32_ERROR(0) {
+                return None;
+            }
+
+            let event_handle = CreateEventW(None, true, false, None).ok()?;
+
+            let _ = RegNotifyChangeKeyValue(
+                hkcu_root,
+                true,
+                REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
+                Some(event_handle),
+                true,
+            );
+
+            let loop_root_raw = hkcu_root.0 as usize;
+            let loop_handle_raw = event_handle.0 as usize;
+
+            tokio::task::spawn_blocking(move || {
+                let thread_root = HKEY(loop_root_raw as *mut std::ffi::c_void);
+                let thread_handle = HANDLE(loop_handle_raw as *mut std::ffi::c_void);
+
+                loop {
+                    let wait_result = windows::Win32::System::Threading::WaitForSingleObject(
+                        thread_handle,
+                        windows::Win32::System::Threading::INFINITE,
+                    );
+
+                    if wait_result.0 != 0 {
+                        break;
+                    }
+
+                    let _ = notify_tx.send(());
+
+                    let _ = ResetEvent(thread_handle);
+
+                    let _ = RegNotifyChangeKeyValue(
+                        thread_root,
+                        true,
+                        REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
+                        Some(thread_handle),
+                        true,
+                    );
+                }
+            });
+
+            Some(Self { hkcu_root, event_handle })
+        }
+    }
+}
+
+impl Drop for SessionHandlers {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.event_handle.is_invalid() {
+                let _ = windows::Win32::Foundation::CloseHandle(self.event_handle);
+            }
+            if !self.hkcu_root.is_invalid() {
+                let _ = RegCloseKey(self.hkcu_root);
+            }
+        }
+    }
+}
+
+async fn run_event_driven<C: Capability>(
+    service: &mut CapabilityService<C>,
+    tx: EventSender,
+    runtime: Arc<RuntimeState>,
+) -> anyhow::Result<()> {
+    let (notify_tx, mut notify_rx) = watch::channel(());
+
+    let _handlers = SessionHandlers::new(notify_tx, C::NAME);
+    // without a watch the sender is gone (as it is once the watch thread gives up), and
+    // waiting on it would return straight away
+    let mut watching = true;
+
+    let hkcu = RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
+    let mut cached_app_paths = prebuild_registry_cache(&hkcu, C::NAME);
+
+    let initial_apps = evaluate_consent_state(&cached_app_paths);
+    publish::<C>(&tx, &runtime, describe_all(&initial_apps).await);
+    service.active = initial_apps;
+
+    loop {
+        if watching && notify_rx.changed().await.is_ok() {
+            tokio::time::sleep(Duration::from_millis(80)).await;
+            while notify_rx.has_changed().unwrap_or(false) {
+                notify_rx.mark_unchanged();
+            }
+        } else {
+            if watching {
+                eprintln!("[CapabilityService] Polling {}, can't watch the consent store", C::NAME);
+                watching = false;
+            }
+            tokio::time::sleep(FALLBACK_POLL).await;
+        }
+
+        let mut current = evaluate_consent_state(&cached_app_paths);
+
+        // nothing we know about changed, so the change came from an app we haven't cached yet
+        if current == service.active {
+            cached_app_paths = prebuild_registry_cache(&hkcu, C::NAME);
+            current = evaluate_consent_state(&cached_app_paths);
+        }
+
+        if current != service.active {
+            publish::<C>(&tx, &runtime, describe_all(&current).await);
+            service.active = current;
+        }
+    }
+}
+
+struct CachedKey {
+    key: RegKey,
+    app: ConsentApp,
+}
+
+/// A `ConsentStore\<capability>` entry: either a package family name, or an exe path with `#`
+/// in place of path separators.
+#[derive(Debug, Clone, PartialEq)]
+struct ConsentApp {
+    id: String,
+    packaged: bool,
+}
+
+fn prebuild_registry_cache(hkcu: &RegKey, capability: &str) -> Vec<CachedKey> {
+    let mut cache = Vec::with_capacity(32);
+
+    for (kind, packaged) in [("NonPackaged", false), ("Packaged", true)] {
+        let base_path = format!("{CONSENT_STORE}\\{capability}\\{kind}");
+
+        if let Ok(root_key) = hkcu.open_subkey(base_path) {
+            for entry in root_key.enum_keys().flatten() {
+                if let Ok(app_key) = root_key.open_subkey(&entry) {
+                    cache.push(CachedKey { key: app_key, app: ConsentApp { id: entry, packaged } });
+                }
+            }
+        }
+    }
+    cache
+}
+
+fn evaluate_consent_state(cached_paths: &[CachedKey]) -> Vec<ConsentApp> {
+    let mut apps = Vec::new();
+    for target in cached_paths {
+        if let Ok(stop) = target.key.get_value::<u64, _>("LastUsedTimeStop") {
+            if stop == 0 {
+                apps.push(target.app.clone());
+            }
+        }
+    }
+    apps
+}
+
+async fn describe_all(apps: &[ConsentApp]) -> Vec<AppUsage> {
+    let mut usage = Vec::with_capacity(apps.len());
+
+    for app in apps {
+        if app.packaged {
+            // the store only keeps the package family name; "App" is the usual entry point
+            let aumid = format!("{}!App", app.id);
+            usage.push(AppUsage {
+                pid: 0,
+                exe_path: String::new(),
+                name: resolve_name_from_aumid(&aumid),
+                icon: resolve_app_icon(&aumid).await,
+            });
+        } else {
+            let exe_path = app.id.replace('#', "\\");
+            let pid = find_process(&exe_path).unwrap_or(0);
+            usage.push(describe_process(pid, exe_path).await);
+        }
+    }
+
+    usage
+}
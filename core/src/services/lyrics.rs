@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    CoreEvent, Lyrics, LyricsIndex, bus::EventSender, runtime::RuntimeState, services::Service,
+    utils::lyrics_dir,
+};
+
+/// Loads the lyrics for whatever is playing, from the lyrics folder or else from the
+/// player. Only MPRIS players hand lyrics over; the Windows media session has none.
+pub struct LyricsService;
+
+#[async_trait]
+impl Service for LyricsService {
+    fn new() -> Self {
+        Self
+    }
+
+    async fn run(self, tx: EventSender, runtime: Arc<RuntimeState>) {
+        std::thread::spawn(move || {
+            let dir = runtime.config.lyrics_dir.clone().unwrap_or_else(lyrics_dir);
+            let mut index = LyricsIndex::build(&dir);
+            let mut seen = None;
+
+            // woken on every media update, most of which leave the track as it was
+            while runtime.lyrics_wake_rx.recv().is_ok() {
+                let track =
+                    runtime.media.read().unwrap().as_ref().map(|media| {
+                        (media.artist.clone(), media.title.clone(), media.lyrics.clone())
+                    });
+                if track == seen {
+                    continue;
+                }
+
+                if index.is_stale() {
+                    index = LyricsIndex::build(&dir);
+                }
+
+                let lyrics = track.as_ref().and_then(|(artist, title, from_player)| {
+                    match index.find(artist, title) {
+                        Some(path) => match std::fs::read_to_string(path) {
+                            Ok(source) => Some(Arc::new(Lyrics::parse(&source))),
+                            Err(e) => {
+                                eprintln!("[Lyrics] Failed to read {}: {e}", path.display());
+                                None
+                            }
+                        },
+                        // lyrics without timestamps parse to no lines, and aren't shown
+                        None => from_player
+                            .as_deref()
+                            .map(|source| Arc::new(Lyrics::parse(source)))
+                            .filter(|lyrics| !lyrics.lines.is_empty()),
+                    }
+                });
+
+                let had_lyrics = runtime.lyrics.read().unwrap().is_some();
+                if had_lyrics || lyrics.is_some() {
+                    *runtime.lyrics.write().unwrap() = lyrics;
+                    let _ = tx.send(CoreEvent::LyricsChanged);
+                }
+                seen = track;
+            }
+        });
+    }
+}
@@ -3,31 +3,31 @@ use std::{
     time::{Duration, SystemTime},
 };
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use async_trait::async_trait;
 use tokio::sync::watch;
 use windows::{
     Foundation::TypedEventHandler,
-    Media::Control::{
-        GlobalSystemMediaTransportControlsSession,
-        GlobalSystemMediaTransportControlsSessionManager,
-        GlobalSystemMediaTransportControlsSessionPlaybackStatus,
+    Media::{
+        Control::{
+            GlobalSystemMediaTransportControlsSession,
+            GlobalSystemMediaTransportControlsSessionManager,
+            GlobalSystemMediaTransportControlsSessionPlaybackStatus,
+        },
+        MediaPlaybackAutoRepeatMode,
     },
     Win32::System::Com::{COINIT_MULTITHREADED, CoInitializeEx},
 };
 
+use super::MediaService;
 use crate::{
-    CoreEvent, MediaState,
+    CoreEvent, MediaCapabilities, MediaCommand, MediaState, RepeatMode,
     bus::EventSender,
     runtime::RuntimeState,
     services::Service,
     utils::{artwork::extract_album_art, icon::resolve_app_icon, name::resolve_name_from_aumid},
 };
 
-pub struct MediaService {
-    current: Option<MediaState>,
-}
-
 #[async_trait]
 impl Service for MediaService {
     fn new() -> Self {
@@ -123,7 +123,7 @@ async fn run_event_driven(
         _handlers = SessionHandlers::new(session.clone(), &notify_tx).ok();
 
         if let Ok(initial_state) = build_media_state(&session).await {
-            *runtime.media.write().unwrap() = Some(initial_state.clone());
+            runtime.update_media(|media| media.report(Some(initial_state.clone())));
             service.current = Some(initial_state.clone());
             let _ = tx.send(CoreEvent::MediaStarted(initial_state));
         }
@@ -162,23 +162,7 @@ async fn run_event_driven(
             Err(_) => None,
         };
 
-        match (&service.current, &new) {
-            (None, Some(media)) => {
-                let _ = tx.send(CoreEvent::MediaStarted(media.clone()));
-            }
-            (Some(_), None) => {
-                let _ = tx.send(CoreEvent::MediaStopped);
-            }
-            (Some(old), Some(new)) => {
-                if old != new {
-                    let _ = tx.send(CoreEvent::TrackChanged(new.clone()));
-                }
-            }
-            _ => {}
-        }
-
-        *runtime.media.write().unwrap() = new.clone();
-        service.current = new;
+        service.publish(new, &tx, &runtime);
     }
 }
 
@@ -186,13 +170,35 @@ async fn build_media_state(
     session: &GlobalSystemMediaTransportControlsSession,
 ) -> Result<MediaState> {
     let props = session.TryGetMediaPropertiesAsync()?.await?;
-    let playback = session.GetPlaybackInfo()?.PlaybackStatus()?;
+    let info = session.GetPlaybackInfo()?;
+    let playback = info.PlaybackStatus()?;
+    let controls = info.Controls()?;
     let timeline = session.GetTimelineProperties()?;
 
     let duration_ms = timeline.EndTime()?.Duration as u64 / 10_000;
     let position_ms = timeline.Position()?.Duration as u64 / 10_000;
     let playing = playback == GlobalSystemMediaTransportControlsSessionPlaybackStatus::Playing;
 
+    // these come back empty from players without the setting
+    let shuffle = info.IsShuffleActive().and_then(|shuffle| shuffle.Value()).ok();
+    let repeat = info.AutoRepeatMode().and_then(|mode| mode.Value()).ok().map(|mode| match mode {
+        MediaPlaybackAutoRepeatMode::Track => RepeatMode::Track,
+        MediaPlaybackAutoRepeatMode::List => RepeatMode::List,
+        _ => RepeatMode::Off,
+    });
+    let rate = info.PlaybackRate().and_then(|rate| rate.Value()).ok();
+
+    let capabilities = MediaCapabilities {
+        play_pause: controls.IsPlayPauseToggleEnabled()?,
+        next: controls.IsNextEnabled()?,
+        previous: controls.IsPreviousEnabled()?,
+        seek: controls.IsPlaybackPositionEnabled()?,
+        shuffle: controls.IsShuffleEnabled()?,
+        repeat: controls.IsRepeatEnabled()?,
+        rate: controls.IsPlaybackRateEnabled()?,
+        rate_range: None,
+    };
+
     let app_id = session.SourceAppUserModelId()?.to_string();
 
     let last_updated_filetime = timeline.LastUpdatedTime()?;
@@ -200,16 +206,57 @@ async fn build_media_state(
     let unix_ms = (win32_ticks / 10_000) - 11_644_473_600_000;
     let synced_at = SystemTime::UNIX_EPOCH + Duration::from_millis(unix_ms as u64);
 
+    let (album_art, palette) = extract_album_art(&props).await?.unzip();
+
     Ok(MediaState {
         app_name: resolve_name_from_aumid(&app_id),
         title: props.Title()?.to_string(),
         artist: props.Artist()?.to_string(),
         album: props.AlbumTitle()?.to_string(),
-        album_art: extract_album_art(&props).await?,
+        album_art,
+        palette: palette.flatten(),
         duration_ms,
         position_ms,
         playing,
+        shuffle,
+        repeat,
+        rate,
+        capabilities,
+        // the media session has no lyrics
+        lyrics: None,
         app_icon: resolve_app_icon(&app_id).await,
+        app_id,
         synced_at,
     })
 }
+
+pub(crate) async fn execute(command: MediaCommand) -> Result<()> {
+    let manager = GlobalSystemMediaTransportControlsSessionManager::RequestAsync()?.await?;
+    let session = manager.GetCurrentSession()?;
+
+    let accepted = match command {
+        MediaCommand::TogglePlayback => session.TryTogglePlayPauseAsync()?.await?,
+        MediaCommand::Next => session.TrySkipNextAsync()?.await?,
+        MediaCommand::Previous => session.TrySkipPreviousAsync()?.await?,
+        MediaCommand::Seek(position_ms) => {
+            session.TryChangePlaybackPositionAsync((position_ms * 10_000) as i64)?.await?
+        }
+        MediaCommand::SetShuffle(shuffle) => session.TryChangeShuffleActiveAsync(shuffle)?.await?,
+        MediaCommand::SetRepeat(repeat) => {
+            let mode = match repeat {
+                RepeatMode::Off => MediaPlaybackAutoRepeatMode::None,
+                RepeatMode::Track => MediaPlaybackAutoRepeatMode::Track,
+                RepeatMode::List => MediaPlaybackAutoRepeatMode::List,
+            };
+            session.TryChangeAutoRepeatModeAsync(mode)?.await?
+        }
+        MediaCommand::SetRate(rate) => session.TryChangePlaybackRateAsync(rate)?.await?,
+    };
+
+    // players only take the commands they advertise, and say no to the rest
+    if !accepted {
+        bail!("the player didn't accept it");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use crate::{CoreEvent, MediaState, bus::EventSender, runtime::RuntimeState};
+
+#[cfg(windows)]
+mod gsmtc;
+#[cfg(target_os = "linux")]
+mod mpris;
+
+#[cfg(windows)]
+pub(crate) use gsmtc::execute;
+#[cfg(target_os = "linux")]
+pub(crate) use mpris::execute;
+
+/// Follows the player the OS media controls point at: the GSMTC's current session on
+/// Windows, an MPRIS player on Linux.
+pub struct MediaService {
+    current: Option<MediaState>,
+}
+
+impl MediaService {
+    /// Hands what the player reports to the runtime, and tells the UI when that's news.
+    fn publish(&mut self, new: Option<MediaState>, tx: &EventSender, runtime: &RuntimeState) {
+        match (&self.current, &new) {
+            (None, Some(media)) => {
+                let _ = tx.send(CoreEvent::MediaStarted(media.clone()));
+            }
+            (Some(_), None) => {
+                let _ = tx.send(CoreEvent::MediaStopped);
+            }
+            (Some(old), Some(new)) if old != new => {
+                let _ = tx.send(CoreEvent::TrackChanged(new.clone()));
+            }
+            _ => {}
+        }
+
+        runtime.update_media(|media| media.report(new.clone()));
+        self.current = new;
+    }
+}
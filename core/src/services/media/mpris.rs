@@ -0,0 +1,342 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{Result, anyhow, bail};
+use async_trait::async_trait;
+
+use super::MediaService;
+use crate::{
+    MediaCapabilities, MediaCommand, MediaState, Palette, RepeatMode,
+    bus::EventSender,
+    runtime::RuntimeState,
+    services::Service,
+    utils::{
+        artwork::load_album_art,
+        busctl::{Variant, busctl, busctl_json, monitor},
+    },
+};
+
+const NAME_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const ROOT_INTERFACE: &str = "org.mpris.MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// Property changes and seeks of any player, and players coming and going.
+const SIGNALS: [&str; 2] = [
+    "type='signal',path='/org/mpris/MediaPlayer2'",
+    "type='signal',interface='org.freedesktop.DBus',member='NameOwnerChanged',\
+     arg0namespace='org.mpris.MediaPlayer2'",
+];
+
+/// Without a session bus the watcher can't start at all, so it retries less and less often.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Bus name of the player the island shows, which commands go to.
+static ACTIVE_PLAYER: Mutex<Option<String>> = Mutex::new(None);
+
+#[async_trait]
+impl Service for MediaService {
+    fn new() -> Self {
+        Self { current: None }
+    }
+
+    async fn run(mut self, tx: EventSender, runtime: Arc<RuntimeState>) {
+        std::thread::spawn(move || {
+            let mut delay = Duration::from_secs(1);
+
+            loop {
+                let started = Instant::now();
+                if let Err(e) = self.watch(&tx, &runtime) {
+                    eprintln!("[MediaService] Reinitializing after: {e}");
+                }
+
+                if started.elapsed() > MAX_RETRY_DELAY {
+                    delay = Duration::from_secs(1);
+                }
+                std::thread::sleep(delay);
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+        });
+    }
+}
+
+impl MediaService {
+    /// Re-reads the players whenever one of them signals a change. Only returns on error.
+    fn watch(&mut self, tx: &EventSender, runtime: &RuntimeState) -> Result<()> {
+        let events = monitor(&SIGNALS)?;
+        let mut artwork = Artwork::default();
+
+        self.publish(read_media(&mut artwork)?, tx, runtime);
+
+        for line in events {
+            // a JSON object per message
+            if line?.starts_with('{') {
+                self.publish(read_media(&mut artwork)?, tx, runtime);
+            }
+        }
+
+        bail!("busctl monitor exited")
+    }
+}
+
+struct Player {
+    name: String,
+    properties: HashMap<String, Variant>,
+}
+
+impl Player {
+    fn status(&self) -> &str {
+        self.string("PlaybackStatus").unwrap_or("Stopped")
+    }
+
+    fn string(&self, key: &str) -> Option<&str> {
+        self.properties.get(key)?.data.as_str()
+    }
+
+    fn flag(&self, key: &str) -> Option<bool> {
+        self.properties.get(key)?.data.as_bool()
+    }
+
+    fn number(&self, key: &str) -> Option<f64> {
+        self.properties.get(key)?.data.as_f64()
+    }
+
+    fn repeat(&self) -> Option<RepeatMode> {
+        match self.string("LoopStatus")? {
+            "None" => Some(RepeatMode::Off),
+            "Track" => Some(RepeatMode::Track),
+            "Playlist" => Some(RepeatMode::List),
+            _ => None,
+        }
+    }
+
+    /// What the player says it takes. `CanControl` off means nothing can be changed, and
+    /// the optional properties are only settable when the player has them at all.
+    fn capabilities(&self) -> MediaCapabilities {
+        if self.flag("CanControl") == Some(false) {
+            return MediaCapabilities {
+                play_pause: false,
+                next: false,
+                previous: false,
+                seek: false,
+                shuffle: false,
+                repeat: false,
+                rate: false,
+                rate_range: None,
+            };
+        }
+
+        let can = |key: &str| self.flag(key).unwrap_or(true);
+        let slowest = self.number("MinimumRate").unwrap_or(1.0);
+        let fastest = self.number("MaximumRate").unwrap_or(1.0);
+
+        MediaCapabilities {
+            play_pause: if self.status() == "Playing" { can("CanPause") } else { can("CanPlay") },
+            next: can("CanGoNext"),
+            previous: can("CanGoPrevious"),
+            seek: can("CanSeek"),
+            shuffle: self.flag("Shuffle").is_some(),
+            repeat: self.repeat().is_some(),
+            // a player that only plays at 1.0 leaves out the range, or makes it empty
+            rate: self.number("Rate").is_some() && slowest < fastest,
+            rate_range: Some((slowest, fastest)),
+        }
+    }
+
+    fn metadata(&self) -> HashMap<String, Variant> {
+        self.properties
+            .get("Metadata")
+            .and_then(|metadata| serde_json::from_value(metadata.data.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    fn position_ms(&self) -> u64 {
+        self.properties.get("Position").and_then(microseconds).unwrap_or(0) / 1000
+    }
+}
+
+fn properties(name: &str, interface: &str) -> Result<HashMap<String, Variant>> {
+    busctl_json(&[
+        "call",
+        name,
+        OBJECT_PATH,
+        "org.freedesktop.DBus.Properties",
+        "GetAll",
+        "s",
+        interface,
+    ])
+}
+
+/// The player to show: one that's playing, sticking with the last one while it still
+/// is, otherwise the last one as long as it's around.
+fn active_player() -> Result<Option<Player>> {
+    let names: Vec<String> =
+        busctl_json(&["call", "org.freedesktop.DBus", "/", "org.freedesktop.DBus", "ListNames"])?;
+    let previous = ACTIVE_PLAYER.lock().unwrap().clone();
+
+    let player = names
+        .into_iter()
+        .filter(|name| name.starts_with(NAME_PREFIX))
+        .filter_map(|name| {
+            // players can vanish between listing and asking them
+            let properties = properties(&name, PLAYER_INTERFACE).ok()?;
+            Some(Player { name, properties })
+        })
+        .filter(|player| player.status() != "Stopped")
+        .max_by_key(|player| {
+            (player.status() == "Playing", previous.as_deref() == Some(player.name.as_str()))
+        });
+
+    *ACTIVE_PLAYER.lock().unwrap() = player.as_ref().map(|player| player.name.clone());
+    Ok(player)
+}
+
+fn read_media(artwork: &mut Artwork) -> Result<Option<MediaState>> {
+    let Some(player) = active_player()? else {
+        return Ok(None);
+    };
+
+    let root = properties(&player.name, ROOT_INTERFACE).unwrap_or_default();
+    let root_string = |key: &str| {
+        root.get(key)
+            .and_then(|value| value.data.as_str())
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+
+    // "org.mpris.MediaPlayer2.chromium.instance2817" is chromium
+    let bus_id = player.name[NAME_PREFIX.len()..].split(".instance").next().unwrap_or_default();
+    let app_id = root_string("DesktopEntry").unwrap_or_else(|| bus_id.to_string());
+    let app_name = root_string("Identity").unwrap_or_else(|| app_id.clone());
+
+    let metadata = player.metadata();
+    let text = |key: &str| {
+        let value = &metadata.get(key)?.data;
+        match value.as_array() {
+            // xesam:artist is a list
+            Some(values) => Some(
+                values.iter().filter_map(|value| value.as_str()).collect::<Vec<_>>().join(", "),
+            ),
+            None => value.as_str().map(str::to_string),
+        }
+    };
+
+    let (album_art, palette) = text("mpris:artUrl").and_then(|url| artwork.load(&url)).unzip();
+
+    Ok(Some(MediaState {
+        synced_at: SystemTime::now(),
+        app_id,
+        app_name,
+        title: text("xesam:title").unwrap_or_default(),
+        artist: text("xesam:artist").unwrap_or_default(),
+        album: text("xesam:album").unwrap_or_default(),
+        album_art,
+        palette: palette.flatten(),
+        duration_ms: metadata.get("mpris:length").and_then(microseconds).unwrap_or(0) / 1000,
+        position_ms: player.position_ms(),
+        playing: player.status() == "Playing",
+        shuffle: player.flag("Shuffle"),
+        repeat: player.repeat(),
+        rate: player.number("Rate"),
+        capabilities: player.capabilities(),
+        lyrics: text("xesam:asText").filter(|lyrics| !lyrics.is_empty()).map(Arc::from),
+        app_icon: None,
+    }))
+}
+
+/// Players disagree on whether lengths and positions are signed.
+fn microseconds(value: &Variant) -> Option<u64> {
+    value.data.as_u64().or_else(|| value.data.as_i64().map(|us| us.max(0) as u64))
+}
+
+/// The artwork of the last track, so it's only loaded again when the track changes.
+#[derive(Default)]
+struct Artwork {
+    url: String,
+    loaded: Option<(String, Option<Palette>)>,
+}
+
+impl Artwork {
+    fn load(&mut self, url: &str) -> Option<(String, Option<Palette>)> {
+        if self.url != url {
+            self.url = url.to_string();
+            self.loaded = load_album_art(url).unwrap_or_else(|e| {
+                eprintln!("[MediaService] Failed to load artwork {url}: {e}");
+                None
+            });
+        }
+
+        self.loaded.clone()
+    }
+}
+
+pub(crate) async fn execute(command: MediaCommand) -> Result<()> {
+    tokio::task::spawn_blocking(move || execute_blocking(command)).await?
+}
+
+fn execute_blocking(command: MediaCommand) -> Result<()> {
+    let player = active_player()?.ok_or_else(|| anyhow!("no player is open"))?;
+    let call = |method: &str, args: &[&str]| -> Result<()> {
+        let mut full_args = vec!["call", &player.name, OBJECT_PATH, PLAYER_INTERFACE, method];
+        full_args.extend_from_slice(args);
+        busctl(&full_args).map(drop)
+    };
+    let capabilities = player.capabilities();
+    let set = |settable: bool, property: &str, signature: &str, value: &str| -> Result<()> {
+        // busctl would set it anyway, and the player would just ignore it
+        if !settable {
+            bail!("the player didn't accept it");
+        }
+
+        busctl(&[
+            "set-property",
+            &player.name,
+            OBJECT_PATH,
+            PLAYER_INTERFACE,
+            property,
+            signature,
+            value,
+        ])
+        .map(drop)
+    };
+
+    match command {
+        MediaCommand::TogglePlayback => call("PlayPause", &[]),
+        MediaCommand::Next => call("Next", &[]),
+        MediaCommand::Previous => call("Previous", &[]),
+        MediaCommand::Seek(position_ms) => {
+            let track_id = player.metadata().get("mpris:trackid").and_then(|id| {
+                id.data
+                    .as_str()
+                    .filter(|id| *id != "/org/mpris/MediaPlayer2/TrackList/NoTrack")
+                    .map(str::to_string)
+            });
+
+            match track_id {
+                Some(track_id) => {
+                    call("SetPosition", &["ox", &track_id, &(position_ms * 1000).to_string()])
+                }
+                // without a track id only a relative seek is possible
+                None => {
+                    let offset_us = (position_ms as i64 - player.position_ms() as i64) * 1000;
+                    call("Seek", &["x", &offset_us.to_string()])
+                }
+            }
+        }
+        MediaCommand::SetShuffle(shuffle) => {
+            set(capabilities.shuffle, "Shuffle", "b", &shuffle.to_string())
+        }
+        MediaCommand::SetRepeat(repeat) => {
+            let status = match repeat {
+                RepeatMode::Off => "None",
+                RepeatMode::Track => "Track",
+                RepeatMode::List => "Playlist",
+            };
+            set(capabilities.repeat, "LoopStatus", "s", status)
+        }
+        MediaCommand::SetRate(rate) => set(capabilities.rate, "Rate", "d", &rate.to_string()),
+    }
+}
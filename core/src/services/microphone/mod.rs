@@ -0,0 +1,24 @@
+#[cfg(target_os = "linux")]
+mod pulse;
+#[cfg(windows)]
+mod wasapi;
+
+#[cfg(target_os = "linux")]
+pub(crate) use pulse::set_mic_muted;
+#[cfg(target_os = "linux")]
+pub use pulse::{MicrophoneService, mic_processes};
+#[cfg(windows)]
+pub use wasapi::MicrophoneService;
+#[cfg(windows)]
+pub(crate) use wasapi::set_mic_muted;
+
+use std::sync::atomic::Ordering;
+
+use crate::{CoreEvent, bus::EventSender, runtime::RuntimeState};
+
+/// Stores the capture mute state, telling the UI only when it actually changed.
+pub(crate) fn publish_muted(tx: &EventSender, runtime: &RuntimeState, muted: bool) {
+    if runtime.mic_muted.swap(muted, Ordering::Relaxed) != muted {
+        let _ = tx.send(CoreEvent::MicrophoneMuted(muted));
+    }
+}
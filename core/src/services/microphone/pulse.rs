@@ -0,0 +1,203 @@
+use std::{collections::HashMap, path::Path, sync::Arc, time::Duration};
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use super::publish_muted;
+use crate::{
+    CoreEvent,
+    bus::EventSender,
+    privacy::Sensor,
+    runtime::RuntimeState,
+    services::Service,
+    utils::{
+        pactl::{pactl, pactl_json, subscribe},
+        procfs::describe_process,
+    },
+};
+
+pub struct MicrophoneService {
+    active: Vec<u32>,
+}
+
+#[async_trait]
+impl Service for MicrophoneService {
+    fn new() -> Self {
+        Self { active: Vec::new() }
+    }
+
+    async fn run(mut self, tx: EventSender, runtime: Arc<RuntimeState>) {
+        std::thread::spawn(move || {
+            loop {
+                if let Err(e) = self.watch(&tx, &runtime) {
+                    eprintln!("[MicrophoneService] Reinitializing after: {e}");
+                    std::thread::sleep(Duration::from_secs(1));
+                }
+            }
+        });
+    }
+}
+
+impl MicrophoneService {
+    /// Re-evaluates whenever the sound server reports a capture stream change. Only returns
+    /// on error.
+    fn watch(&mut self, tx: &EventSender, runtime: &RuntimeState) -> Result<()> {
+        let events = subscribe()?;
+
+        self.evaluate(tx, runtime)?;
+        publish_muted(tx, runtime, mic_muted());
+
+        for line in events {
+            // e.g. "Event 'new' on source-output #87"
+            let line = line?;
+            if line.contains("on source-output") {
+                self.evaluate(tx, runtime)?;
+            } else if line.contains("on source #") || line.contains("on server") {
+                // a source's mute flipped, or the default source changed
+                publish_muted(tx, runtime, mic_muted());
+            }
+        }
+
+        bail!("pactl subscribe exited")
+    }
+
+    fn evaluate(&mut self, tx: &EventSender, runtime: &RuntimeState) -> Result<()> {
+        let streams = capture_streams()?;
+        let pids = sorted_pids(&streams);
+
+        if pids == self.active {
+            return Ok(());
+        }
+
+        let apps: Vec<_> = pids
+            .iter()
+            .map(|&pid| {
+                let name = streams
+                    .iter()
+                    .find(|stream| stream.pid() == Some(pid))
+                    .and_then(|stream| stream.properties.get("application.name").cloned());
+                describe_process(Path::new("/proc"), pid, name)
+            })
+            .collect();
+
+        runtime.set_sensor_apps(Sensor::Microphone, apps.clone());
+        let _ = tx.send(if pids.is_empty() {
+            CoreEvent::MicrophoneInactive
+        } else {
+            CoreEvent::MicrophoneActive(apps)
+        });
+
+        self.active = pids;
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct SourceOutput {
+    source: u32,
+    #[serde(default)]
+    corked: bool,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+}
+
+impl SourceOutput {
+    fn pid(&self) -> Option<u32> {
+        self.properties.get("application.process.id")?.parse().ok()
+    }
+}
+
+#[derive(Deserialize)]
+struct Source {
+    index: u32,
+    name: String,
+    #[serde(default)]
+    monitor_of_sink: Option<String>,
+}
+
+impl Source {
+    fn is_monitor(&self) -> bool {
+        self.name.ends_with(".monitor")
+            || self.monitor_of_sink.as_deref().is_some_and(|sink| sink != "n/a")
+    }
+}
+
+/// Recording streams on real capture devices. Streams on monitor sources only capture
+/// playback (our own visualizer among them), so they don't count as microphone use.
+fn capture_streams() -> Result<Vec<SourceOutput>> {
+    let sources: Vec<Source> = pactl_json(&["list", "sources"])?;
+    let outputs: Vec<SourceOutput> = pactl_json(&["list", "source-outputs"])?;
+
+    Ok(outputs
+        .into_iter()
+        .filter(|output| !output.corked)
+        .filter(|output| {
+            sources.iter().any(|source| source.index == output.source && !source.is_monitor())
+        })
+        .collect())
+}
+
+/// Processes currently recording from a microphone, sorted. A loopback capture (e.g.
+/// `parec` on a real source) shows up here like any other app.
+pub fn mic_processes() -> Result<Vec<u32>> {
+    Ok(sorted_pids(&capture_streams()?))
+}
+
+/// Without a default source there's no microphone to be muted, so that reads as unmuted.
+fn mic_muted() -> bool {
+    // "Mute: yes"
+    pactl(&["get-source-mute", "@DEFAULT_SOURCE@"]).is_ok_and(|out| out.trim().ends_with("yes"))
+}
+
+pub(crate) fn set_mic_muted(muted: bool) -> Result<()> {
+    pactl(&["set-source-mute", "@DEFAULT_SOURCE@", if muted { "1" } else { "0" }])?;
+    Ok(())
+}
+
+fn sorted_pids(streams: &[SourceOutput]) -> Vec<u32> {
+    let mut pids: Vec<u32> = streams.iter().filter_map(|stream| stream.pid()).collect();
+    pids.sort_unstable();
+    pids.dedup();
+    pids
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::{Command, Stdio};
+
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a PulseAudio/PipeWire server"]
+    fn a_loopback_capture_counts_as_microphone_use() {
+        let source = pactl(&["get-default-source"]).expect("pactl should reach the sound server");
+        let source = source.trim();
+        assert!(
+            !source.ends_with(".monitor"),
+            "the default source {source} only monitors playback, a capture device is needed"
+        );
+
+        let mut parec = Command::new("parec")
+            .args(["--raw", "--device", source])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("parec should run where pactl does");
+        let pid = parec.id();
+
+        // the stream shows up once parec has connected
+        let recording = |expected: bool| {
+            (0..30).any(|_| {
+                std::thread::sleep(Duration::from_millis(100));
+                mic_processes().is_ok_and(|pids| pids.contains(&pid) == expected)
+            })
+        };
+        let seen = recording(true);
+        let _ = parec.kill();
+        let _ = parec.wait();
+
+        assert!(seen, "parec {pid} recording from {source} wasn't reported");
+        assert!(recording(false), "parec {pid} was still reported after exiting");
+    }
+}
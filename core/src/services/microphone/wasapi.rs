@@ -1,16 +1,17 @@
-use std::{
-    sync::{Arc, Mutex, atomic::Ordering, mpsc},
-    time::Duration,
-};
+use std::sync::{Arc, Mutex};
 
 use anyhow::Result;
 use async_trait::async_trait;
 use tokio::sync::watch;
 use windows::Win32::{
     Media::Audio::{
-        AudioSessionState, AudioSessionStateActive, DEVICE_STATE_ACTIVE, DEVICE_STATE_DISABLED,
-        DEVICE_STATE_NOTPRESENT, DEVICE_STATE_UNPLUGGED, IAudioSessionControl,
-        IAudioSessionControl2, IAudioSessionEvents, IAudioSessionEvents_Impl,
+        AUDIO_VOLUME_NOTIFICATION_DATA, AudioSessionState, AudioSessionStateActive,
+        DEVICE_STATE_ACTIVE, DEVICE_STATE_DISABLED, DEVICE_STATE_NOTPRESENT,
+        DEVICE_STATE_UNPLUGGED,
+        Endpoints::{
+            IAudioEndpointVolume, IAudioEndpointVolumeCallback, IAudioEndpointVolumeCallback_Impl,
+        },
+        IAudioSessionControl, IAudioSessionControl2, IAudioSessionEvents, IAudioSessionEvents_Impl,
         IAudioSessionManager2, IAudioSessionNotification, IAudioSessionNotification_Impl,
         IMMDeviceEnumerator, IMMNotificationClient, IMMNotificationClient_Impl, MMDeviceEnumerator,
         eCapture, eCommunications,
@@ -19,16 +20,24 @@ use windows::Win32::{
 };
 use windows_core::{Interface, Ref, implement};
 
-use crate::{CoreEvent, bus::EventSender, runtime::RuntimeState, services::Service};
+use super::publish_muted;
+use crate::{
+    AppUsage, CoreEvent,
+    bus::EventSender,
+    privacy::Sensor,
+    runtime::RuntimeState,
+    services::Service,
+    utils::process::{describe_process, process_path},
+};
 
 pub struct MicrophoneService {
-    active: bool,
+    active: Vec<u32>,
 }
 
 #[async_trait]
 impl Service for MicrophoneService {
     fn new() -> Self {
-        Self { active: false }
+        Self { active: Vec::new() }
     }
 
     async fn run(mut self, tx: EventSender, runtime: Arc<RuntimeState>) {
@@ -187,7 +196,7 @@ async fn run_event_driven(
     tx: EventSender,
     runtime: Arc<RuntimeState>,
 ) -> Result<()> {
-    let (device_change_tx, device_change_rx) = mpsc::sync_channel::<()>(1);
+    let (device_change_tx, mut device_change_rx) = watch::channel(());
     let notifier: IMMNotificationClient = DeviceChangeNotifier { tx: device_change_tx }.into();
 
     let enumerator: IMMDeviceEnumerator =
@@ -201,6 +210,7 @@ async fn run_event_driven(
 
     let mut active_device_handlers: Vec<SessionHandlers> = Vec::new();
     let (notify_tx, mut notify_rx) = watch::channel(());
+    let (mute_tx, mut mute_rx) = watch::channel(());
 
     let rebuild_all_handlers = |handlers: &mut Vec<SessionHandlers>| {
         handlers.clear();
@@ -225,49 +235,114 @@ async fn run_event_driven(
     };
 
     rebuild_all_handlers(&mut active_device_handlers);
+    let mut mute_watch = MuteWatch::new(&enumerator, &mute_tx).ok();
 
-    let initial_state = evaluate_mic_status(&active_device_handlers);
-    service.active = initial_state;
-    runtime.mic.store(initial_state, Ordering::Relaxed);
+    let initial_pids = active_mic_processes(&active_device_handlers);
+    runtime.set_sensor_apps(Sensor::Microphone, describe_all(&initial_pids).await);
+    service.active = initial_pids;
+    publish_muted(&tx, &runtime, mute_watch.as_ref().is_some_and(MuteWatch::muted));
 
     loop {
-        if device_change_rx.try_recv().is_ok() {
-            while device_change_rx.try_recv().is_ok() {}
-            eprintln!(
-                "[MicrophoneService] Hardware layout changed. Re-indexing all microphones..."
-            );
-            rebuild_all_handlers(&mut active_device_handlers);
+        tokio::select! {
+            Ok(()) = device_change_rx.changed() => {
+                eprintln!(
+                    "[MicrophoneService] Hardware layout changed. Re-indexing all microphones..."
+                );
+                rebuild_all_handlers(&mut active_device_handlers);
+
+                // the default microphone may be another one now
+                mute_watch = MuteWatch::new(&enumerator, &mute_tx).ok();
+                publish_muted(&tx, &runtime, mute_watch.as_ref().is_some_and(MuteWatch::muted));
+            }
+            Ok(()) = notify_rx.changed() => {}
+            // the endpoint's mute can also change from the keyboard or the sound settings
+            Ok(()) = mute_rx.changed() => {
+                publish_muted(&tx, &runtime, mute_watch.as_ref().is_some_and(MuteWatch::muted));
+                continue;
+            }
         }
 
-        let mut should_evaluate = false;
+        let pids = active_mic_processes(&active_device_handlers);
 
-        match tokio::time::timeout(Duration::from_millis(250), notify_rx.changed()).await {
-            Ok(Ok(())) => {
-                while notify_rx.has_changed().unwrap_or(false) {
-                    notify_rx.mark_unchanged();
-                }
-                should_evaluate = true;
-            }
-            _ => {}
+        if pids != service.active {
+            let apps = describe_all(&pids).await;
+
+            runtime.set_sensor_apps(Sensor::Microphone, apps.clone());
+            let _ = tx.send(if pids.is_empty() {
+                CoreEvent::MicrophoneInactive
+            } else {
+                CoreEvent::MicrophoneActive(apps)
+            });
+
+            service.active = pids;
         }
+    }
+}
 
-        if should_evaluate {
-            let found_active = evaluate_mic_status(&active_device_handlers);
-
-            if found_active != service.active {
-                service.active = found_active;
-                runtime.mic.store(found_active, Ordering::Relaxed);
-                let _ = tx.send(if found_active {
-                    CoreEvent::MicrophoneActive
-                } else {
-                    CoreEvent::MicrophoneInactive
-                });
-            }
+/// Volume control of the default communications microphone, the one calls record from.
+fn capture_endpoint(enumerator: &IMMDeviceEnumerator) -> Result<IAudioEndpointVolume> {
+    unsafe {
+        let device = enumerator.GetDefaultAudioEndpoint(eCapture, eCommunications)?;
+
+        Ok(device.Activate(CLSCTX_ALL, None)?)
+    }
+}
+
+pub(crate) fn set_mic_muted(muted: bool) -> Result<()> {
+    let enumerator: IMMDeviceEnumerator =
+        unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? };
+    unsafe { capture_endpoint(&enumerator)?.SetMute(muted, std::ptr::null())? };
+    Ok(())
+}
+
+/// Signals `tx` whenever the default communications microphone's volume or mute changes,
+/// until dropped. That device is only looked up once, so it has to be recreated when the
+/// default changes.
+struct MuteWatch {
+    volume: IAudioEndpointVolume,
+    callback: IAudioEndpointVolumeCallback,
+}
+
+impl MuteWatch {
+    fn new(enumerator: &IMMDeviceEnumerator, tx: &watch::Sender<()>) -> Result<Self> {
+        let volume = capture_endpoint(enumerator)?;
+        let callback: IAudioEndpointVolumeCallback = MuteNotifier { tx: tx.clone() }.into();
+        unsafe {
+            volume.RegisterControlChangeNotify(&callback)?;
         }
+
+        Ok(Self { volume, callback })
     }
+
+    fn muted(&self) -> bool {
+        unsafe { self.volume.GetMute() }.is_ok_and(|muted| muted.as_bool())
+    }
+}
+
+impl Drop for MuteWatch {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.volume.UnregisterControlChangeNotify(&self.callback);
+        }
+    }
+}
+
+#[implement(IAudioEndpointVolumeCallback)]
+struct MuteNotifier {
+    tx: watch::Sender<()>,
 }
 
-fn evaluate_mic_status(handlers: &[SessionHandlers]) -> bool {
+impl IAudioEndpointVolumeCallback_Impl for MuteNotifier_Impl {
+    fn OnNotify(&self, _: *mut AUDIO_VOLUME_NOTIFICATION_DATA) -> windows_core::Result<()> {
+        let _ = self.tx.send(());
+        Ok(())
+    }
+}
+
+/// Processes with an active capture session on any microphone, sorted so changes compare cleanly.
+fn active_mic_processes(handlers: &[SessionHandlers]) -> Vec<u32> {
+    let mut pids = Vec::new();
+
     for handler in handlers {
         unsafe {
             if let Ok(session_enum) = handler.manager.GetSessionEnumerator() {
@@ -277,10 +352,9 @@ fn evaluate_mic_status(handlers: &[SessionHandlers]) -> bool {
                             if let (Ok(state), Ok(control2)) =
                                 (control.GetState(), control.cast::<IAudioSessionControl2>())
                             {
-                                if state == AudioSessionStateActive
-                                    && control2.GetProcessId().unwrap_or(0) != 0
-                                {
-                                    return true;
+                                let pid = control2.GetProcessId().unwrap_or(0);
+                                if state == AudioSessionStateActive && pid != 0 {
+                                    pids.push(pid);
                                 }
                             }
                         }
@@ -290,12 +364,22 @@ fn evaluate_mic_status(handlers: &[SessionHandlers]) -> bool {
         }
     }
 
-    false
+    pids.sort_unstable();
+    pids.dedup();
+    pids
+}
+
+async fn describe_all(pids: &[u32]) -> Vec<AppUsage> {
+    let mut apps = Vec::with_capacity(pids.len());
+    for &pid in pids {
+        apps.push(describe_process(pid, process_path(pid).unwrap_or_default()).await);
+    }
+    apps
 }
 
 #[implement(IMMNotificationClient)]
 struct DeviceChangeNotifier {
-    tx: mpsc::SyncSender<()>,
+    tx: watch::Sender<()>,
 }
 
 impl IMMNotificationClient_Impl for DeviceChangeNotifier_Impl {
@@ -306,18 +390,18 @@ impl IMMNotificationClient_Impl for DeviceChangeNotifier_Impl {
         _: &windows_core::PCWSTR,
     ) -> windows_core::Result<()> {
         if flow == eCapture && role == eCommunications {
-            let _ = self.tx.try_send(());
+            let _ = self.tx.send(());
         }
         Ok(())
     }
 
     fn OnDeviceAdded(&self, _: &windows_core::PCWSTR) -> windows_core::Result<()> {
-        let _ = self.tx.try_send(());
+        let _ = self.tx.send(());
         Ok(())
     }
 
     fn OnDeviceRemoved(&self, _: &windows_core::PCWSTR) -> windows_core::Result<()> {
-        let _ = self.tx.try_send(());
+        let _ = self.tx.send(());
         Ok(())
     }
 
@@ -333,7 +417,7 @@ impl IMMNotificationClient_Impl for DeviceChangeNotifier_Impl {
                 | DEVICE_STATE_NOTPRESENT
                 | DEVICE_STATE_UNPLUGGED
         ) {
-            let _ = self.tx.try_send(());
+            let _ = self.tx.send(());
         }
         Ok(())
     }
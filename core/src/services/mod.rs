@@ -4,11 +4,15 @@ use async_trait::async_trait;
 
 use crate::{bus::EventSender, runtime::RuntimeState};
 
+pub mod appearance;
 pub mod audio;
-pub mod camera;
+pub mod capability;
+pub mod lyrics;
 pub mod media;
 pub mod microphone;
+#[cfg(windows)]
 pub mod notifications;
+pub mod volume;
 
 #[async_trait]
 pub trait Service: Send + Sync {
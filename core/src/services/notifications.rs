@@ -1,4 +1,8 @@
-use std::{collections::HashSet, sync::Arc, time::Duration};
+use std::{
+    collections::HashSet,
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
 
 use anyhow::{Result, bail};
 use async_trait::async_trait;
@@ -84,6 +88,10 @@ async fn run_unpackaged_polling(
             }
             seen.insert(id);
 
+            if runtime.do_not_disturb.load(Ordering::Relaxed) {
+                continue;
+            }
+
             let app_id = notification
                 .AppInfo()
                 .ok()
@@ -0,0 +1,90 @@
+use std::{
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+#[cfg(windows)]
+use windows::Win32::System::Com::{COINIT_MULTITHREADED, CoInitializeEx};
+
+use crate::{CoreEvent, VolumeState, bus::EventSender, runtime::RuntimeState, services::Service};
+
+#[cfg(target_os = "linux")]
+mod pulse;
+#[cfg(windows)]
+mod wasapi;
+
+#[cfg(target_os = "linux")]
+use pulse::watch;
+#[cfg(target_os = "linux")]
+pub(crate) use pulse::{output_devices, set_mute, set_output_device, set_volume, volume};
+#[cfg(windows)]
+use wasapi::watch;
+#[cfg(windows)]
+pub(crate) use wasapi::{output_devices, set_mute, set_output_device, set_volume, volume};
+
+/// What volume changes from the island apply to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum VolumeTarget {
+    /// The default output device.
+    Master,
+    /// Every playback stream of a process.
+    Process(u32),
+}
+
+pub struct VolumeService {
+    current: Option<VolumeState>,
+}
+
+#[async_trait]
+impl Service for VolumeService {
+    fn new() -> Self {
+        Self { current: None }
+    }
+
+    async fn run(mut self, tx: EventSender, runtime: Arc<RuntimeState>) {
+        std::thread::spawn(move || {
+            #[cfg(windows)]
+            unsafe {
+                let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            }
+
+            loop {
+                let result = watch(&mut |state| self.publish(state, &tx, &runtime));
+
+                if let Err(e) = result {
+                    eprintln!("[VolumeService] Reinitializing after: {e}");
+                    std::thread::sleep(Duration::from_secs(1));
+                }
+            }
+        });
+    }
+}
+
+impl VolumeService {
+    fn publish(&mut self, state: VolumeState, tx: &EventSender, runtime: &RuntimeState) {
+        let event = match &self.current {
+            // the first reading is just where things stand, not something to announce
+            None => None,
+            Some(old) if old.device != state.device => {
+                Some(CoreEvent::OutputDeviceChanged { device: state.device.clone() })
+            }
+            Some(old) if old.level != state.level || old.muted != state.muted => {
+                Some(CoreEvent::VolumeChanged {
+                    level: state.level,
+                    muted: state.muted,
+                    device: state.device.clone(),
+                })
+            }
+            Some(_) => None,
+        };
+
+        *runtime.volume.write().unwrap() = Some(state.clone());
+        self.current = Some(state);
+
+        if let Some(event) = event {
+            runtime.volume_osd.store(true, Ordering::Relaxed);
+            let _ = tx.send(event);
+        }
+    }
+}
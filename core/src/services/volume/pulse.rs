@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow, bail};
+use serde::Deserialize;
+
+use crate::{
+    OutputDevice, VolumeState,
+    services::volume::VolumeTarget,
+    utils::pactl::{pactl, pactl_json, subscribe},
+};
+
+/// `PA_VOLUME_NORM`, the raw volume value for 100%.
+const VOLUME_NORM: f32 = 65536.0;
+
+/// Reports the default sink's volume to `on_change` whenever the sound server reports a
+/// sink or server (default sink) change. Only returns on error.
+pub(super) fn watch(on_change: &mut dyn FnMut(VolumeState)) -> Result<()> {
+    let events = subscribe()?;
+
+    on_change(default_sink_state()?);
+
+    for line in events {
+        let line = line?;
+
+        // e.g. "Event 'change' on sink #52" or "Event 'change' on server #-1"
+        if line.contains("on sink #") || line.contains("on server") {
+            on_change(default_sink_state()?);
+        }
+    }
+
+    bail!("pactl subscribe exited")
+}
+
+/// A sink or a sink input; `pactl` describes both with the same fields.
+#[derive(Deserialize)]
+struct Node {
+    #[serde(default)]
+    index: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    mute: bool,
+    #[serde(default)]
+    volume: HashMap<String, ChannelVolume>,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+}
+
+impl Node {
+    fn level(&self) -> f32 {
+        if self.volume.is_empty() {
+            return 0.0;
+        }
+
+        let sum = self.volume.values().map(|channel| channel.value as f32).sum::<f32>();
+        (sum / self.volume.len() as f32 / VOLUME_NORM).clamp(0.0, 1.0)
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.properties.get("application.process.id")?.parse().ok()
+    }
+}
+
+#[derive(Deserialize)]
+struct ChannelVolume {
+    value: u32,
+}
+
+fn default_sink_state() -> Result<VolumeState> {
+    let default_sink = pactl(&["get-default-sink"])?.trim().to_string();
+    let sinks: Vec<Node> = pactl_json(&["list", "sinks"])?;
+
+    let sink = sinks
+        .into_iter()
+        .find(|sink| sink.name == default_sink)
+        .ok_or_else(|| anyhow!("Default sink {default_sink} not found"))?;
+
+    Ok(VolumeState {
+        level: sink.level(),
+        muted: sink.mute,
+        device: if sink.description.is_empty() { sink.name } else { sink.description },
+    })
+}
+
+fn sink_inputs(pid: u32) -> Result<Vec<Node>> {
+    let inputs: Vec<Node> = pactl_json(&["list", "sink-inputs"])?;
+    let inputs: Vec<Node> = inputs.into_iter().filter(|input| input.pid() == Some(pid)).collect();
+
+    if inputs.is_empty() {
+        bail!("No playback stream for process {pid}");
+    }
+    Ok(inputs)
+}
+
+pub(crate) fn volume(target: VolumeTarget) -> Result<(f32, bool)> {
+    match target {
+        VolumeTarget::Master => default_sink_state().map(|state| (state.level, state.muted)),
+        VolumeTarget::Process(pid) => {
+            let inputs = sink_inputs(pid)?;
+            Ok((inputs[0].level(), inputs.iter().all(|input| input.mute)))
+        }
+    }
+}
+
+pub(crate) fn set_volume(target: VolumeTarget, level: f32) -> Result<()> {
+    let level = ((level.clamp(0.0, 1.0) * VOLUME_NORM).round() as u32).to_string();
+
+    match target {
+        VolumeTarget::Master => {
+            pactl(&["set-sink-volume", "@DEFAULT_SINK@", &level])?;
+        }
+        VolumeTarget::Process(pid) => {
+            for input in sink_inputs(pid)? {
+                pactl(&["set-sink-input-volume", &input.index.to_string(), &level])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn set_mute(target: VolumeTarget, muted: bool) -> Result<()> {
+    let muted = if muted { "1" } else { "0" };
+
+    match target {
+        VolumeTarget::Master => {
+            pactl(&["set-sink-mute", "@DEFAULT_SINK@", muted])?;
+        }
+        VolumeTarget::Process(pid) => {
+            for input in sink_inputs(pid)? {
+                pactl(&["set-sink-input-mute", &input.index.to_string(), muted])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn output_devices() -> Result<Vec<OutputDevice>> {
+    let default_sink = pactl(&["get-default-sink"])?.trim().to_string();
+    let sinks: Vec<Node> = pactl_json(&["list", "sinks"])?;
+
+    Ok(sinks
+        .into_iter()
+        .map(|sink| OutputDevice {
+            is_default: sink.name == default_sink,
+            name: if sink.description.is_empty() { sink.name.clone() } else { sink.description },
+            id: sink.name,
+        })
+        .collect())
+}
+
+/// Streams that weren't pinned to a sink follow the new default on their own.
+pub(crate) fn set_output_device(id: &str) -> Result<()> {
+    pactl(&["set-default-sink", id])?;
+    Ok(())
+}
@@ -0,0 +1,348 @@
+use std::sync::mpsc;
+
+use anyhow::{Result, bail};
+use windows::Win32::{
+    Devices::FunctionDiscovery::PKEY_Device_FriendlyName,
+    Media::Audio::{
+        AUDIO_VOLUME_NOTIFICATION_DATA, DEVICE_STATE_ACTIVE,
+        Endpoints::{
+            IAudioEndpointVolume, IAudioEndpointVolumeCallback, IAudioEndpointVolumeCallback_Impl,
+        },
+        IAudioSessionControl2, IAudioSessionManager2, IMMDevice, IMMDeviceEnumerator,
+        IMMNotificationClient, IMMNotificationClient_Impl, ISimpleAudioVolume, MMDeviceEnumerator,
+        eCommunications, eConsole, eMultimedia, eRender,
+    },
+    System::Com::{
+        CLSCTX_ALL, CoCreateInstance, CoTaskMemFree, STGM_READ,
+        StructuredStorage::{PropVariantClear, PropVariantToStringAlloc},
+    },
+};
+use windows_core::{HSTRING, Interface, PCWSTR, implement};
+
+use crate::{OutputDevice, VolumeState, services::volume::VolumeTarget};
+
+use policy_config::{IPolicyConfig, POLICY_CONFIG_CLIENT};
+
+enum Change {
+    Volume,
+    Device,
+}
+
+/// Reports the default output device's volume to `on_change` whenever it or the default
+/// device itself changes. Only returns on error.
+pub(super) fn watch(on_change: &mut dyn FnMut(VolumeState)) -> Result<()> {
+    let enumerator: IMMDeviceEnumerator =
+        unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? };
+
+    let (change_tx, change_rx) = mpsc::channel::<Change>();
+    let notifier: IMMNotificationClient = DeviceChangeNotifier { tx: change_tx.clone() }.into();
+    unsafe {
+        enumerator.RegisterEndpointNotificationCallback(&notifier)?;
+    }
+
+    let _guard = NotifierGuard(&enumerator, &notifier);
+
+    loop {
+        let device = unsafe { enumerator.GetDefaultAudioEndpoint(eRender, eConsole)? };
+        let name = device_name(&device).unwrap_or_default();
+
+        let volume: IAudioEndpointVolume = unsafe { device.Activate(CLSCTX_ALL, None)? };
+        let callback: IAudioEndpointVolumeCallback =
+            VolumeNotifier { tx: change_tx.clone() }.into();
+        unsafe {
+            volume.RegisterControlChangeNotify(&callback)?;
+        }
+
+        let result = (|| -> Result<()> {
+            loop {
+                let level = unsafe { volume.GetMasterVolumeLevelScalar()? };
+                let muted = unsafe { volume.GetMute()?.as_bool() };
+                on_change(VolumeState { level, muted, device: name.clone() });
+
+                match change_rx.recv()? {
+                    Change::Volume => {}
+                    Change::Device => return Ok(()),
+                }
+            }
+        })();
+
+        unsafe {
+            let _ = volume.UnregisterControlChangeNotify(&callback);
+        }
+        result?;
+    }
+}
+
+/// The volume controls behind a [`VolumeTarget`].
+enum Control {
+    Endpoint(IAudioEndpointVolume),
+    Sessions(Vec<ISimpleAudioVolume>),
+}
+
+impl Control {
+    fn open(target: VolumeTarget) -> Result<Self> {
+        let device = unsafe {
+            let enumerator: IMMDeviceEnumerator =
+                CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+            enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?
+        };
+
+        let VolumeTarget::Process(pid) = target else {
+            return Ok(Self::Endpoint(unsafe { device.Activate(CLSCTX_ALL, None)? }));
+        };
+
+        let manager: IAudioSessionManager2 = unsafe { device.Activate(CLSCTX_ALL, None)? };
+        let sessions = unsafe { manager.GetSessionEnumerator()? };
+
+        // a process can own more than one session, and they should move together
+        let mut volumes = Vec::new();
+        for idx in 0..unsafe { sessions.GetCount()? } {
+            let session = unsafe { sessions.GetSession(idx)? }.cast::<IAudioSessionControl2>()?;
+            if unsafe { session.GetProcessId() }.is_ok_and(|id| id == pid) {
+                volumes.push(session.cast::<ISimpleAudioVolume>()?);
+            }
+        }
+
+        if volumes.is_empty() {
+            bail!("No audio session for process {pid}");
+        }
+        Ok(Self::Sessions(volumes))
+    }
+
+    fn get(&self) -> Result<(f32, bool)> {
+        unsafe {
+            match self {
+                Self::Endpoint(volume) => {
+                    Ok((volume.GetMasterVolumeLevelScalar()?, volume.GetMute()?.as_bool()))
+                }
+                Self::Sessions(volumes) => {
+                    Ok((volumes[0].GetMasterVolume()?, volumes[0].GetMute()?.as_bool()))
+                }
+            }
+        }
+    }
+
+    fn set_level(&self, level: f32) -> Result<()> {
+        unsafe {
+            match self {
+                Self::Endpoint(volume) => {
+                    volume.SetMasterVolumeLevelScalar(level, std::ptr::null())?
+                }
+                Self::Sessions(volumes) => {
+                    for volume in volumes {
+                        volume.SetMasterVolume(level, std::ptr::null())?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set_mute(&self, muted: bool) -> Result<()> {
+        unsafe {
+            match self {
+                Self::Endpoint(volume) => volume.SetMute(muted, std::ptr::null())?,
+                Self::Sessions(volumes) => {
+                    for volume in volumes {
+                        volume.SetMute(muted, std::ptr::null())?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn volume(target: VolumeTarget) -> Result<(f32, bool)> {
+    Control::open(target)?.get()
+}
+
+pub(crate) fn set_volume(target: VolumeTarget, level: f32) -> Result<()> {
+    Control::open(target)?.set_level(level.clamp(0.0, 1.0))
+}
+
+pub(crate) fn set_mute(target: VolumeTarget, muted: bool) -> Result<()> {
+    Control::open(target)?.set_mute(muted)
+}
+
+pub(crate) fn output_devices() -> Result<Vec<OutputDevice>> {
+    unsafe {
+        let enumerator: IMMDeviceEnumerator =
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let default_id = device_id(&enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?)?;
+
+        let collection = enumerator.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE)?;
+        let mut devices = Vec::new();
+
+        for idx in 0..collection.GetCount()? {
+            let device = collection.Item(idx)?;
+            let id = device_id(&device)?;
+
+            devices.push(OutputDevice {
+                name: device_name(&device).unwrap_or_else(|_| id.clone()),
+                is_default: id == default_id,
+                id,
+            });
+        }
+
+        Ok(devices)
+    }
+}
+
+/// Windows has no public API for this; the settings app and every sound switcher go
+/// through the undocumented `IPolicyConfig`, which has been stable since Vista.
+pub(crate) fn set_output_device(id: &str) -> Result<()> {
+    let id = HSTRING::from(id);
+
+    unsafe {
+        let policy: IPolicyConfig = CoCreateInstance(&POLICY_CONFIG_CLIENT, None, CLSCTX_ALL)?;
+
+        for role in [eConsole, eMultimedia, eCommunications] {
+            policy.SetDefaultEndpoint(PCWSTR(id.as_ptr()), role).ok()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn device_id(device: &IMMDevice) -> Result<String> {
+    unsafe {
+        let id = device.GetId()?;
+        let result = id.to_string().unwrap_or_default();
+        CoTaskMemFree(Some(id.as_ptr() as *const _));
+
+        Ok(result)
+    }
+}
+
+fn device_name(device: &IMMDevice) -> Result<String> {
+    unsafe {
+        let store = device.OpenPropertyStore(STGM_READ)?;
+        let mut value = store.GetValue(&PKEY_Device_FriendlyName)?;
+
+        let name = PropVariantToStringAlloc(&value);
+        let _ = PropVariantClear(&mut value);
+
+        let name = name?;
+        let result = name.to_string().unwrap_or_default();
+        CoTaskMemFree(Some(name.as_ptr() as *const _));
+
+        Ok(result)
+    }
+}
+
+#[implement(IAudioEndpointVolumeCallback)]
+struct VolumeNotifier {
+    tx: mpsc::Sender<Change>,
+}
+
+impl IAudioEndpointVolumeCallback_Impl for VolumeNotifier_Impl {
+    fn OnNotify(&self, _: *mut AUDIO_VOLUME_NOTIFICATION_DATA) -> windows_core::Result<()> {
+        let _ = self.tx.send(Change::Volume);
+        Ok(())
+    }
+}
+
+#[implement(IMMNotificationClient)]
+struct DeviceChangeNotifier {
+    tx: mpsc::Sender<Change>,
+}
+
+impl IMMNotificationClient_Impl for DeviceChangeNotifier_Impl {
+    fn OnDefaultDeviceChanged(
+        &self,
+        flow: windows::Win32::Media::Audio::EDataFlow,
+        role: windows::Win32::Media::Audio::ERole,
+        _: &windows_core::PCWSTR,
+    ) -> windows_core::Result<()> {
+        if flow == eRender && role == eConsole {
+            let _ = self.tx.send(Change::Device);
+        }
+        Ok(())
+    }
+
+    fn OnDeviceAdded(&self, _: &windows_core::PCWSTR) -> windows_core::Result<()> {
+        Ok(())
+    }
+    fn OnDeviceRemoved(&self, _: &windows_core::PCWSTR) -> windows_core::Result<()> {
+        Ok(())
+    }
+    fn OnDeviceStateChanged(
+        &self,
+        _: &windows_core::PCWSTR,
+        _: windows::Win32::Media::Audio::DEVICE_STATE,
+    ) -> windows_core::Result<()> {
+        Ok(())
+    }
+    fn OnPropertyValueChanged(
+        &self,
+        _: &windows_core::PCWSTR,
+        _: &windows::Win32::Foundation::PROPERTYKEY,
+    ) -> windows_core::Result<()> {
+        Ok(())
+    }
+}
+
+struct NotifierGuard<'a>(&'a IMMDeviceEnumerator, &'a IMMNotificationClient);
+
+impl Drop for NotifierGuard<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.0.UnregisterEndpointNotificationCallback(self.1);
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+mod policy_config {
+    use std::ffi::c_void;
+
+    use windows::Win32::Media::Audio::ERole;
+    use windows_core::{GUID, HRESULT, IUnknown, IUnknown_Vtbl, PCWSTR, interface};
+
+    pub(super) const POLICY_CONFIG_CLIENT: GUID =
+        GUID::from_u128(0x870af99c_171d_4f9e_af0d_e63df40c2bc9);
+
+    /// Only `SetDefaultEndpoint` is used; the rest are declared to keep the vtable in order.
+    #[interface("f8679f50-850a-41cf-9c72-430f290290c8")]
+    pub(super) unsafe trait IPolicyConfig: IUnknown {
+        fn GetMixFormat(&self, device: PCWSTR, format: *mut *mut c_void) -> HRESULT;
+        fn GetDeviceFormat(
+            &self,
+            device: PCWSTR,
+            default: i32,
+            format: *mut *mut c_void,
+        ) -> HRESULT;
+        fn ResetDeviceFormat(&self, device: PCWSTR) -> HRESULT;
+        fn SetDeviceFormat(
+            &self,
+            device: PCWSTR,
+            endpoint: *mut c_void,
+            mix: *mut c_void,
+        ) -> HRESULT;
+        fn GetProcessingPeriod(
+            &self,
+            device: PCWSTR,
+            default: i32,
+            default_period: *mut i64,
+            minimum_period: *mut i64,
+        ) -> HRESULT;
+        fn SetProcessingPeriod(&self, device: PCWSTR, period: *mut i64) -> HRESULT;
+        fn GetShareMode(&self, device: PCWSTR, mode: *mut c_void) -> HRESULT;
+        fn SetShareMode(&self, device: PCWSTR, mode: *mut c_void) -> HRESULT;
+        fn GetPropertyValue(
+            &self,
+            device: PCWSTR,
+            key: *const c_void,
+            value: *mut c_void,
+        ) -> HRESULT;
+        fn SetPropertyValue(
+            &self,
+            device: PCWSTR,
+            key: *const c_void,
+            value: *mut c_void,
+        ) -> HRESULT;
+        pub(super) fn SetDefaultEndpoint(&self, device: PCWSTR, role: ERole) -> HRESULT;
+        fn SetEndpointVisibility(&self, device: PCWSTR, visible: i32) -> HRESULT;
+    }
+}
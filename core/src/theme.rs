@@ -0,0 +1,413 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{ThemeSelection, utils::themes_dir};
+
+/// Complete, every other theme starts from it.
+const DARK: &str = include_str!("../themes/dark.toml");
+const PRESETS: [(&str, &str); 2] = [
+    ("light", include_str!("../themes/light.toml")),
+    ("high-contrast", include_str!("../themes/high-contrast.toml")),
+];
+
+/// How the island looks. Theme files only need the keys they change; the rest comes from
+/// the preset they `extends`, or the dark one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Theme {
+    pub colors: ThemeColors,
+    pub metrics: ThemeMetrics,
+    pub effects: ThemeEffects,
+    pub motion: ThemeMotion,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ThemeColors {
+    pub island_bg: Rgba,
+    pub island_border: Rgba,
+    pub island_shadow: Rgba,
+    pub text_primary: Rgba,
+    pub text_secondary: Rgba,
+    pub accent: Rgba,
+    pub on_accent: Rgba,
+    pub track: Rgba,
+    pub track_faint: Rgba,
+    pub track_strong: Rgba,
+    pub track_hover: Rgba,
+    pub hover: Rgba,
+    pub danger: Rgba,
+    pub mic: Rgba,
+    pub camera: Rgba,
+    pub screen_capture: Rgba,
+    pub location: Rgba,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ThemeMetrics {
+    pub radius: f32,
+    pub border_width: f32,
+    pub font_family: String,
+    /// Points.
+    pub font_size: f32,
+    pub spacing: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ThemeEffects {
+    pub shadow_blur: f32,
+    pub opacity: f32,
+}
+
+/// Durations in milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ThemeMotion {
+    pub curve: Curve,
+    pub resize: u32,
+    pub slide: u32,
+    pub fade: u32,
+}
+
+/// The UI can only animate along curves it was compiled with, so themes pick one by name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Curve {
+    #[default]
+    Spring,
+    Smooth,
+    Linear,
+}
+
+/// A color written as `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl TryFrom<String> for Rgba {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        let hex =
+            value.strip_prefix('#').ok_or_else(|| anyhow!("`{value}` is not a #hex color"))?;
+        // from_str_radix would also take a sign
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("`{value}` is not a #hex color");
+        }
+
+        let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).map(|d| d * 17);
+        let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+
+        let channels = match hex.len() {
+            3 => [digit(0), digit(1), digit(2), Ok(255)],
+            4 => [digit(0), digit(1), digit(2), digit(3)],
+            6 => [pair(0), pair(2), pair(4), Ok(255)],
+            8 => [pair(0), pair(2), pair(4), pair(6)],
+            _ => bail!("`{value}` is not a #hex color"),
+        };
+        let [r, g, b, a] =
+            channels.map(|c| c.map_err(|_| anyhow!("`{value}` is not a #hex color")));
+
+        Ok(Self { r: r?, g: g?, b: b?, a: a? })
+    }
+}
+
+impl From<Rgba> for String {
+    fn from(value: Rgba) -> Self {
+        format!("#{:02x}{:02x}{:02x}{:02x}", value.r, value.g, value.b, value.a)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::preset("dark").unwrap()
+    }
+}
+
+impl Theme {
+    pub fn preset(name: &str) -> Option<Self> {
+        if name == "dark" {
+            return Some(toml::from_str(DARK).expect("built-in dark theme is broken"));
+        }
+
+        let (_, source) = PRESETS.iter().find(|(preset, _)| *preset == name)?;
+
+        match Self::parse(source, false) {
+            Ok(theme) => Some(theme),
+            Err(e) => panic!("built-in theme {name} is broken: {e:#}"),
+        }
+    }
+
+    /// The theme `selection` names, where `auto` means the dark or light one depending on
+    /// `dark`. Falls back to a preset when the file can't be read.
+    pub fn load(selection: &ThemeSelection, dark: bool) -> Self {
+        let name = selection.resolve(dark);
+
+        match theme_file(name) {
+            Some(path) => match Self::from_file(&path) {
+                Ok(theme) => return theme,
+                Err(e) => eprintln!("[Theme] Failed to load {}: {e:#}", path.display()),
+            },
+            None if Self::preset(name).is_none() => eprintln!("[Theme] No theme named {name}"),
+            None => {}
+        }
+
+        Self::preset(name)
+            .unwrap_or_else(|| Self::preset(if dark { "dark" } else { "light" }).unwrap())
+    }
+
+    /// When the file behind `selection` was last written, for noticing edits. `None` for
+    /// presets.
+    pub fn modified(selection: &ThemeSelection, dark: bool) -> Option<SystemTime> {
+        let path = theme_file(selection.resolve(dark))?;
+
+        std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+        Self::parse(&contents, json)
+    }
+
+    fn parse(contents: &str, json: bool) -> Result<Self> {
+        let mut table: toml::Table =
+            if json { serde_json::from_str(contents)? } else { toml::from_str(contents)? };
+
+        let base = match table.remove("extends") {
+            Some(extends) => {
+                let extends = extends.as_str().context("`extends` must be a theme name")?;
+                Self::preset(extends).ok_or_else(|| anyhow!("no preset named {extends}"))?
+            }
+            None => Self::default(),
+        };
+
+        let mut merged = toml::Table::try_from(base)?;
+        merge(&mut merged, table);
+
+        Ok(merged.try_into()?)
+    }
+}
+
+fn merge(base: &mut toml::Table, changes: toml::Table) {
+    for (key, value) in changes {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(section)), toml::Value::Table(changes)) => {
+                merge(section, changes)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// A user theme called `name` in the themes folder, or `name` itself when it's a path.
+fn theme_file(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.extension().is_some() {
+        let path = themes_dir().join(path);
+        return path.is_file().then_some(path);
+    }
+
+    ["toml", "json"]
+        .into_iter()
+        .map(|ext| themes_dir().join(format!("{name}.{ext}")))
+        .find(|path| path.is_file())
+}
+
+impl ThemeSelection {
+    fn resolve(&self, dark: bool) -> &str {
+        match self {
+            ThemeSelection::Auto if dark => "dark",
+            ThemeSelection::Auto => "light",
+            ThemeSelection::Named(name) => name,
+        }
+    }
+}
+
+#[cfg(windows)]
+const PERSONALIZE_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize";
+
+/// Whether the OS asks apps for dark colors; `None` when it doesn't say.
+#[cfg(windows)]
+pub fn system_prefers_dark() -> Option<bool> {
+    let hkcu = winreg::RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
+    let key = hkcu.open_subkey(PERSONALIZE_KEY).ok()?;
+    let light: u32 = key.get_value("AppsUseLightTheme").ok()?;
+
+    Some(light == 0)
+}
+
+/// Pings `changed` whenever the app color setting is written. Only returns on error.
+#[cfg(windows)]
+pub(crate) fn watch_system_theme(changed: &crossbeam_channel::Sender<()>) -> Result<()> {
+    use windows::Win32::System::Registry::{
+        HKEY, REG_NOTIFY_CHANGE_LAST_SET, RegNotifyChangeKeyValue,
+    };
+
+    let hkcu = winreg::RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
+    let key = hkcu.open_subkey_with_flags(PERSONALIZE_KEY, winreg::enums::KEY_NOTIFY)?;
+
+    loop {
+        // without an event to signal the call itself waits for the next change
+        unsafe {
+            RegNotifyChangeKeyValue(
+                HKEY(key.raw_handle() as _),
+                false,
+                REG_NOTIFY_CHANGE_LAST_SET,
+                None,
+                false,
+            )
+        }
+        .ok()?;
+
+        let _ = changed.try_send(());
+    }
+}
+
+/// Whether the desktop asks apps for dark colors; `None` when it doesn't say. Reads the
+/// GNOME setting most desktops follow, then the GTK theme's name.
+#[cfg(target_os = "linux")]
+pub fn system_prefers_dark() -> Option<bool> {
+    let gsettings = |key: &str| {
+        let output = std::process::Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.interface", key])
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        Some(String::from_utf8_lossy(&output.stdout).trim().trim_matches('\'').to_lowercase())
+    };
+
+    match gsettings("color-scheme").as_deref() {
+        Some("prefer-dark") => return Some(true),
+        Some("prefer-light") => return Some(false),
+        _ => {}
+    }
+
+    let gtk_theme = gsettings("gtk-theme").or_else(|| std::env::var("GTK_THEME").ok())?;
+    Some(gtk_theme.to_lowercase().contains("dark"))
+}
+
+/// Pings `changed` whenever one of the settings [`system_prefers_dark`] reads changes.
+/// Only returns on error.
+#[cfg(target_os = "linux")]
+pub(crate) fn watch_system_theme(changed: &crossbeam_channel::Sender<()>) -> Result<()> {
+    let monitor = crate::utils::child::ChildLines::spawn(
+        std::process::Command::new("gsettings").args(["monitor", "org.gnome.desktop.interface"]),
+    )?;
+
+    // "color-scheme: 'prefer-dark'", among fonts, cursors and the rest of the schema
+    for line in monitor {
+        let line = line?;
+        if line.starts_with("color-scheme:") || line.starts_with("gtk-theme:") {
+            let _ = changed.try_send(());
+        }
+    }
+
+    bail!("gsettings monitor exited")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(value: &str) -> Result<Rgba> {
+        Rgba::try_from(value.to_string())
+    }
+
+    #[test]
+    fn every_preset_parses() {
+        let dark = Theme::preset("dark").unwrap();
+
+        for (name, _) in PRESETS {
+            let preset = Theme::preset(name).unwrap();
+            assert_ne!(preset, dark, "{name} is the dark theme");
+        }
+        assert!(Theme::preset("solarized").is_none());
+    }
+
+    #[test]
+    fn a_theme_file_only_changes_its_own_keys() {
+        let theme = Theme::parse(
+            r##"
+            extends = "light"
+
+            [colors]
+            accent = "#ff0000"
+
+            [metrics]
+            radius = 12
+            "##,
+            false,
+        )
+        .unwrap();
+
+        let mut expected = Theme::preset("light").unwrap();
+        expected.colors.accent = rgba("#ff0000").unwrap();
+        expected.metrics.radius = 12.0;
+        assert_eq!(theme, expected);
+
+        // and the dark one without `extends`
+        let theme = Theme::parse("[effects]\nopacity = 0.8", false).unwrap();
+        let mut expected = Theme::default();
+        expected.effects.opacity = 0.8;
+        assert_eq!(theme, expected);
+    }
+
+    #[test]
+    fn json_themes_read_like_toml_ones() {
+        let toml = "extends = \"high-contrast\"\n[motion]\ncurve = \"linear\"\nfade = 0";
+        let json = r#"{ "extends": "high-contrast", "motion": { "curve": "linear", "fade": 0 } }"#;
+
+        assert_eq!(Theme::parse(json, true).unwrap(), Theme::parse(toml, false).unwrap());
+    }
+
+    #[test]
+    fn bad_theme_files_are_errors() {
+        for contents in [
+            "[colors]\naccent = \"red\"",
+            "[colors]\naccent = 16711680",
+            "[metrics]\nradius = \"round\"",
+            "[motion]\ncurve = \"bouncy\"",
+            "extends = \"solarized\"",
+            "extends = 1",
+            "[colors",
+        ] {
+            assert!(Theme::parse(contents, false).is_err(), "{contents}");
+        }
+    }
+
+    #[test]
+    fn colors_take_every_hex_form() {
+        let color = |r, g, b, a| Rgba { r, g, b, a };
+
+        assert_eq!(rgba("#f80").unwrap(), color(0xff, 0x88, 0x00, 0xff));
+        assert_eq!(rgba("#f808").unwrap(), color(0xff, 0x88, 0x00, 0x88));
+        assert_eq!(rgba("#1a2B3c").unwrap(), color(0x1a, 0x2b, 0x3c, 0xff));
+        assert_eq!(rgba("#1a2b3c9a").unwrap(), color(0x1a, 0x2b, 0x3c, 0x9a));
+
+        // written back out in full
+        assert_eq!(String::from(rgba("#f80").unwrap()), "#ff8800ff");
+
+        for bad in
+            ["", "#", "f80", "#f8", "#f8000", "#f80000000", "#g80", "#+f80", "#+f8000", "#ä80"]
+        {
+            assert!(rgba(bad).is_err(), "{bad}");
+        }
+    }
+}
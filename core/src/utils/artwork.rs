@@ -1,15 +1,22 @@
 use anyhow::Result;
+#[cfg(windows)]
 use windows::{
     Media::Control::GlobalSystemMediaTransportControlsSessionMediaProperties,
     Storage::Streams::{Buffer, DataReader, InputStreamOptions},
 };
 use xxhash_rust::xxh3::xxh3_64;
 
-use crate::utils::artwork_dir;
+use crate::{
+    Palette,
+    utils::{artwork_dir, palette::cached_palette},
+};
 
+/// Saves the session's artwork as a PNG named after its hash, and returns its path along
+/// with its palette.
+#[cfg(windows)]
 pub async fn extract_album_art(
     props: &GlobalSystemMediaTransportControlsSessionMediaProperties,
-) -> Result<Option<String>> {
+) -> Result<Option<(String, Option<Palette>)>> {
     let thumbnail = match props.Thumbnail() {
         Ok(t) => t,
         Err(_) => return Ok(None),
@@ -28,6 +35,35 @@ pub async fn extract_album_art(
     let mut bytes = vec![0u8; size as usize];
     reader.ReadBytes(&mut bytes)?;
 
+    save_album_art(bytes).map(Some)
+}
+
+/// Loads the artwork behind an MPRIS `mpris:artUrl`: a local file, or a download through
+/// `curl` for players that only link to it, like Spotify.
+#[cfg(target_os = "linux")]
+pub fn load_album_art(url: &str) -> Result<Option<(String, Option<Palette>)>> {
+    use anyhow::{Context, bail};
+
+    let bytes = if let Some(path) = url.strip_prefix("file://") {
+        std::fs::read(percent_decode(path))?
+    } else if url.starts_with("https://") || url.starts_with("http://") {
+        let output = std::process::Command::new("curl")
+            .args(["--silent", "--fail", "--location", "--max-time", "5", url])
+            .stdin(std::process::Stdio::null())
+            .output()
+            .context("Failed to run curl")?;
+        if !output.status.success() {
+            bail!("curl {url} exited with {}", output.status);
+        }
+        output.stdout
+    } else {
+        return Ok(None);
+    };
+
+    save_album_art(bytes).map(Some)
+}
+
+fn save_album_art(bytes: Vec<u8>) -> Result<(String, Option<Palette>)> {
     let hash = format!("{:016x}", xxh3_64(&bytes));
 
     let dir = artwork_dir();
@@ -36,12 +72,41 @@ pub async fn extract_album_art(
 
     if path.exists() {
         drop(bytes);
-        return Ok(Some(path.to_string_lossy().to_string()));
+        let palette = cached_palette(&path, None);
+        return Ok((path.to_string_lossy().to_string(), palette));
     }
 
     let img = image::load_from_memory(&bytes)?;
     drop(bytes);
     img.save(&path)?;
+    let palette = cached_palette(&path, Some(&img));
+
+    Ok((path.to_string_lossy().to_string(), palette))
+}
+
+/// `/home/me/My%20Music/cover.jpg` to `/home/me/My Music/cover.jpg`.
+#[cfg(target_os = "linux")]
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match (bytes[idx], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
 
-    Ok(Some(path.to_string_lossy().to_string()))
+    String::from_utf8_lossy(&decoded).into_owned()
 }
@@ -0,0 +1,61 @@
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::utils::child::ChildLines;
+
+/// How long a player gets to answer a call; a hung one shouldn't hold up the rest.
+const CALL_TIMEOUT_SECS: u32 = 2;
+
+/// Runs `busctl` against the session bus and returns stdout.
+pub fn busctl(args: &[&str]) -> Result<String> {
+    let output = Command::new("busctl")
+        .args(["--user", &format!("--timeout={CALL_TIMEOUT_SECS}")])
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run busctl")?;
+
+    if !output.status.success() {
+        // e.g. "Call failed: cannot go back"
+        let error = String::from_utf8_lossy(&output.stderr);
+        bail!("busctl {} failed: {}", args.join(" "), error.trim());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Same as [`busctl`] for a `call` or `get-property`, returning the first value of the
+/// reply.
+pub fn busctl_json<T: DeserializeOwned>(args: &[&str]) -> Result<T> {
+    #[derive(Deserialize)]
+    struct Reply<T> {
+        // {"type":"as","data":[["org.freedesktop.DBus",":1.0"]]}
+        data: (T,),
+    }
+
+    let mut full_args = vec!["--json=short"];
+    full_args.extend_from_slice(args);
+
+    let reply: Reply<T> = serde_json::from_str(&busctl(&full_args)?)?;
+    Ok(reply.data.0)
+}
+
+/// A value of any type, the way `busctl --json` writes a `v`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Variant {
+    pub data: Value,
+}
+
+/// The session bus messages matching any of `matches`, a JSON object per line.
+pub fn monitor(matches: &[&str]) -> Result<ChildLines> {
+    let mut command = Command::new("busctl");
+    command.args(["--user", "--json=short", "monitor"]);
+    for rule in matches {
+        command.arg(format!("--match={rule}"));
+    }
+
+    ChildLines::spawn(&mut command)
+}
@@ -0,0 +1,49 @@
+use std::{
+    io::{self, BufRead, BufReader, Lines},
+    process::{Child, ChildStdout, Command, Stdio},
+};
+
+use anyhow::{Context, Result, anyhow};
+
+/// The stdout of a long-running command like `pactl subscribe`, a line at a time. The
+/// process is killed when this is dropped, so a watcher that bails out early doesn't leave
+/// it behind.
+pub struct ChildLines {
+    child: Child,
+    lines: Lines<BufReader<ChildStdout>>,
+}
+
+impl ChildLines {
+    pub fn spawn(command: &mut Command) -> Result<Self> {
+        let program = command.get_program().to_string_lossy().to_string();
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to run {program}"))?;
+
+        let Some(stdout) = child.stdout.take() else {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow!("{program} has no stdout"));
+        };
+
+        Ok(Self { child, lines: BufReader::new(stdout).lines() })
+    }
+}
+
+impl Iterator for ChildLines {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.next()
+    }
+}
+
+impl Drop for ChildLines {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
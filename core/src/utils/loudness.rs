@@ -0,0 +1,454 @@
+use std::{collections::VecDeque, f64::consts::PI};
+
+use crate::LoudnessState;
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+const STEPS_PER_SECOND: usize = 10;
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+
+const TRUE_PEAK_TAPS: usize = 49;
+
+// gating blocks are binned by loudness so the integrated value doesn't need all of them
+const HISTOGRAM_BINS_PER_LU: f64 = 10.0;
+const HISTOGRAM_MAX_LUFS: f64 = 10.0;
+const HISTOGRAM_BINS: usize =
+    ((HISTOGRAM_MAX_LUFS - ABSOLUTE_GATE_LUFS) * HISTOGRAM_BINS_PER_LU) as usize;
+
+/// ITU-R BS.1770-4 loudness meter: K-weighted momentary (400ms), short-term (3s) and
+/// gated integrated loudness, plus 4x oversampled true-peak.
+pub struct LoudnessMeter {
+    channels: Vec<ChannelState>,
+    step_frames: usize,
+    step_position: usize,
+    step_energies: VecDeque<f64>,
+    gating_blocks: GatingHistogram,
+    integrated: f64,
+    true_peak: f32,
+}
+
+struct ChannelState {
+    weight: f64,
+    pre_filter: Biquad,
+    rlb_filter: Biquad,
+    step_sum: f64,
+    oversampler: TruePeakInterpolator,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: f32, channels: usize) -> Self {
+        let sample_rate = sample_rate as f64;
+        let oversampling = if sample_rate < 96_000.0 {
+            4
+        } else if sample_rate < 192_000.0 {
+            2
+        } else {
+            1
+        };
+
+        let channels = (0..channels)
+            .map(|idx| ChannelState {
+                weight: channel_weight(idx, channels),
+                pre_filter: Biquad::high_shelf(sample_rate),
+                rlb_filter: Biquad::high_pass(sample_rate),
+                step_sum: 0.0,
+                oversampler: TruePeakInterpolator::new(oversampling),
+            })
+            .collect();
+
+        Self {
+            channels,
+            step_frames: (sample_rate as usize / STEPS_PER_SECOND).max(1),
+            step_position: 0,
+            step_energies: VecDeque::with_capacity(SHORT_TERM_STEPS + 1),
+            gating_blocks: GatingHistogram::new(),
+            integrated: f64::NEG_INFINITY,
+            true_peak: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for channel in &mut self.channels {
+            channel.pre_filter.reset();
+            channel.rlb_filter.reset();
+            channel.step_sum = 0.0;
+            channel.oversampler.reset();
+        }
+
+        self.step_position = 0;
+        self.step_energies.clear();
+        self.gating_blocks.clear();
+        self.integrated = f64::NEG_INFINITY;
+        self.true_peak = 0.0;
+    }
+
+    /// Feeds one interleaved frame; `frame` must hold one sample per configured channel.
+    #[inline(always)]
+    pub fn push_frame(&mut self, frame: impl IntoIterator<Item = f32>) {
+        for (channel, sample) in self.channels.iter_mut().zip(frame) {
+            let filtered = channel.rlb_filter.process(channel.pre_filter.process(sample as f64));
+            channel.step_sum += filtered * filtered;
+
+            self.true_peak = self.true_peak.max(channel.oversampler.process(sample));
+        }
+
+        self.step_position += 1;
+        if self.step_position >= self.step_frames {
+            self.finish_step();
+        }
+    }
+
+    fn finish_step(&mut self) {
+        let mut energy = 0.0;
+        for channel in &mut self.channels {
+            energy += channel.weight * channel.step_sum / self.step_frames as f64;
+            channel.step_sum = 0.0;
+        }
+        self.step_position = 0;
+
+        self.step_energies.push_back(energy);
+        if self.step_energies.len() > SHORT_TERM_STEPS {
+            self.step_energies.pop_front();
+        }
+
+        // gating blocks are 400ms long with 75% overlap, so one completes on every step
+        if self.step_energies.len() >= MOMENTARY_STEPS {
+            let block = mean_energy(self.step_energies.iter().rev().take(MOMENTARY_STEPS));
+            if energy_to_lufs(block) > ABSOLUTE_GATE_LUFS {
+                self.gating_blocks.push(block);
+                self.integrated = self.gating_blocks.gated_loudness();
+            }
+        }
+    }
+
+    pub fn state(&self) -> LoudnessState {
+        let momentary = if self.step_energies.len() >= MOMENTARY_STEPS {
+            energy_to_lufs(mean_energy(self.step_energies.iter().rev().take(MOMENTARY_STEPS)))
+        } else {
+            f64::NEG_INFINITY
+        };
+
+        let short_term = if self.step_energies.len() >= SHORT_TERM_STEPS {
+            energy_to_lufs(mean_energy(self.step_energies.iter()))
+        } else {
+            f64::NEG_INFINITY
+        };
+
+        LoudnessState {
+            momentary: momentary as f32,
+            short_term: short_term as f32,
+            integrated: self.integrated as f32,
+            true_peak: if self.true_peak > 0.0 {
+                20.0 * self.true_peak.log10()
+            } else {
+                f32::NEG_INFINITY
+            },
+        }
+    }
+}
+
+/// Gating blocks that passed the absolute gate, counted and summed per 0.1 LU of loudness.
+/// The relative gate then lands on a bin boundary, which is well inside the meter's
+/// tolerance, and a session of any length costs the same to gate.
+struct GatingHistogram {
+    counts: Vec<u64>,
+    energies: Vec<f64>,
+    count: u64,
+    energy: f64,
+}
+
+impl GatingHistogram {
+    fn new() -> Self {
+        Self {
+            counts: vec![0; HISTOGRAM_BINS],
+            energies: vec![0.0; HISTOGRAM_BINS],
+            count: 0,
+            energy: 0.0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.counts.fill(0);
+        self.energies.fill(0.0);
+        self.count = 0;
+        self.energy = 0.0;
+    }
+
+    fn bin(lufs: f64) -> usize {
+        let bin = ((lufs - ABSOLUTE_GATE_LUFS) * HISTOGRAM_BINS_PER_LU).floor();
+        (bin.max(0.0) as usize).min(HISTOGRAM_BINS - 1)
+    }
+
+    fn push(&mut self, block: f64) {
+        let bin = Self::bin(energy_to_lufs(block));
+        self.counts[bin] += 1;
+        self.energies[bin] += block;
+        self.count += 1;
+        self.energy += block;
+    }
+
+    fn gated_loudness(&self) -> f64 {
+        if self.count == 0 {
+            return f64::NEG_INFINITY;
+        }
+
+        let relative_gate = energy_to_lufs(self.energy / self.count as f64) + RELATIVE_GATE_LU;
+        let first = Self::bin(relative_gate);
+
+        let count = self.counts[first..].iter().sum::<u64>();
+        let energy = self.energies[first..].iter().sum::<f64>();
+        if count == 0 {
+            return f64::NEG_INFINITY;
+        }
+
+        energy_to_lufs(energy / count as f64)
+    }
+}
+
+fn channel_weight(idx: usize, channels: usize) -> f64 {
+    // WAVEFORMATEXTENSIBLE order: FL FR FC LFE BL BR SL SR
+    match (channels, idx) {
+        (6 | 8, 3) => 0.0,
+        (6 | 8, 4..) => 1.41,
+        _ => 1.0,
+    }
+}
+
+fn mean_energy<'a>(energies: impl Iterator<Item = &'a f64>) -> f64 {
+    let (sum, count) = energies.fold((0.0, 0usize), |(sum, count), e| (sum + e, count + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+#[inline(always)]
+fn energy_to_lufs(energy: f64) -> f64 {
+    if energy <= 0.0 { f64::NEG_INFINITY } else { -0.691 + 10.0 * energy.log10() }
+}
+
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    /// Stage 1 of the K-weighting curve, re-derived for `sample_rate` rather than the
+    /// 48kHz coefficient table in the recommendation.
+    fn high_shelf(sample_rate: f64) -> Self {
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+
+        Self {
+            b: [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2],
+        }
+    }
+
+    /// Stage 2 of the K-weighting curve (the "RLB" high-pass).
+    fn high_pass(sample_rate: f64) -> Self {
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+
+        Self {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2],
+        }
+    }
+
+    #[inline(always)]
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+
+    fn reset(&mut self) {
+        self.z = [0.0; 2];
+    }
+}
+
+/// Polyphase windowed-sinc interpolator used to estimate inter-sample peaks.
+struct TruePeakInterpolator {
+    phases: Vec<Vec<f32>>,
+    /// The last `len` samples, newest first from `cursor`, written twice over so they can
+    /// be read as one slice.
+    history: Vec<f32>,
+    len: usize,
+    cursor: usize,
+}
+
+impl TruePeakInterpolator {
+    fn new(factor: usize) -> Self {
+        let mut phases = vec![Vec::new(); factor];
+
+        if factor == 1 {
+            phases[0].push(1.0);
+        } else {
+            for tap in 0..TRUE_PEAK_TAPS {
+                let m = tap as f64 - (TRUE_PEAK_TAPS - 1) as f64 / 2.0;
+                let x = m * PI / factor as f64;
+                let sinc = if m.abs() < 1e-9 { 1.0 } else { x.sin() / x };
+                let window =
+                    0.5 * (1.0 - (2.0 * PI * tap as f64 / (TRUE_PEAK_TAPS - 1) as f64).cos());
+
+                phases[tap % factor].push((sinc * window) as f32);
+            }
+        }
+
+        let len = phases.iter().map(Vec::len).max().unwrap_or(1);
+
+        Self { phases, history: vec![0.0; len * 2], len, cursor: 0 }
+    }
+
+    #[inline(always)]
+    fn process(&mut self, sample: f32) -> f32 {
+        let len = self.len;
+        self.cursor = (self.cursor + len - 1) % len;
+        self.history[self.cursor] = sample;
+        self.history[self.cursor + len] = sample;
+
+        let recent = &self.history[self.cursor..self.cursor + len];
+        let mut peak = 0.0f32;
+        for phase in &self.phases {
+            let acc: f32 = phase.iter().zip(recent).map(|(coefficient, x)| coefficient * x).sum();
+            peak = peak.max(acc.abs());
+        }
+
+        peak
+    }
+
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.cursor = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    /// Stereo sine segments as `(dBFS, seconds)`, in the manner of EBU Tech 3341's test
+    /// signals.
+    fn meter_sine(frequency: f32, segments: &[(f32, f32)]) -> LoudnessMeter {
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        let mut frame = 0u64;
+
+        for &(dbfs, seconds) in segments {
+            let amplitude = 10f32.powf(dbfs / 20.0);
+            for _ in 0..(seconds * SAMPLE_RATE) as u64 {
+                let phase = (frame as f64 * frequency as f64 / SAMPLE_RATE as f64).fract();
+                let sample = amplitude * (phase * 2.0 * PI).sin() as f32;
+                meter.push_frame([sample, sample]);
+                frame += 1;
+            }
+        }
+
+        meter
+    }
+
+    /// Faded in over 10ms; a sine that starts abruptly really does overshoot between the
+    /// first samples, and that's not what these cases measure.
+    fn true_peak(frequency: f32, amplitude: f32, phase_degrees: f64) -> f32 {
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 1);
+        let phase = phase_degrees.to_radians();
+        let fade = (SAMPLE_RATE / 100.0) as u64;
+
+        for frame in 0..SAMPLE_RATE as u64 {
+            let t = frame as f64 * frequency as f64 / SAMPLE_RATE as f64;
+            let gain = (frame as f64 / fade as f64).min(1.0);
+            meter.push_frame([amplitude * (gain * (2.0 * PI * t + phase).sin()) as f32]);
+        }
+
+        meter.state().true_peak
+    }
+
+    fn assert_lu(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() <= 0.1, "{actual} LUFS, expected {expected}");
+    }
+
+    #[test]
+    fn steady_sine_reads_its_level() {
+        // Tech 3341 cases 1 and 2
+        for level in [-23.0, -33.0] {
+            let state = meter_sine(1000.0, &[(level, 20.0)]).state();
+
+            assert_lu(state.momentary, level);
+            assert_lu(state.short_term, level);
+            assert_lu(state.integrated, level);
+        }
+    }
+
+    #[test]
+    fn quiet_passages_are_gated_out_of_integrated() {
+        // Tech 3341 case 3: the relative gate drops the -36 dBFS parts
+        let state = meter_sine(1000.0, &[(-36.0, 10.0), (-23.0, 60.0), (-36.0, 10.0)]).state();
+
+        assert_lu(state.integrated, -23.0);
+    }
+
+    #[test]
+    fn near_silence_is_gated_out_of_integrated() {
+        // Tech 3341 case 4: and the absolute gate drops the -72 dBFS ones
+        let state = meter_sine(
+            1000.0,
+            &[(-72.0, 10.0), (-36.0, 10.0), (-23.0, 60.0), (-36.0, 10.0), (-72.0, 10.0)],
+        )
+        .state();
+
+        assert_lu(state.integrated, -23.0);
+    }
+
+    #[test]
+    fn silence_reads_negative_infinity() {
+        let state = meter_sine(1000.0, &[(-100.0, 5.0)]).state();
+
+        assert_eq!(state.integrated, f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn true_peak_finds_peaks_between_samples() {
+        // Tech 3341 cases 15 to 18: every phase peaks at -6 dBTP, whatever the samples say.
+        // The tolerance is +0.2/-0.4 dB.
+        for (frequency, phase) in
+            [(12_000.0, 0.0), (12_000.0, 45.0), (8000.0, 60.0), (6000.0, 67.5)]
+        {
+            let peak = true_peak(frequency, 0.5, phase);
+            assert!((-6.4..=-5.8).contains(&peak), "{peak} dBTP at {frequency} Hz, {phase}°");
+        }
+
+        // case 19: samples at full scale hide a peak 3 dB over it
+        let peak = true_peak(12_000.0, 1.41, 45.0);
+        assert!((2.6..=3.2).contains(&peak), "{peak} dBTP");
+    }
+
+    #[test]
+    fn reset_forgets_everything() {
+        let mut meter = meter_sine(1000.0, &[(-23.0, 5.0)]);
+        meter.reset();
+
+        let state = meter.state();
+        assert_eq!(state.momentary, f32::NEG_INFINITY);
+        assert_eq!(state.integrated, f32::NEG_INFINITY);
+        assert_eq!(state.true_peak, f32::NEG_INFINITY);
+    }
+}
@@ -1,13 +1,45 @@
 use std::path::PathBuf;
 
 pub mod artwork;
+#[cfg(target_os = "linux")]
+pub mod busctl;
+#[cfg(target_os = "linux")]
+pub mod child;
+#[cfg(windows)]
 pub mod icon;
+pub mod loudness;
+#[cfg(windows)]
 pub mod name;
+#[cfg(target_os = "linux")]
+pub mod pactl;
+pub mod palette;
+#[cfg(windows)]
+pub mod process;
+#[cfg(target_os = "linux")]
+pub mod procfs;
 pub mod simd_audio;
 
 pub fn cache_dir() -> PathBuf {
     dirs::cache_dir().unwrap().join("Lumen")
 }
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir().unwrap().join("Lumen")
+}
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+pub fn themes_dir() -> PathBuf {
+    config_dir().join("themes")
+}
+pub fn lyrics_dir() -> PathBuf {
+    config_dir().join("lyrics")
+}
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir().unwrap().join("Lumen")
+}
+pub fn privacy_log_path() -> PathBuf {
+    data_dir().join("privacy.jsonl")
+}
 pub fn artwork_dir() -> PathBuf {
     cache_dir().join("artwork")
 }
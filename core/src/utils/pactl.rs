@@ -0,0 +1,34 @@
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, bail};
+use serde::de::DeserializeOwned;
+
+use crate::utils::child::ChildLines;
+
+/// Runs `pactl` against the running PulseAudio or PipeWire pulse server and returns stdout.
+pub fn pactl(args: &[&str]) -> Result<String> {
+    let output = Command::new("pactl")
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to run pactl")?;
+
+    if !output.status.success() {
+        bail!("pactl {} exited with {}", args.join(" "), output.status);
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Same as [`pactl`], asking for and parsing its JSON output.
+pub fn pactl_json<T: DeserializeOwned>(args: &[&str]) -> Result<T> {
+    let mut full_args = vec!["--format=json"];
+    full_args.extend_from_slice(args);
+
+    Ok(serde_json::from_str(&pactl(&full_args)?)?)
+}
+
+/// `pactl subscribe`, a line per event, e.g. "Event 'new' on source-output #87".
+pub fn subscribe() -> Result<ChildLines> {
+    ChildLines::spawn(Command::new("pactl").arg("subscribe"))
+}
@@ -0,0 +1,219 @@
+use std::path::Path;
+
+use image::{DynamicImage, imageops};
+use serde::{Deserialize, Serialize};
+
+use crate::Rgba;
+
+/// Artwork is scaled down to this many pixels a side before counting colors.
+const SAMPLE_SIZE: u32 = 64;
+/// Bits kept per channel when grouping similar colors.
+const BUCKET_BITS: u32 = 4;
+
+/// The colors an album cover is mostly made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Palette {
+    /// The most common color.
+    pub dominant: Rgba,
+    /// The most common colorful one, or `dominant` when the art is all greys.
+    pub vibrant: Rgba,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    count: u32,
+    sum: [u32; 3],
+}
+
+struct Swatch {
+    color: Rgba,
+    count: u32,
+    saturation: f32,
+    lightness: f32,
+}
+
+/// Picks the palette of `image`. The same image always gives the same palette; `None` when
+/// it's entirely transparent.
+pub fn extract_palette(image: &DynamicImage) -> Option<Palette> {
+    let sample = imageops::thumbnail(image, SAMPLE_SIZE, SAMPLE_SIZE);
+
+    let shift = 8 - BUCKET_BITS;
+    let mut buckets = vec![Bucket::default(); 1 << (BUCKET_BITS * 3)];
+
+    for pixel in sample.pixels() {
+        let [r, g, b, a] = pixel.0;
+        if a < 128 {
+            continue;
+        }
+
+        let index = ((r as usize >> shift) << (BUCKET_BITS * 2))
+            | ((g as usize >> shift) << BUCKET_BITS)
+            | (b as usize >> shift);
+        let bucket = &mut buckets[index];
+
+        bucket.count += 1;
+        bucket.sum[0] += r as u32;
+        bucket.sum[1] += g as u32;
+        bucket.sum[2] += b as u32;
+    }
+
+    let swatches: Vec<Swatch> = buckets
+        .iter()
+        .filter(|bucket| bucket.count > 0)
+        .map(|bucket| {
+            let [r, g, b] = bucket.sum.map(|sum| ((sum + bucket.count / 2) / bucket.count) as u8);
+            let (saturation, lightness) = saturation_lightness(r, g, b);
+
+            Swatch { color: Rgba { r, g, b, a: 255 }, count: bucket.count, saturation, lightness }
+        })
+        .collect();
+
+    // ties go to the first bucket, so the result never depends on anything but the pixels
+    let dominant = swatches.iter().rev().max_by_key(|swatch| swatch.count)?;
+    let vibrant = swatches
+        .iter()
+        .rev()
+        .filter(|swatch| swatch.saturation >= 0.35 && (0.2..=0.85).contains(&swatch.lightness))
+        .max_by(|a, b| vibrancy(a).total_cmp(&vibrancy(b)))
+        .unwrap_or(dominant);
+
+    Some(Palette { dominant: dominant.color, vibrant: vibrant.color })
+}
+
+/// How well a swatch would work as an accent: common, colorful and neither near black nor
+/// near white.
+fn vibrancy(swatch: &Swatch) -> f32 {
+    let midtone = 1.0 - (swatch.lightness - 0.5).abs() * 2.0;
+
+    swatch.count as f32 * swatch.saturation * (0.5 + midtone)
+}
+
+/// HSL saturation and lightness, both 0 to 1.
+fn saturation_lightness(r: u8, g: u8, b: u8) -> (f32, f32) {
+    let max = r.max(g).max(b) as f32 / 255.0;
+    let min = r.min(g).min(b) as f32 / 255.0;
+    let lightness = (max + min) / 2.0;
+
+    if max == min {
+        return (0.0, lightness);
+    }
+
+    let saturation = (max - min) / (1.0 - (2.0 * lightness - 1.0).abs());
+    (saturation.min(1.0), lightness)
+}
+
+/// The palette of the artwork saved at `path`, kept in a file next to it so the art is only
+/// decoded once. Pass `image` when it's already decoded.
+pub fn cached_palette(path: &Path, image: Option<&DynamicImage>) -> Option<Palette> {
+    let cache = path.with_extension("palette.json");
+
+    if let Ok(contents) = std::fs::read_to_string(&cache)
+        && let Ok(palette) = serde_json::from_str(&contents)
+    {
+        return Some(palette);
+    }
+
+    let palette = match image {
+        Some(image) => extract_palette(image)?,
+        None => extract_palette(&image::open(path).ok()?)?,
+    };
+
+    if let Ok(contents) = serde_json::to_string(&palette)
+        && let Err(e) = std::fs::write(&cache, contents)
+    {
+        eprintln!("[Artwork] Failed to cache palette for {}: {e}", path.display());
+    }
+
+    Some(palette)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageBuffer, Rgba as Pixel, RgbaImage};
+
+    use super::*;
+
+    fn rgba(r: u8, g: u8, b: u8) -> Rgba {
+        Rgba { r, g, b, a: 255 }
+    }
+
+    /// A cover with `background` everywhere but a `size`-pixel square of `patch` in the
+    /// top-left corner.
+    fn cover(background: [u8; 4], patch: [u8; 4], size: u32) -> DynamicImage {
+        let image: RgbaImage = ImageBuffer::from_fn(200, 200, |x, y| {
+            if x < size && y < size { Pixel(patch) } else { Pixel(background) }
+        });
+
+        DynamicImage::ImageRgba8(image)
+    }
+
+    #[test]
+    fn a_single_color_is_both_dominant_and_vibrant() {
+        let palette = extract_palette(&cover([51, 102, 204, 255], [51, 102, 204, 255], 0));
+
+        assert_eq!(
+            palette,
+            Some(Palette { dominant: rgba(51, 102, 204), vibrant: rgba(51, 102, 204) })
+        );
+    }
+
+    #[test]
+    fn a_small_colorful_patch_is_vibrant_on_a_dull_cover() {
+        let palette = extract_palette(&cover([40, 40, 44, 255], [220, 30, 40, 255], 60)).unwrap();
+
+        assert_eq!(palette.dominant, rgba(40, 40, 44));
+        assert_eq!(palette.vibrant, rgba(220, 30, 40));
+    }
+
+    #[test]
+    fn greyscale_art_falls_back_to_the_dominant_color() {
+        let image: RgbaImage = ImageBuffer::from_fn(128, 128, |x, _| {
+            let v = (x / 32 * 60) as u8;
+            Pixel([v, v, v, 255])
+        });
+        let palette = extract_palette(&DynamicImage::ImageRgba8(image)).unwrap();
+
+        assert_eq!(palette.vibrant, palette.dominant);
+    }
+
+    #[test]
+    fn transparent_pixels_are_ignored() {
+        let palette = extract_palette(&cover([0, 0, 0, 0], [30, 200, 90, 255], 20)).unwrap();
+        assert_eq!(palette.dominant, rgba(30, 200, 90));
+
+        assert_eq!(extract_palette(&cover([0, 0, 0, 0], [0, 0, 0, 0], 0)), None);
+    }
+
+    #[test]
+    fn the_same_art_always_gives_the_same_palette() {
+        let image: RgbaImage = ImageBuffer::from_fn(300, 300, |x, y| {
+            Pixel([(x * 7 % 256) as u8, (y * 13 % 256) as u8, ((x + y) % 256) as u8, 255])
+        });
+        let image = DynamicImage::ImageRgba8(image);
+
+        let first = extract_palette(&image);
+        assert!(first.is_some());
+        assert_eq!(first, extract_palette(&image));
+        assert_eq!(first, extract_palette(&image.clone()));
+    }
+
+    #[test]
+    fn palettes_are_cached_next_to_the_art() {
+        let dir = std::env::temp_dir().join(format!("lumen-palette-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cover.png");
+
+        let image = cover([40, 40, 44, 255], [220, 30, 40, 255], 60);
+        image.save(&path).unwrap();
+
+        let palette = cached_palette(&path, None);
+        assert_eq!(palette, extract_palette(&image));
+        assert!(dir.join("cover.palette.json").is_file());
+
+        // the cached copy wins over the art itself
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cached_palette(&path, None), palette);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
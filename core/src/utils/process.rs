@@ -0,0 +1,70 @@
+use std::path::Path;
+
+use windows::Win32::{
+    Foundation::{CloseHandle, HANDLE},
+    System::{
+        ProcessStatus::EnumProcesses,
+        Threading::{
+            OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+            QueryFullProcessImageNameW,
+        },
+    },
+};
+use windows_core::PWSTR;
+
+use crate::{
+    AppUsage,
+    utils::{icon::resolve_app_icon, name::resolve_name_from_aumid},
+};
+
+pub struct OwnedHandle(pub HANDLE);
+
+impl Drop for OwnedHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
+
+/// Full Win32 path of the executable behind `pid`.
+pub fn process_path(pid: u32) -> Option<String> {
+    unsafe {
+        let handle = OwnedHandle(OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?);
+
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as u32;
+        QueryFullProcessImageNameW(
+            handle.0,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut len,
+        )
+        .ok()?;
+
+        Some(String::from_utf16_lossy(&buffer[..len as usize]))
+    }
+}
+
+/// Finds a running process by executable path, for sources that only know the path.
+pub fn find_process(exe_path: &str) -> Option<u32> {
+    let mut pids = vec![0u32; 4096];
+    let mut needed = 0;
+
+    unsafe {
+        EnumProcesses(pids.as_mut_ptr(), (pids.len() * 4) as u32, &mut needed).ok()?;
+    }
+    pids.truncate(needed as usize / 4);
+
+    pids.into_iter()
+        .filter(|&pid| pid != 0)
+        .find(|&pid| process_path(pid).is_some_and(|path| path.eq_ignore_ascii_case(exe_path)))
+}
+
+pub async fn describe_process(pid: u32, exe_path: String) -> AppUsage {
+    // exe names go through the same fallbacks as bare-exe AUMIDs
+    let file_name = Path::new(&exe_path).file_name().map(|name| name.to_string_lossy().to_string());
+    let name = resolve_name_from_aumid(file_name.as_deref().unwrap_or(&exe_path));
+
+    AppUsage { pid, icon: resolve_app_icon(&exe_path).await, name, exe_path }
+}
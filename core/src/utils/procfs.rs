@@ -0,0 +1,27 @@
+use std::{fs, path::Path};
+
+use crate::AppUsage;
+
+/// Path of the executable behind `pid`, from `<proc_root>/<pid>/exe`.
+pub fn process_path(proc_root: &Path, pid: u32) -> Option<String> {
+    let path = fs::read_link(proc_root.join(pid.to_string()).join("exe")).ok()?;
+
+    Some(path.to_string_lossy().to_string())
+}
+
+/// Builds an [`AppUsage`] for `pid`, preferring `name` (e.g. a stream's `application.name`)
+/// over the process's own command name.
+pub fn describe_process(proc_root: &Path, pid: u32, name: Option<String>) -> AppUsage {
+    let exe_path = process_path(proc_root, pid).unwrap_or_default();
+
+    let name = name
+        .filter(|name| !name.is_empty())
+        .or_else(|| {
+            let comm = fs::read_to_string(proc_root.join(pid.to_string()).join("comm")).ok()?;
+            Some(comm.trim().to_string())
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| pid.to_string());
+
+    AppUsage { pid, exe_path, name, icon: None }
+}
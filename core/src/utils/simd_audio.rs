@@ -121,8 +121,8 @@ pub unsafe fn simd_apply_spatial_filter(
 
             for i in (0..24).step_by(8) {
                 let mut norm_dist = [0.0f32; 8];
-                for lane in 0..8 {
-                    norm_dist[lane] = ((i + lane) as f32 - 11.5).abs() / 11.5;
+                for (lane, dist) in norm_dist.iter_mut().enumerate() {
+                    *dist = ((i + lane) as f32 - 11.5).abs() / 11.5;
                 }
 
                 let dist_v = _mm256_loadu_ps(norm_dist.as_ptr());
@@ -3,6 +3,10 @@ fn main() {
 
     slint_build::compile("ui/Shell.slint").expect("Failed to compile Slint UI");
 
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("windows") {
+        return;
+    }
+
     let mut res = winres::WindowsResource::new();
     res.set_icon("../assets/lumen.ico");
     res.set("FileDescription", "Lumen");
@@ -1,52 +1,114 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use anyhow::{Result, anyhow};
-use lumen_core::{IslandCore, RuntimeState};
-use slint::{ComponentHandle, Weak};
+use anyhow::Result;
+use lumen_core::{
+    AudioCommand, CommandError, CoreEvent, IslandAction, IslandCore, MediaCommand, RuntimeState,
+    VisualizerMode, start_of_today_ms,
+};
+use slint::{ComponentHandle, SharedString, Weak};
 
 use crate::{
     Assets, IslandContent, IslandData, Shell,
+    platform::register_hotkeys,
     state::{ContentState, IslandState},
-    sync::{media_to_slint, notification_to_slint},
+    sync::{
+        apps_to_slint, levels_to_slint, loudness_to_slint, lyrics_to_slint, media_to_slint,
+        notification_to_slint, output_devices_to_slint, scope_to_path, usage_to_slint,
+        visualizer_to_slint, volume_to_slint,
+    },
+    theme::apply_theme,
 };
 
+/// How long a control stays marked after its action failed.
+const FAILURE_DURATION: Duration = Duration::from_millis(1500);
+
 #[derive(Clone)]
 pub struct Lumen {
     state: Arc<Mutex<IslandState>>,
-    shell: Option<Weak<Shell>>,
+    shells: Shells,
     core: Arc<IslandCore>,
 }
 
+/// Every shell showing the island: the one `main` creates, plus a mirror for each extra
+/// monitor when the island is mirrored. Upgrading only works on the UI thread.
+#[derive(Clone, Default)]
+struct Shells(Arc<Mutex<Vec<Weak<Shell>>>>);
+
+impl Shells {
+    fn add(&self, shell: &Shell) {
+        let mut shells = self.0.lock().unwrap();
+        // mirrors are dropped when their monitor goes away
+        shells.retain(|shell| shell.upgrade().is_some());
+        shells.push(shell.as_weak());
+    }
+
+    fn upgrade(&self) -> Vec<Shell> {
+        self.0.lock().unwrap().iter().filter_map(|shell| shell.upgrade()).collect()
+    }
+}
+
 impl Lumen {
     pub fn new() -> Self {
-        Self {
-            state: Arc::new(Mutex::new(IslandState::new())),
-            shell: None,
-            core: Arc::new(IslandCore::new()),
-        }
+        let core = Arc::new(IslandCore::new());
+
+        let mut state = IslandState::new();
+        state.radius = core.runtime().theme.read().unwrap().metrics.radius.round() as i32;
+
+        Self { state: Arc::new(Mutex::new(state)), shells: Shells::default(), core }
     }
 
-    pub fn start(&mut self, shell: &Shell) -> Result<()> {
+    pub fn start(&self, shell: &Shell) -> Result<()> {
         self.attach_shell(shell);
         self.attach_core();
 
-        self.attach_tick();
-        self.attach_actions();
+        self.attach_tick(shell);
+        self.attach_actions(shell);
+        self.attach_hotkeys();
 
         self.core.start();
 
         self.dispatch();
 
-        if let Some(shell) = self.shell.as_ref().and_then(|s| s.upgrade()) {
-            shell.run()?;
-            return Ok(());
+        shell.run()?;
+        Ok(())
+    }
+
+    /// Opens another shell showing the same island, for mirroring it onto another monitor.
+    pub fn open_mirror(&self) -> Option<Shell> {
+        let shell = match Shell::new() {
+            Ok(shell) => shell,
+            Err(e) => {
+                eprintln!("[Lumen] Failed to open a mirror: {e}");
+                return None;
+            }
+        };
+
+        self.attach_shell(&shell);
+        self.attach_tick(&shell);
+        self.attach_actions(&shell);
+        self.sync_shell();
+
+        if let Err(e) = shell.show() {
+            eprintln!("[Lumen] Failed to show a mirror: {e}");
+            return None;
         }
 
-        Err(anyhow!("No shell attached"))
+        Some(shell)
     }
 
-    fn attach_shell(&mut self, shell: &Shell) {
-        self.shell = Some(shell.as_weak());
+    fn attach_shell(&self, shell: &Shell) {
+        self.shells.add(shell);
+
+        let global = shell.global::<IslandData>();
+        let config = self.core.config();
+
+        global.set_visualizer(visualizer_to_slint(config.visualizer));
+        global.set_loudness_enabled(config.loudness_meter);
+
+        apply_theme(shell, &self.runtime().theme.read().unwrap());
     }
 
     fn attach_core(&self) {
@@ -54,19 +116,72 @@ impl Lumen {
         let lumen = self.clone();
 
         std::thread::spawn(move || {
-            while rx.recv().is_ok() {
+            while let Ok(event) = rx.recv() {
+                match &event {
+                    // what the command showed ahead of time is already rolled back
+                    CoreEvent::CommandFinished { command, result: Err(e), .. }
+                        if *e != CommandError::Cancelled =>
+                    {
+                        Self::report_failure(
+                            lumen.shells.clone(),
+                            &IslandAction::from(*command),
+                            e.clone().into(),
+                        );
+                    }
+                    CoreEvent::AudioCommandFailed { command, error } => {
+                        Self::report_failure(
+                            lumen.shells.clone(),
+                            &IslandAction::from(command.clone()),
+                            anyhow::anyhow!("{error}"),
+                        );
+                    }
+                    _ => {}
+                }
+
                 let lumen = lumen.clone();
                 let _ = slint::invoke_from_event_loop(move || {
+                    match event {
+                        CoreEvent::ThemeChanged => lumen.apply_theme(),
+                        CoreEvent::ControlledVolume(volume) => {
+                            for shell in lumen.shells.upgrade() {
+                                shell
+                                    .global::<IslandData>()
+                                    .set_media_volume(volume_to_slint(&volume));
+                            }
+                        }
+                        CoreEvent::OutputDevices(devices) => {
+                            for shell in lumen.shells.upgrade() {
+                                shell
+                                    .global::<IslandData>()
+                                    .set_output_devices(output_devices_to_slint(&devices));
+                            }
+                        }
+                        _ => {}
+                    }
                     lumen.dispatch();
                 });
             }
         });
     }
 
+    fn apply_theme(&self) {
+        let runtime = self.runtime();
+        let theme = runtime.theme.read().unwrap();
+
+        self.state.lock().unwrap().radius = theme.metrics.radius.round() as i32;
+        for shell in &self.shells.upgrade() {
+            apply_theme(shell, &theme);
+        }
+    }
+
     pub fn state(&self) -> Arc<Mutex<IslandState>> {
         self.state.clone()
     }
 
+    pub fn core(&self) -> Arc<IslandCore> {
+        self.core.clone()
+    }
+
     pub fn runtime(&self) -> Arc<RuntimeState> {
         self.core.runtime()
     }
@@ -81,30 +196,45 @@ impl Lumen {
 
         self.set_mic(runtime.mic.load(std::sync::atomic::Ordering::Relaxed));
         self.set_camera(runtime.camera.load(std::sync::atomic::Ordering::Relaxed));
+        self.set_screen_capture(runtime.screen_capture.load(std::sync::atomic::Ordering::Relaxed));
+        self.set_location(runtime.location.load(std::sync::atomic::Ordering::Relaxed));
+        self.state.lock().unwrap().lyrics = runtime.lyrics.read().unwrap().is_some();
+
+        // the expanded media layout has its own volume control, the OSD would just cover it
+        let expanded = self.state.lock().unwrap().expanded;
+        if runtime.volume_osd.load(std::sync::atomic::Ordering::Relaxed)
+            && !expanded
+            && let Some(volume) = runtime.volume.read().unwrap().as_ref().cloned()
+        {
+            self.set_content(ContentState::Volume(volume));
+            return;
+        }
 
         if let Some(notification) = runtime.notifications.lock().unwrap().front().cloned() {
             self.set_content(ContentState::Notification(notification));
             return;
         }
 
-        if let Some(media) = runtime.media.read().unwrap().as_ref().cloned() {
-            if media.playing {
-                self.set_content(ContentState::Media(media));
-                return;
-            }
+        if let Some(media) = runtime.media.read().unwrap().as_ref().cloned()
+            && media.playing
+        {
+            self.set_content(ContentState::Media(media));
+            return;
         }
 
         self.set_content(ContentState::Idle);
     }
 
     fn sync_shell(&self) {
-        if let Some(shell) = &self.shell.as_ref().and_then(|s| s.upgrade()) {
+        for shell in &self.shells.upgrade() {
             let state = self.state.lock().unwrap();
 
             let content = state.content.clone();
             let bounds = state.bounds();
             let mic = state.mic;
             let camera = state.camera;
+            let screen_capture = state.screen_capture;
+            let location = state.location;
             let expanded = state.expanded;
 
             drop(state);
@@ -112,10 +242,40 @@ impl Lumen {
             let global = shell.global::<IslandData>();
 
             global.set_expanded(expanded);
+            if !expanded {
+                global.set_output_picker(false);
+            } else if let Some(volume) = self.runtime().volume.read().unwrap().as_ref() {
+                // switching outputs is reported by the volume service, not by the action itself
+                let mut media_volume = global.get_media_volume();
+                media_volume.device = SharedString::from(&volume.device);
+                global.set_media_volume(media_volume);
+            }
             global.set_mic(mic);
+            global
+                .set_mic_muted(self.runtime().mic_muted.load(std::sync::atomic::Ordering::Relaxed));
             global.set_camera(camera);
+            global.set_screen_capture(screen_capture);
+            global.set_location(location);
 
             let assets = shell.global::<Assets>();
+            let runtime = self.runtime();
+
+            global.set_mic_apps(apps_to_slint(
+                &runtime.mic_apps.read().unwrap(),
+                &assets.get_fallback_app(),
+            ));
+            global.set_camera_apps(apps_to_slint(
+                &runtime.camera_apps.read().unwrap(),
+                &assets.get_fallback_app(),
+            ));
+            global.set_screen_capture_apps(apps_to_slint(
+                &runtime.screen_capture_apps.read().unwrap(),
+                &assets.get_fallback_app(),
+            ));
+            global.set_location_apps(apps_to_slint(
+                &runtime.location_apps.read().unwrap(),
+                &assets.get_fallback_app(),
+            ));
 
             match &content {
                 ContentState::Idle => {
@@ -127,107 +287,274 @@ impl Lumen {
                         &assets.get_fallback_app(),
                         &assets.get_fallback_media(),
                     ));
+                    // the tick only moves it along while playing, a seek can land while paused
+                    global.set_media_position(m.current_position_ms() as i32);
+                    global.set_lyrics(lyrics_to_slint(
+                        self.runtime().lyrics.read().unwrap().as_deref(),
+                    ));
+                    Self::sync_lyric(&global, &self.runtime(), m.current_position_ms());
                     global.set_content(IslandContent::Media);
                 }
                 ContentState::Notification(n) => {
                     global.set_notification(notification_to_slint(n, &assets.get_fallback_app()));
                     global.set_content(IslandContent::Notification);
                 }
+                ContentState::Volume(v) => {
+                    global.set_volume(volume_to_slint(v));
+                    global.set_content(IslandContent::Volume);
+                }
             }
 
             shell.set_island_width(bounds.width as f32);
             shell.set_island_height(bounds.height as f32);
             shell.set_island_radius(bounds.radius as f32);
             shell.set_island_y(bounds.y as f32);
+
+            global.invoke_layout_changed();
         }
     }
 
-    fn attach_tick(&self) {
+    fn attach_tick(&self, shell: &Shell) {
         let runtime = self.runtime();
+        let visualizer = self.core.config().visualizer;
+        let loudness_enabled = self.core.config().loudness_meter;
 
-        if let Some(shell) = self.shell.as_ref().and_then(|s| s.upgrade()) {
-            let weak = shell.as_weak();
+        let weak = shell.as_weak();
 
-            shell.on_tick(move || {
-                let Some(shell) = weak.upgrade() else {
-                    return;
-                };
+        shell.on_tick(move || {
+            let Some(shell) = weak.upgrade() else {
+                return;
+            };
 
-                let media = { runtime.media.read().unwrap().clone() };
+            let global = shell.global::<IslandData>();
 
-                if let Some(media) = media {
-                    if !media.playing {
-                        return;
-                    }
+            // the meter measures whatever is playing, with or without a media session
+            if loudness_enabled
+                && global.get_expanded()
+                && let Ok(loudness_lock) = runtime.loudness.try_read()
+            {
+                global.set_loudness(loudness_to_slint(&loudness_lock));
+            }
 
-                    let global = shell.global::<IslandData>();
+            let media = { runtime.media.read().unwrap().clone() };
+
+            if let Some(media) = media {
+                if !media.playing {
+                    return;
+                }
 
-                    if let Ok(spectrum_lock) = runtime.spectrum.try_read() {
-                        let spectrum_slice: slint::ModelRc<f32> = (&spectrum_lock[..]).into();
-                        global.set_spectrum(spectrum_slice);
+                match visualizer {
+                    VisualizerMode::Bars | VisualizerMode::MirroredBars => {
+                        if let Ok(spectrum_lock) = runtime.spectrum.try_read() {
+                            let spectrum_slice: slint::ModelRc<f32> = (&spectrum_lock[..]).into();
+                            global.set_spectrum(spectrum_slice);
+                        }
                     }
+                    VisualizerMode::Waveform => {
+                        if let Ok(waveform_lock) = runtime.waveform.try_read() {
+                            let waveform_slice: slint::ModelRc<f32> = (&waveform_lock[..]).into();
+                            global.set_waveform(waveform_slice);
+                        }
+                    }
+                    VisualizerMode::Oscilloscope => {
+                        if let Ok(scope_lock) = runtime.scope.try_read() {
+                            global.set_scope_left(scope_to_path(&scope_lock[..], 0));
+                            global.set_scope_right(scope_to_path(&scope_lock[..], 1));
+                        }
+                    }
+                    VisualizerMode::Vu => {
+                        if let Ok(levels_lock) = runtime.levels.try_read() {
+                            global.set_levels(levels_to_slint(&levels_lock));
+                        }
+                    }
+                }
 
-                    global.set_media_position(media.current_position_ms() as i32);
-                };
-            });
-        }
+                global.set_media_position(media.current_position_ms() as i32);
+                Self::sync_lyric(&global, &runtime, media.current_position_ms());
+            };
+        });
     }
 
-    fn attach_actions(&self) {
-        let Some(shell) = self.shell.as_ref().and_then(|s| s.upgrade()) else {
-            return;
-        };
+    fn attach_actions(&self, shell: &Shell) {
+        let lumen = self.clone();
+
+        shell.global::<IslandData>().on_action(move |name, payload| {
+            match IslandAction::parse(&name, &payload) {
+                Ok(action) => lumen.handle_action(action),
+                Err(e) => eprintln!("[Lumen] Ignored action from the UI: {e:#}"),
+            }
+        });
+    }
 
+    fn attach_hotkeys(&self) {
         let lumen = self.clone();
 
-        shell.global::<IslandData>().on_action(move |action, payload| {
-            lumen.handle_action(&action, &payload);
+        register_hotkeys(self.core.config().hotkeys.bindings(), move |action| {
+            let lumen = lumen.clone();
+            let _ = slint::invoke_from_event_loop(move || lumen.handle_action(action));
         });
     }
 
-    fn handle_action(&self, action: &str, payload: &str) {
+    /// Carries out `action`. Call it on the UI thread; anything slow runs in the background
+    /// and shows on the island when it fails. Media and audio controls go through the core,
+    /// which reports back with events.
+    pub fn handle_action(&self, action: IslandAction) {
         match action {
-            "expand" => {
-                self.set_expanded(payload == "true");
+            IslandAction::Expand => {
+                self.set_expanded(true);
                 self.sync_shell();
+                self.core.send_audio_command(AudioCommand::ReadVolume);
             }
-            "dismiss-notification" => {
-                let Ok(id) = payload.parse::<u64>() else {
-                    return;
-                };
-                self.core.dismiss_notification(id);
+            IslandAction::Collapse => {
+                self.set_expanded(false);
+                self.sync_shell();
+                self.core.dismiss_volume();
             }
-            "toggle-playback" => {
-                let core = self.core.clone();
-                std::thread::spawn(move || {
-                    let _ = futures::executor::block_on(core.toggle_playback());
+            IslandAction::ToggleExpanded => {
+                let state = self.state.lock().unwrap();
+                // the same layouts a click expands
+                let expandable =
+                    matches!(state.content, ContentState::Media(_) | ContentState::Notification(_));
+                let expand = !state.expanded && expandable;
+                drop(state);
+
+                self.handle_action(if expand {
+                    IslandAction::Expand
+                } else {
+                    IslandAction::Collapse
                 });
             }
-            "next" => {
-                let core = self.core.clone();
-                std::thread::spawn(move || {
-                    let _ = futures::executor::block_on(core.next());
-                });
+            IslandAction::DismissNotification(id) => {
+                let id = id
+                    .or_else(|| self.runtime().notifications.lock().unwrap().front().map(|n| n.id));
+                if let Some(id) = id {
+                    self.core.dismiss_notification(id);
+                }
             }
-            "previous" => {
-                let core = self.core.clone();
-                std::thread::spawn(move || {
-                    let _ = futures::executor::block_on(core.previous());
-                });
+            IslandAction::DismissVolume => {
+                self.core.dismiss_volume();
             }
-            "seek" => {
-                let Ok(position) = payload.parse::<u64>() else {
-                    return;
-                };
-                let core = self.core.clone();
-                std::thread::spawn(move || {
-                    let _ = futures::executor::block_on(core.seek(position));
+            IslandAction::ResetLoudness => {
+                self.core.reset_loudness();
+            }
+            IslandAction::ToggleDoNotDisturb => {
+                self.core.set_do_not_disturb(!self.core.do_not_disturb());
+            }
+            IslandAction::TogglePlayback => {
+                self.core.send_media_command(MediaCommand::TogglePlayback);
+            }
+            IslandAction::Next => {
+                self.core.send_media_command(MediaCommand::Next);
+            }
+            IslandAction::Previous => {
+                self.core.send_media_command(MediaCommand::Previous);
+            }
+            IslandAction::Seek(position) => {
+                self.core.send_media_command(MediaCommand::Seek(position));
+            }
+            // what's showing already has the commands still underway in it, so quick clicks
+            // each move one step further
+            IslandAction::ToggleShuffle => {
+                let shuffle = self.runtime().media.read().unwrap().as_ref().and_then(|m| m.shuffle);
+                if let Some(shuffle) = shuffle {
+                    self.core.send_media_command(MediaCommand::SetShuffle(!shuffle));
+                }
+            }
+            IslandAction::CycleRepeat => {
+                let repeat = self.runtime().media.read().unwrap().as_ref().and_then(|m| m.repeat);
+                if let Some(repeat) = repeat {
+                    self.core.send_media_command(MediaCommand::SetRepeat(repeat.next()));
+                }
+            }
+            IslandAction::CycleRate => {
+                let rate =
+                    self.runtime().media.read().unwrap().as_ref().and_then(|m| m.next_rate());
+                if let Some(rate) = rate {
+                    self.core.send_media_command(MediaCommand::SetRate(rate));
+                }
+            }
+            IslandAction::VolumeStep(step) => {
+                self.core.send_audio_command(AudioCommand::StepVolume(step));
+            }
+            IslandAction::ToggleMute => {
+                self.core.send_audio_command(AudioCommand::ToggleMute);
+            }
+            IslandAction::ToggleMicMute => {
+                self.core.send_audio_command(AudioCommand::ToggleMicMute);
+            }
+            IslandAction::ListOutputDevices => {
+                self.core.send_audio_command(AudioCommand::ListOutputDevices);
+            }
+            IslandAction::ListPrivacyReport => {
+                let shells = self.shells.clone();
+                self.run_in_background(action, move |core| {
+                    let report = core.privacy_report(start_of_today_ms())?;
+                    let _ = slint::invoke_from_event_loop(move || {
+                        for shell in shells.upgrade() {
+                            shell.global::<IslandData>().set_privacy_today(usage_to_slint(&report));
+                        }
+                    });
+                    Ok(())
                 });
             }
+            IslandAction::SetOutputDevice(id) => {
+                self.core.send_audio_command(AudioCommand::SetOutputDevice(id));
+            }
+        }
+    }
+
+    /// Runs `work` on a thread of its own, and reports it on the island when it fails.
+    fn run_in_background(
+        &self,
+        action: IslandAction,
+        work: impl FnOnce(&IslandCore) -> Result<()> + Send + 'static,
+    ) {
+        let core = self.core.clone();
+        let shells = self.shells.clone();
+
+        std::thread::spawn(move || {
+            if let Err(e) = work(&core) {
+                Self::report_failure(shells, &action, e);
+            }
+        });
+    }
 
-            _ => {
-                eprintln!("[Lumen] Unknown action: {action} ({payload})");
+    /// Logs why `action` failed, and marks its control on the island for a moment.
+    fn report_failure(shells: Shells, action: &IslandAction, error: anyhow::Error) {
+        eprintln!("[Lumen] `{action}` failed: {error:#}");
+
+        let name = SharedString::from(action.name());
+        let _ = slint::invoke_from_event_loop(move || {
+            for shell in shells.upgrade() {
+                shell.global::<IslandData>().set_failed_action(name.clone());
             }
+
+            slint::Timer::single_shot(FAILURE_DURATION, move || {
+                for shell in shells.upgrade() {
+                    let global = shell.global::<IslandData>();
+                    // another action may have failed since, and gets its own time on screen
+                    if global.get_failed_action() == name {
+                        global.set_failed_action(SharedString::new());
+                    }
+                }
+            });
+        });
+    }
+
+    /// Moves the lyrics on to the line being sung at `position_ms`.
+    fn sync_lyric(global: &IslandData, runtime: &RuntimeState, position_ms: u64) {
+        let Ok(lyrics) = runtime.lyrics.try_read() else {
+            return;
+        };
+        let line = lyrics.as_ref().and_then(|lyrics| {
+            let index = lyrics.line_at(position_ms)?;
+            Some((index, lyrics.lines[index].sung(position_ms)))
+        });
+
+        let (index, sung) = line.map_or((-1, ""), |(index, sung)| (index as i32, sung));
+        global.set_lyric_index(index);
+        if global.get_lyric_sung() != sung {
+            global.set_lyric_sung(SharedString::from(sung));
         }
     }
 
@@ -246,6 +573,16 @@ impl Lumen {
         state.camera = active;
     }
 
+    fn set_screen_capture(&self, active: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.screen_capture = active;
+    }
+
+    fn set_location(&self, active: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.location = active;
+    }
+
     pub fn set_expanded(&self, expanded: bool) {
         let mut state = self.state.lock().unwrap();
         state.expanded = expanded;
@@ -6,7 +6,7 @@ use single_instance::SingleInstance;
 use crate::{
     app::Lumen,
     geometry::{SHELL_HEIGHT, SHELL_WIDTH},
-    platform::{initialize_tray, initialize_window},
+    platform::{initialize_window, install_platform},
 };
 
 mod app;
@@ -14,6 +14,7 @@ mod geometry;
 mod platform;
 mod state;
 mod sync;
+mod theme;
 
 slint::include_modules!();
 
@@ -25,20 +26,30 @@ fn main() -> Result<()> {
         return Err(anyhow!("One instance of Lumen is already running."));
     }
 
-    slint::platform::set_platform(Box::new(i_slint_backend_winit::Backend::new().unwrap()))
-        .unwrap();
+    install_platform(SHELL_WIDTH, SHELL_HEIGHT);
 
-    let mut app = Lumen::new();
+    let app = Lumen::new();
 
     let state = app.state().clone();
+    let core = app.core();
+    let mirrors = app.clone();
     let shell = Shell::new().unwrap();
-    let weak = shell.as_weak();
 
-    let (_tray, _tray_timer) = initialize_tray();
-
-    initialize_window(&shell, SHELL_WIDTH, SHELL_HEIGHT, state.clone(), move || {
-        weak.upgrade().map(|s| s.global::<IslandData>().get_collapsed()).unwrap_or(false)
-    });
+    #[cfg(windows)]
+    let (_tray, _tray_timer) = {
+        let app = app.clone();
+        platform::initialize_tray(app.core(), move |action| app.handle_action(action))
+    };
+
+    initialize_window(
+        &shell,
+        SHELL_WIDTH,
+        SHELL_HEIGHT,
+        app.core().config(),
+        state.clone(),
+        move |visible| core.set_visualizer_visible(visible),
+        move || mirrors.open_mirror(),
+    );
 
     app.start(&shell)?;
 
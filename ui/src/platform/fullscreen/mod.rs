@@ -0,0 +1,13 @@
+#[cfg(target_os = "linux")]
+mod wayland;
+#[cfg(windows)]
+mod win32;
+#[cfg(target_os = "linux")]
+mod x11;
+
+#[cfg(target_os = "linux")]
+pub use wayland::Toplevels;
+#[cfg(windows)]
+pub use win32::is_foreground_fullscreen;
+#[cfg(target_os = "linux")]
+pub use x11::{FullscreenAtoms, active_window, fullscreen_active_window};
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+// `zwlr_foreign_toplevel_handle_v1.state` values
+const ACTIVATED: u32 = 2;
+const FULLSCREEN: u32 = 3;
+
+#[derive(Default, Clone)]
+struct Toplevel {
+    /// Focused and fullscreen at once.
+    fullscreen: bool,
+    /// Protocol ids of the outputs it's on.
+    outputs: Vec<u32>,
+}
+
+/// Toplevels as wlr-foreign-toplevel-management reports them, keyed by protocol id. A
+/// handle's state arrives in pieces and only applies on its `done` event.
+#[derive(Default)]
+pub struct Toplevels {
+    pending: HashMap<u32, Toplevel>,
+    current: HashMap<u32, Toplevel>,
+}
+
+impl Toplevels {
+    /// `state` is the protocol's array of native-endian `u32` states.
+    pub fn set_state(&mut self, id: u32, state: &[u8]) {
+        let states: Vec<u32> = state
+            .chunks_exact(4)
+            .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+
+        self.pending(id).fullscreen = states.contains(&ACTIVATED) && states.contains(&FULLSCREEN);
+    }
+
+    pub fn output_enter(&mut self, id: u32, output: u32) {
+        let outputs = &mut self.pending(id).outputs;
+        if !outputs.contains(&output) {
+            outputs.push(output);
+        }
+    }
+
+    pub fn output_leave(&mut self, id: u32, output: u32) {
+        self.pending(id).outputs.retain(|&o| o != output);
+    }
+
+    pub fn done(&mut self, id: u32) {
+        if let Some(toplevel) = self.pending.remove(&id) {
+            self.current.insert(id, toplevel);
+        }
+    }
+
+    pub fn closed(&mut self, id: u32) {
+        self.pending.remove(&id);
+        self.current.remove(&id);
+    }
+
+    /// Whether the focused toplevel is fullscreen on one of `outputs`, or anywhere when
+    /// `outputs` is empty.
+    pub fn fullscreen_active(&self, outputs: &[u32]) -> bool {
+        self.current.values().any(|toplevel| {
+            toplevel.fullscreen
+                && (outputs.is_empty()
+                    || toplevel.outputs.is_empty()
+                    || toplevel.outputs.iter().any(|output| outputs.contains(output)))
+        })
+    }
+
+    fn pending(&mut self, id: u32) -> &mut Toplevel {
+        self.pending.entry(id).or_insert_with(|| self.current.get(&id).cloned().unwrap_or_default())
+    }
+}
@@ -133,7 +133,11 @@ pub fn is_foreground_fullscreen(app_hwnd: HWND) -> bool {
             return false;
         }
 
+        // only a fullscreen app on the island's own monitor covers it
         let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        if monitor != MonitorFromWindow(app_hwnd, MONITOR_DEFAULTTONEAREST) {
+            return false;
+        }
 
         let mut mi =
             MONITORINFO { cbSize: std::mem::size_of::<MONITORINFO>() as u32, ..Default::default() };
@@ -0,0 +1,48 @@
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{AtomEnum, ConnectionExt as _, Window},
+};
+
+x11rb::atom_manager! {
+    pub FullscreenAtoms: FullscreenAtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_WM_STATE,
+        _NET_WM_STATE_FULLSCREEN,
+    }
+}
+
+/// The window the EWMH window manager reports as active, unless it's `own`.
+pub fn active_window(
+    conn: &impl Connection,
+    root: Window,
+    own: Window,
+    atoms: &FullscreenAtoms,
+) -> Option<Window> {
+    property32(conn, root, atoms._NET_ACTIVE_WINDOW, AtomEnum::WINDOW)
+        .and_then(|windows| windows.first().copied())
+        .filter(|&active| active != x11rb::NONE && active != own)
+}
+
+/// The active window, if it's fullscreen. `own` never counts, so the island can't hide
+/// itself.
+pub fn fullscreen_active_window(
+    conn: &impl Connection,
+    root: Window,
+    own: Window,
+    atoms: &FullscreenAtoms,
+) -> Option<Window> {
+    active_window(conn, root, own, atoms).filter(|&active| {
+        property32(conn, active, atoms._NET_WM_STATE, AtomEnum::ATOM)
+            .is_some_and(|states| states.contains(&atoms._NET_WM_STATE_FULLSCREEN))
+    })
+}
+
+fn property32(
+    conn: &impl Connection,
+    window: Window,
+    property: u32,
+    kind: AtomEnum,
+) -> Option<Vec<u32>> {
+    let reply = conn.get_property(false, window, property, kind, 0, 64).ok()?.reply().ok()?;
+    Some(reply.value32()?.collect())
+}
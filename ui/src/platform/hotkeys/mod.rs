@@ -0,0 +1,29 @@
+use lumen_core::{Hotkey, IslandAction};
+
+#[cfg(windows)]
+mod win32;
+#[cfg(target_os = "linux")]
+mod x11;
+
+/// Registers `bindings` system-wide and calls `on_hotkey` from a background thread whenever
+/// one is pressed. Combinations another app already holds are skipped.
+pub fn register_hotkeys(
+    bindings: Vec<(Hotkey, IslandAction)>,
+    on_hotkey: impl Fn(IslandAction) + Send + 'static,
+) {
+    if bindings.is_empty() {
+        return;
+    }
+
+    #[cfg(windows)]
+    win32::register(bindings, on_hotkey);
+
+    #[cfg(target_os = "linux")]
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        // only the compositor sees every key press, and there's no common protocol to ask
+        // it for a binding
+        eprintln!("[Hotkeys] Global hotkeys aren't supported on Wayland");
+    } else {
+        x11::register(bindings, on_hotkey);
+    }
+}
@@ -0,0 +1,84 @@
+use lumen_core::{Hotkey, IslandAction, Key, Modifiers};
+use windows::Win32::UI::{
+    Input::KeyboardAndMouse::{
+        HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN, RegisterHotKey,
+        VIRTUAL_KEY, VK_BACK, VK_DELETE, VK_DOWN, VK_END, VK_ESCAPE, VK_F1, VK_HOME, VK_INSERT,
+        VK_LEFT, VK_MEDIA_NEXT_TRACK, VK_MEDIA_PLAY_PAUSE, VK_MEDIA_PREV_TRACK, VK_NEXT, VK_PRIOR,
+        VK_RETURN, VK_RIGHT, VK_SPACE, VK_TAB, VK_UP,
+    },
+    WindowsAndMessaging::{GetMessageW, MSG, WM_HOTKEY},
+};
+
+/// Hotkeys belong to the thread that registers them, so they get one of their own to post
+/// `WM_HOTKEY` to.
+pub fn register(
+    bindings: Vec<(Hotkey, IslandAction)>,
+    on_hotkey: impl Fn(IslandAction) + Send + 'static,
+) {
+    std::thread::spawn(move || {
+        // ids start at 1, so the binding is at id - 1
+        for (id, (hotkey, _)) in bindings.iter().enumerate() {
+            let modifiers = modifiers(hotkey.modifiers) | MOD_NOREPEAT;
+            let key = virtual_key(hotkey.key).0 as u32;
+
+            if let Err(e) = unsafe { RegisterHotKey(None, id as i32 + 1, modifiers, key) } {
+                eprintln!("[Hotkeys] Couldn't register {hotkey}, another app may hold it: {e}");
+            }
+        }
+
+        let mut msg = MSG::default();
+        while unsafe { GetMessageW(&mut msg, None, 0, 0) }.as_bool() {
+            if msg.message != WM_HOTKEY {
+                continue;
+            }
+
+            if let Some((_, action)) = msg.wParam.0.checked_sub(1).and_then(|idx| bindings.get(idx))
+            {
+                on_hotkey(action.clone());
+            }
+        }
+    });
+}
+
+fn modifiers(modifiers: Modifiers) -> HOT_KEY_MODIFIERS {
+    let mut flags = HOT_KEY_MODIFIERS(0);
+
+    for (held, flag) in [
+        (modifiers.ctrl, MOD_CONTROL),
+        (modifiers.alt, MOD_ALT),
+        (modifiers.shift, MOD_SHIFT),
+        (modifiers.logo, MOD_WIN),
+    ] {
+        if held {
+            flags |= flag;
+        }
+    }
+    flags
+}
+
+fn virtual_key(key: Key) -> VIRTUAL_KEY {
+    match key {
+        // letters and digits share their ASCII codes
+        Key::Letter(c) => VIRTUAL_KEY(c as u16),
+        Key::Digit(d) => VIRTUAL_KEY(b'0' as u16 + d as u16),
+        Key::F(n) => VIRTUAL_KEY(VK_F1.0 + n as u16 - 1),
+        Key::Space => VK_SPACE,
+        Key::Enter => VK_RETURN,
+        Key::Escape => VK_ESCAPE,
+        Key::Tab => VK_TAB,
+        Key::Backspace => VK_BACK,
+        Key::Insert => VK_INSERT,
+        Key::Delete => VK_DELETE,
+        Key::Home => VK_HOME,
+        Key::End => VK_END,
+        Key::PageUp => VK_PRIOR,
+        Key::PageDown => VK_NEXT,
+        Key::Left => VK_LEFT,
+        Key::Right => VK_RIGHT,
+        Key::Up => VK_UP,
+        Key::Down => VK_DOWN,
+        Key::PlayPause => VK_MEDIA_PLAY_PAUSE,
+        Key::NextTrack => VK_MEDIA_NEXT_TRACK,
+        Key::PreviousTrack => VK_MEDIA_PREV_TRACK,
+    }
+}
@@ -0,0 +1,213 @@
+use anyhow::Result;
+use lumen_core::{Hotkey, IslandAction, Key, Modifiers};
+use x11rb::{
+    connection::Connection,
+    errors::ReplyError,
+    protocol::{
+        ErrorKind, Event,
+        xproto::{ConnectionExt as _, GrabMode, KeyPressEvent, Keycode, ModMask, Window},
+    },
+    rust_connection::RustConnection,
+};
+
+const XK_NUM_LOCK: u32 = 0xff7f;
+const XK_ALT_L: u32 = 0xffe9;
+const XK_META_L: u32 = 0xffe7;
+const XK_SUPER_L: u32 = 0xffeb;
+
+/// Grabs the hotkeys on a connection of its own and waits for them on a background thread.
+pub fn register(
+    bindings: Vec<(Hotkey, IslandAction)>,
+    on_hotkey: impl Fn(IslandAction) + Send + 'static,
+) {
+    std::thread::spawn(move || {
+        if let Err(e) = run(&bindings, on_hotkey) {
+            eprintln!("[Hotkeys] Stopped listening for hotkeys: {e}");
+        }
+    });
+}
+
+fn run(bindings: &[(Hotkey, IslandAction)], on_hotkey: impl Fn(IslandAction)) -> Result<()> {
+    let (conn, screen) = RustConnection::connect(None)?;
+    let root = conn.setup().roots[screen].root;
+
+    let grabs = Grabs::new(&conn, root, bindings)?;
+    for hotkey in &grabs.taken {
+        eprintln!("[Hotkeys] Couldn't register {hotkey}, another client grabbed it first");
+    }
+
+    loop {
+        if let Event::KeyPress(event) = conn.wait_for_event()?
+            && let Some(action) = grabs.action(&event)
+        {
+            on_hotkey(action);
+        }
+    }
+}
+
+/// Hotkeys grabbed on the root window, each once for every combination of Caps Lock and
+/// Num Lock so neither being on gets in the way.
+pub struct Grabs {
+    grabs: Vec<Grab>,
+    /// The hotkeys another client had already grabbed.
+    pub taken: Vec<Hotkey>,
+    /// Lock modifiers a key press may carry without it mattering.
+    locks: u16,
+}
+
+struct Grab {
+    keycode: Keycode,
+    modifiers: u16,
+    action: IslandAction,
+}
+
+impl Grabs {
+    pub fn new(
+        conn: &impl Connection,
+        root: Window,
+        bindings: &[(Hotkey, IslandAction)],
+    ) -> Result<Self> {
+        let keymap = Keymap::new(conn)?;
+        let num_lock = keymap.modifier_of(XK_NUM_LOCK).unwrap_or(ModMask::M2.into());
+        let caps_lock = u16::from(ModMask::LOCK);
+        let locks = [0, caps_lock, num_lock, caps_lock | num_lock];
+
+        let mut grabs = Vec::new();
+        let mut taken = Vec::new();
+
+        for (hotkey, action) in bindings {
+            let Some(keycode) = keymap.keycode(keysym(hotkey.key)) else {
+                eprintln!("[Hotkeys] No key on this keyboard for {hotkey}");
+                continue;
+            };
+            let modifiers = keymap.mask(hotkey.modifiers);
+
+            let mut granted = Vec::new();
+            for lock in locks {
+                let mask = ModMask::from(modifiers | lock);
+                let cookie =
+                    conn.grab_key(false, root, mask, keycode, GrabMode::ASYNC, GrabMode::ASYNC)?;
+
+                match cookie.check() {
+                    Ok(()) => granted.push(mask),
+                    Err(ReplyError::X11Error(e)) if e.error_kind == ErrorKind::Access => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+
+            // half a grab would only fire with some locks on, so let go of the rest
+            if granted.len() < locks.len() {
+                for mask in granted {
+                    conn.ungrab_key(keycode, root, mask)?;
+                }
+                taken.push(*hotkey);
+                continue;
+            }
+
+            grabs.push(Grab { keycode, modifiers, action: action.clone() });
+        }
+
+        conn.flush()?;
+
+        Ok(Self { grabs, taken, locks: caps_lock | num_lock })
+    }
+
+    pub fn action(&self, event: &KeyPressEvent) -> Option<IslandAction> {
+        // the upper bits are mouse buttons held down at the time
+        let modifiers = u16::from(event.state) & 0xff & !self.locks;
+
+        self.grabs
+            .iter()
+            .find(|grab| grab.keycode == event.detail && grab.modifiers == modifiers)
+            .map(|grab| grab.action.clone())
+    }
+}
+
+/// Which keys the keyboard has and which of them act as modifiers.
+pub struct Keymap {
+    min_keycode: Keycode,
+    keysyms_per_keycode: usize,
+    keysyms: Vec<u32>,
+    keycodes_per_modifier: usize,
+    modifiers: Vec<Keycode>,
+}
+
+impl Keymap {
+    pub fn new(conn: &impl Connection) -> Result<Self> {
+        let setup = conn.setup();
+        let (min_keycode, max_keycode) = (setup.min_keycode, setup.max_keycode);
+
+        let mapping =
+            conn.get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)?.reply()?;
+        let modifiers = conn.get_modifier_mapping()?.reply()?;
+
+        Ok(Self {
+            min_keycode,
+            keysyms_per_keycode: mapping.keysyms_per_keycode.max(1) as usize,
+            keysyms: mapping.keysyms,
+            keycodes_per_modifier: modifiers.keycodes_per_modifier().max(1) as usize,
+            modifiers: modifiers.keycodes,
+        })
+    }
+
+    /// The first key that types `keysym`, in any of its shift levels.
+    pub fn keycode(&self, keysym: u32) -> Option<Keycode> {
+        let idx = self.keysyms.iter().position(|&sym| sym == keysym)?;
+
+        Some(self.min_keycode + (idx / self.keysyms_per_keycode) as Keycode)
+    }
+
+    /// The modifier bit the key typing `keysym` sets, when it's a modifier at all.
+    fn modifier_of(&self, keysym: u32) -> Option<u16> {
+        let keycode = self.keycode(keysym)?;
+        let idx = self.modifiers.iter().position(|&modifier| modifier == keycode)?;
+
+        Some(1 << (idx / self.keycodes_per_modifier))
+    }
+
+    /// Alt and Super sit on whichever ModN the keymap puts them, usually Mod1 and Mod4.
+    fn mask(&self, modifiers: Modifiers) -> u16 {
+        let alt = self
+            .modifier_of(XK_ALT_L)
+            .or_else(|| self.modifier_of(XK_META_L))
+            .unwrap_or(ModMask::M1.into());
+        let logo = self.modifier_of(XK_SUPER_L).unwrap_or(ModMask::M4.into());
+
+        [
+            (modifiers.ctrl, ModMask::CONTROL.into()),
+            (modifiers.alt, alt),
+            (modifiers.shift, ModMask::SHIFT.into()),
+            (modifiers.logo, logo),
+        ]
+        .into_iter()
+        .filter(|(held, _)| *held)
+        .fold(0, |mask, (_, bit)| mask | bit)
+    }
+}
+
+pub fn keysym(key: Key) -> u32 {
+    match key {
+        // Latin-1 keysyms are the lowercase characters themselves
+        Key::Letter(c) => c.to_ascii_lowercase() as u32,
+        Key::Digit(d) => b'0' as u32 + d as u32,
+        Key::F(n) => 0xffbe + n as u32 - 1,
+        Key::Space => 0x0020,
+        Key::Enter => 0xff0d,
+        Key::Escape => 0xff1b,
+        Key::Tab => 0xff09,
+        Key::Backspace => 0xff08,
+        Key::Insert => 0xff63,
+        Key::Delete => 0xffff,
+        Key::Home => 0xff50,
+        Key::End => 0xff57,
+        Key::PageUp => 0xff55,
+        Key::PageDown => 0xff56,
+        Key::Left => 0xff51,
+        Key::Up => 0xff52,
+        Key::Right => 0xff53,
+        Key::Down => 0xff54,
+        Key::PlayPause => 0x1008ff14,
+        Key::NextTrack => 0x1008ff17,
+        Key::PreviousTrack => 0x1008ff16,
+    }
+}
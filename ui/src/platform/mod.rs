@@ -1,10 +1,102 @@
+#[cfg(windows)]
 mod clickthrough;
-mod cursor;
 mod fullscreen;
+mod hotkeys;
+mod monitor;
+mod region;
+#[cfg(windows)]
 mod toast;
+#[cfg(windows)]
 mod tray;
+#[cfg(windows)]
 mod updater;
+#[cfg(target_os = "linux")]
+mod wayland;
+#[cfg(windows)]
+mod win32;
 mod window;
+#[cfg(target_os = "linux")]
+mod x11;
 
+pub use hotkeys::register_hotkeys;
+#[cfg(windows)]
 pub use tray::initialize_tray;
 pub use window::initialize_window;
+
+/// The native window the island is drawn in, kept borderless, unfocusable and above
+/// everything else by each windowing system's own means.
+pub trait Window {
+    /// Limits pointer input to `rects`, in window-local physical pixels; everywhere else
+    /// clicks go through to whatever is below. Empty means no input at all.
+    fn set_input_region(&self, rects: &[region::Rect]);
+    /// How far past its input region the island draws its shadow, in physical pixels, for
+    /// windowing systems where the input region clips drawing too.
+    fn set_overhang(&self, _overhang: i32) {}
+    fn set_visible(&self, visible: bool);
+    /// Puts the window back on top of other always-on-top windows.
+    fn raise(&self);
+    /// Whether a fullscreen app is focused on the monitor this window is on.
+    fn fullscreen_app_active(&self) -> bool;
+    /// Moves the window to `position` on `monitor`, or wherever the windowing system prefers
+    /// when there's none.
+    fn place(&self, monitor: Option<&monitor::Monitor>, position: &monitor::Position);
+
+    /// What of `monitor` isn't reserved for panels and taskbars.
+    fn work_area(&self, _monitor: &monitor::Monitor) -> Option<region::Rect> {
+        None
+    }
+
+    /// Whether the monitors, or the room panels leave on them, may have changed since the
+    /// last call. Backends that aren't told say they may have, every time.
+    fn displays_changed(&self) -> bool {
+        true
+    }
+
+    /// The connected monitors, for backends winit doesn't know about.
+    fn monitors(&self) -> Option<Vec<monitor::Monitor>> {
+        None
+    }
+
+    /// The monitor the window is on, when it was left to the windowing system to pick.
+    fn current_monitor(&self) -> Option<monitor::Monitor> {
+        None
+    }
+
+    /// The pointer, in desktop coordinates.
+    fn cursor_position(&self) -> Option<(i32, i32)> {
+        None
+    }
+
+    /// A point inside the focused window, in desktop coordinates.
+    fn focused_position(&self) -> Option<(i32, i32)> {
+        None
+    }
+
+    /// Takes the keyboard from whatever app has it, or gives it back. The window is never
+    /// focused otherwise.
+    fn set_keyboard_focus(&self, _focused: bool) {}
+
+    /// Whether the windowing system lets us open another overlay to mirror this one.
+    fn can_mirror(&self) -> bool {
+        true
+    }
+}
+
+/// Uses our own layer-shell platform on wlroots compositors and winit everywhere else.
+pub fn install_platform(width: i32, height: i32) {
+    #[cfg(target_os = "linux")]
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        match wayland::LayerShellPlatform::new(width as u32, height as u32) {
+            Ok(platform) => {
+                slint::platform::set_platform(Box::new(platform)).unwrap();
+                return;
+            }
+            Err(e) => eprintln!("[Window] Layer shell unavailable, falling back to winit: {e}"),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = (width, height);
+
+    slint::platform::set_platform(Box::new(i_slint_backend_winit::Backend::new().unwrap()))
+        .unwrap();
+}
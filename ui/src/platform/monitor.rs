@@ -0,0 +1,201 @@
+use lumen_core::{Anchor, MonitorPlacement, Offset};
+
+use crate::platform::{Window, region::Rect};
+
+/// A display, in physical pixels of the desktop's coordinate space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub primary: bool,
+}
+
+impl Monitor {
+    pub fn rect(&self) -> Rect {
+        Rect { x: self.x, y: self.y, width: self.width as i32, height: self.height as i32 }
+    }
+
+    pub fn contains(&self, (x, y): (i32, i32)) -> bool {
+        x >= self.x
+            && y >= self.y
+            && x < self.x + self.width as i32
+            && y < self.y + self.height as i32
+    }
+}
+
+/// The monitors the island goes on for `placement`, falling back to the primary one when
+/// the cursor, focused window or named monitor can't be found. Empty leaves it to the
+/// windowing system.
+pub fn select<'a>(
+    placement: &MonitorPlacement,
+    monitors: &'a [Monitor],
+    window: &dyn Window,
+) -> Vec<&'a Monitor> {
+    let containing = |point: Option<(i32, i32)>| {
+        point.and_then(|point| monitors.iter().find(|monitor| monitor.contains(point)))
+    };
+
+    let chosen = match placement {
+        MonitorPlacement::Primary => None,
+        MonitorPlacement::Cursor => containing(window.cursor_position()),
+        MonitorPlacement::Focused => containing(window.focused_position()),
+        MonitorPlacement::Named(name) => {
+            monitors.iter().find(|monitor| monitor.name.eq_ignore_ascii_case(name))
+        }
+        MonitorPlacement::Mirrored => return monitors.iter().collect(),
+    };
+
+    chosen.or_else(|| monitors.iter().find(|monitor| monitor.primary)).into_iter().collect()
+}
+
+/// Where on its monitor the overlay goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub anchor: Anchor,
+    pub offset: Offset,
+    pub cover_panels: bool,
+    /// Physical pixels the user has dragged the island away from its anchor so far.
+    pub drag: (i32, i32),
+}
+
+impl Position {
+    /// The top-left corner of a window `size` physical pixels big inside `area`.
+    pub fn origin(&self, area: Rect, (width, height): (i32, i32), scale_factor: f64) -> (i32, i32) {
+        let offset_x = (self.offset.x as f64 * scale_factor).round() as i32;
+        let offset_y = (self.offset.y as f64 * scale_factor).round() as i32;
+
+        let x = match self.anchor {
+            Anchor::TopLeft | Anchor::BottomLeft => area.x + offset_x,
+            Anchor::TopCenter | Anchor::BottomCenter => {
+                area.x + (area.width - width) / 2 + offset_x
+            }
+            Anchor::TopRight | Anchor::BottomRight => area.x + area.width - width - offset_x,
+        };
+        let y = if self.anchor.is_bottom() {
+            area.y + area.height - height - offset_y
+        } else {
+            area.y + offset_y
+        };
+
+        (x + self.drag.0, y + self.drag.1)
+    }
+
+    /// The anchor whose spot is closest to where the window has been dragged.
+    pub fn nearest_anchor(&self, area: Rect, size: (i32, i32), scale_factor: f64) -> Anchor {
+        let (x, y) = self.origin(area, size, scale_factor);
+
+        Anchor::ALL
+            .into_iter()
+            .min_by_key(|&anchor| {
+                let (ax, ay) =
+                    Position { anchor, drag: (0, 0), ..*self }.origin(area, size, scale_factor);
+                (ax as i64 - x as i64).pow(2) + (ay as i64 - y as i64).pow(2)
+            })
+            .unwrap_or(self.anchor)
+    }
+}
+
+/// The part of `monitor` the island may go in: all of it, or what panels and taskbars
+/// leave free.
+pub fn usable_area(monitor: &Monitor, window: &dyn Window, cover_panels: bool) -> Rect {
+    if cover_panels {
+        return monitor.rect();
+    }
+
+    window.work_area(monitor).unwrap_or_else(|| monitor.rect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A window on a desktop with a 32px panel at the top and a 48px dock at the bottom.
+    struct Paneled {
+        panels: bool,
+    }
+
+    impl Window for Paneled {
+        fn set_input_region(&self, _rects: &[Rect]) {}
+        fn set_visible(&self, _visible: bool) {}
+        fn raise(&self) {}
+        fn fullscreen_app_active(&self) -> bool {
+            false
+        }
+        fn place(&self, _monitor: Option<&Monitor>, _position: &Position) {}
+
+        fn work_area(&self, monitor: &Monitor) -> Option<Rect> {
+            let rect = monitor.rect();
+            self.panels.then_some(Rect { y: rect.y + 32, height: rect.height - 80, ..rect })
+        }
+    }
+
+    fn monitor() -> Monitor {
+        Monitor {
+            name: "DP-1".into(),
+            x: 1920,
+            y: 0,
+            width: 1920,
+            height: 1080,
+            scale_factor: 1.0,
+            primary: false,
+        }
+    }
+
+    fn position(anchor: Anchor) -> Position {
+        Position { anchor, offset: Offset { x: 10, y: 20 }, cover_panels: false, drag: (0, 0) }
+    }
+
+    const AREA: Rect = Rect { x: 1920, y: 0, width: 2560, height: 1440 };
+    const SIZE: (i32, i32) = (400, 100);
+
+    #[test]
+    fn each_anchor_puts_the_window_in_its_spot() {
+        // offsets are logical pixels, so 15 and 30 at 1.5x, and inward from the edges
+        let expected = [
+            (Anchor::TopLeft, (1935, 30)),
+            (Anchor::TopCenter, (3015, 30)),
+            (Anchor::TopRight, (4065, 30)),
+            (Anchor::BottomLeft, (1935, 1310)),
+            (Anchor::BottomCenter, (3015, 1310)),
+            (Anchor::BottomRight, (4065, 1310)),
+        ];
+
+        for (anchor, origin) in expected {
+            assert_eq!(position(anchor).origin(AREA, SIZE, 1.5), origin, "{anchor:?}");
+        }
+
+        let dragged = Position { drag: (-100, 40), ..position(Anchor::TopCenter) };
+        assert_eq!(dragged.origin(AREA, SIZE, 1.5), (2915, 70));
+    }
+
+    #[test]
+    fn a_drag_snaps_to_the_nearest_anchor() {
+        let snapped =
+            |drag| Position { drag, ..position(Anchor::TopCenter) }.nearest_anchor(AREA, SIZE, 1.5);
+
+        assert_eq!(snapped((0, 0)), Anchor::TopCenter);
+        assert_eq!(snapped((300, 200)), Anchor::TopCenter);
+        assert_eq!(snapped((-900, 100)), Anchor::TopLeft);
+        assert_eq!(snapped((1000, 1250)), Anchor::BottomRight);
+        assert_eq!(snapped((50, 900)), Anchor::BottomCenter);
+    }
+
+    #[test]
+    fn panels_are_kept_clear_unless_covering_them() {
+        let monitor = monitor();
+        let window = Paneled { panels: true };
+
+        let area = usable_area(&monitor, &window, false);
+        assert_eq!(area, Rect { x: 1920, y: 32, width: 1920, height: 1000 });
+        assert_eq!(position(Anchor::TopCenter).origin(area, SIZE, 1.0), (2690, 52));
+        assert_eq!(position(Anchor::BottomCenter).origin(area, SIZE, 1.0), (2690, 912));
+
+        assert_eq!(usable_area(&monitor, &window, true), monitor.rect());
+        // backends that can't tell where the panels are get the whole monitor
+        assert_eq!(usable_area(&monitor, &Paneled { panels: false }, false), monitor.rect());
+    }
+}
@@ -0,0 +1,36 @@
+use crate::geometry::PhysicalBounds;
+
+/// A rectangle in window-local physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Covers the pill (square top corners, rounded bottom ones) with its top-left corner at
+/// `(left, top)`: one rectangle for the straight part, then one per row of the corners.
+pub fn pill_region(left: i32, top: i32, bounds: PhysicalBounds) -> Vec<Rect> {
+    let PhysicalBounds { width, height, radius } = bounds;
+    let radius = radius.clamp(0, width.min(height) / 2);
+
+    let mut rects = vec![Rect { x: left, y: top, width, height: height - radius }];
+
+    for row in 0..radius {
+        let dy = row as f64 + 0.5;
+        let inset = (radius as f64 - (radius as f64 * radius as f64 - dy * dy).sqrt()).round();
+        let inset = inset as i32;
+
+        rects.push(Rect {
+            x: left + inset,
+            y: top + height - radius + row,
+            width: width - 2 * inset,
+            height: 1,
+        });
+    }
+
+    // a collapsed island hangs mostly above the window
+    rects.retain(|rect| rect.y + rect.height > 0 && rect.width > 0 && rect.height > 0);
+    rects
+}
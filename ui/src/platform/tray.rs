@@ -1,22 +1,29 @@
 use std::{
-    sync::{Arc, Mutex},
-    time::Duration,
+    sync::{Arc, Mutex, mpsc},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use lumen_core::{IslandAction, IslandCore, UsageSummary, start_of_today_ms};
 use tray_icon::{
     TrayIcon, TrayIconBuilder,
-    menu::{IconMenuItem, Menu, MenuItem, PredefinedMenuItem},
+    menu::{CheckMenuItem, IconMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
 };
 use windows::{
     Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW},
     core::PCSTR,
 };
 
-use crate::platform::updater::{
-    UpdateState, download_and_apply_update, force_check_for_update, start_update_check,
+use crate::{
+    platform::updater::{
+        UpdateState, download_and_apply_update, force_check_for_update, start_update_check,
+    },
+    sync::format_usage_total,
 };
 
-pub fn initialize_tray() -> (TrayIcon, slint::Timer) {
+pub fn initialize_tray(
+    core: Arc<IslandCore>,
+    on_action: impl Fn(IslandAction) + 'static,
+) -> (TrayIcon, slint::Timer) {
     if let Ok(uxtheme) = unsafe { LoadLibraryW(windows_core::w!("uxtheme.dll")) } {
         unsafe {
             if let Some(proc_addr) = GetProcAddress(uxtheme, PCSTR(135 as *const u8)) {
@@ -30,15 +37,20 @@ pub fn initialize_tray() -> (TrayIcon, slint::Timer) {
     let (tray_img, menu_img) = load_icon();
 
     let header = IconMenuItem::new("Lumen", true, Some(menu_img), None);
+    let do_not_disturb = CheckMenuItem::new("Do Not Disturb", true, false, None);
+    let privacy = Submenu::new("Privacy Today", true);
     let check_updates = MenuItem::new("Check for Updates", true, None);
     let separator = PredefinedMenuItem::separator();
     let quit = MenuItem::new("Quit Lumen", true, None);
 
+    let do_not_disturb_id = do_not_disturb.id().clone();
     let check_updates_id = check_updates.id().clone();
     let quit_id = quit.id().clone();
 
     menu.append(&header).unwrap();
     menu.append(&separator).unwrap();
+    menu.append(&do_not_disturb).unwrap();
+    menu.append(&privacy).unwrap();
     menu.append(&check_updates).unwrap();
     menu.append(&separator).unwrap();
     menu.append(&quit).unwrap();
@@ -57,6 +69,12 @@ pub fn initialize_tray() -> (TrayIcon, slint::Timer) {
 
     let mut last_rendered_state = None;
 
+    let mut privacy_items = Vec::new();
+    let mut last_rendered_privacy = None;
+    // the report reads the log file, so it's put together on a thread and rendered here
+    let (report_tx, report_rx) = mpsc::channel();
+    let mut report_pending = false;
+
     let poll_timer = slint::Timer::default();
     poll_timer.start(slint::TimerMode::Repeated, Duration::from_millis(100), move || {
         let current_state = {
@@ -96,9 +114,38 @@ pub fn initialize_tray() -> (TrayIcon, slint::Timer) {
             last_rendered_state = Some(current_state.clone());
         }
 
+        // a hotkey may have toggled it as well
+        if do_not_disturb.is_checked() != core.do_not_disturb() {
+            do_not_disturb.set_checked(core.do_not_disturb());
+        }
+
+        // totals of apps still in use grow, so re-render every minute as well
+        let minute = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() / 60);
+        let privacy_key = (core.privacy_revision(), minute);
+        if Some(privacy_key) != last_rendered_privacy && !report_pending {
+            let core = core.clone();
+            let report_tx = report_tx.clone();
+            std::thread::spawn(move || {
+                let report = core.privacy_report(start_of_today_ms()).unwrap_or_else(|e| {
+                    eprintln!("[Tray] Failed to read privacy log: {e}");
+                    Vec::new()
+                });
+                let _ = report_tx.send(report);
+            });
+
+            report_pending = true;
+            last_rendered_privacy = Some(privacy_key);
+        }
+        if let Ok(report) = report_rx.try_recv() {
+            render_privacy_report(&privacy, &mut privacy_items, &report);
+            report_pending = false;
+        }
+
         if let Ok(event) = tray_icon::menu::MenuEvent::receiver().try_recv() {
             if event.id == quit_id {
                 let _ = slint::quit_event_loop();
+            } else if event.id == do_not_disturb_id {
+                on_action(IslandAction::ToggleDoNotDisturb);
             } else if event.id == check_updates_id {
                 match current_state {
                     UpdateState::Idle | UpdateState::Failed => {
@@ -148,6 +195,31 @@ pub fn initialize_tray() -> (TrayIcon, slint::Timer) {
     (tray, poll_timer)
 }
 
+fn render_privacy_report(submenu: &Submenu, items: &mut Vec<MenuItem>, report: &[UsageSummary]) {
+    for item in items.drain(..) {
+        let _ = submenu.remove(&item);
+    }
+
+    let lines = if report.is_empty() {
+        vec!["Nothing used a sensor today".to_string()]
+    } else {
+        report.iter().map(format_usage).collect()
+    };
+
+    for line in lines {
+        let item = MenuItem::new(line, false, None);
+        let _ = submenu.append(&item);
+        items.push(item);
+    }
+}
+
+fn format_usage(usage: &UsageSummary) -> String {
+    let total = format_usage_total(usage.total);
+    let in_use = if usage.active { ", in use" } else { "" };
+
+    format!("{}: {} ({total}{in_use})", usage.sensor.label(), usage.app)
+}
+
 fn load_icon() -> (tray_icon::Icon, tray_icon::menu::Icon) {
     let bytes = include_bytes!("../../../assets/lumen.ico");
 
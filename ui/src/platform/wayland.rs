@@ -0,0 +1,1124 @@
+use std::{
+    cell::{Cell, RefCell},
+    fs::File,
+    io::{ErrorKind, Read, Write},
+    os::{
+        fd::{AsFd, AsRawFd, FromRawFd},
+        unix::{fs::FileExt, net::UnixStream},
+    },
+    rc::Rc,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::{Result, bail};
+use lumen_core::Anchor as IslandAnchor;
+use slint::{
+    EventLoopError, LogicalPosition, PhysicalSize, SharedString,
+    platform::{
+        EventLoopProxy, Key, Platform, PlatformError, PointerEventButton, WindowAdapter,
+        WindowEvent,
+        software_renderer::{MinimalSoftwareWindow, PremultipliedRgbaColor, RepaintBufferType},
+    },
+};
+use wayland_client::{
+    Connection, Dispatch, EventQueue, Proxy as _, QueueHandle, WEnum,
+    backend::WaylandError,
+    delegate_noop, event_created_child,
+    globals::{GlobalListContents, registry_queue_init},
+    protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_compositor::WlCompositor,
+        wl_keyboard::{self, WlKeyboard},
+        wl_output::{self, WlOutput},
+        wl_pointer::{self, WlPointer},
+        wl_region::WlRegion,
+        wl_registry::{self, WlRegistry},
+        wl_seat::{self, WlSeat},
+        wl_shm::{self, WlShm},
+        wl_shm_pool::WlShmPool,
+        wl_surface::{self, WlSurface},
+    },
+};
+use wayland_protocols_wlr::{
+    foreign_toplevel::v1::client::{
+        zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+        zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+    },
+    layer_shell::v1::client::{
+        zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+        zwlr_layer_surface_v1::{self, Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1},
+    },
+};
+
+use crate::platform::{
+    Window,
+    fullscreen::Toplevels,
+    monitor::{Monitor, Position},
+    region::Rect,
+};
+
+/// Frame interval while Slint has animations running.
+const FRAME: Duration = Duration::from_millis(16);
+
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
+thread_local! {
+    static WINDOW: RefCell<Option<LayerShellWindow>> = const { RefCell::new(None) };
+}
+
+/// A Slint platform that draws the shell with the software renderer into a wlr-layer-shell
+/// surface, since winit can only create regular toplevels on Wayland.
+pub struct LayerShellPlatform {
+    shared: Rc<Shared>,
+    queue: RefCell<Option<EventQueue<State>>>,
+    state: RefCell<Option<State>>,
+    proxy: Arc<Proxy>,
+    wake: UnixStream,
+}
+
+/// What the event loop, the window adapter and `LayerShellWindow` all look at.
+struct Shared {
+    window: RefCell<Option<Rc<MinimalSoftwareWindow>>>,
+    /// Replaced whenever the layer surface moves to another output.
+    surface: RefCell<WlSurface>,
+    size: (u32, u32),
+    scale: Cell<u32>,
+    visible: Cell<bool>,
+    /// Whether the surface asks for the keyboard.
+    keyboard: Cell<bool>,
+    fullscreen: Cell<bool>,
+    input_region: RefCell<Vec<Rect>>,
+    monitors: RefCell<Vec<Monitor>>,
+    /// Set when `monitors` changed and the overlay has yet to hear about it.
+    monitors_changed: Cell<bool>,
+    /// The output the surface is shown on, when it's only one.
+    current_monitor: RefCell<Option<Monitor>>,
+    /// The output the layer surface should be on, by name; `None` lets the compositor pick.
+    output: RefCell<Option<String>>,
+    /// Set when `output` changed and the layer surface has to be recreated.
+    moved: Cell<bool>,
+    layout: Cell<Layout>,
+    /// Set when `layout` changed and has yet to be sent.
+    relayout: Cell<bool>,
+}
+
+/// How the layer surface sits on its output.
+#[derive(Clone, Copy, PartialEq)]
+struct Layout {
+    anchor: Anchor,
+    /// Top, right, bottom, left.
+    margin: (i32, i32, i32, i32),
+    exclusive_zone: i32,
+}
+
+impl Shared {
+    fn physical_size(&self) -> PhysicalSize {
+        let scale = self.scale.get();
+        PhysicalSize::new(self.size.0 * scale, self.size.1 * scale)
+    }
+
+    fn slint_window(&self) -> Option<Rc<MinimalSoftwareWindow>> {
+        self.window.borrow().clone()
+    }
+
+    fn surface(&self) -> WlSurface {
+        self.surface.borrow().clone()
+    }
+
+    /// Limits pointer input on the current surface to the last region `LayerShellWindow` set.
+    fn apply_input_region(&self, compositor: &WlCompositor, qh: &QueueHandle<State>) {
+        // regions are in surface coordinates, which don't include the buffer scale
+        let scale = self.scale.get() as i32;
+        let region = compositor.create_region(qh, ());
+        for rect in self.input_region.borrow().iter() {
+            region.add(
+                rect.x / scale,
+                rect.y / scale,
+                (rect.width + scale - 1) / scale,
+                (rect.height + scale - 1) / scale,
+            );
+        }
+
+        self.surface().set_input_region(Some(&region));
+        region.destroy();
+    }
+}
+
+impl LayerShellPlatform {
+    /// Fails when there's no Wayland compositor or it doesn't implement layer-shell.
+    pub fn new(width: u32, height: u32) -> Result<Self> {
+        let conn = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+        let qh = queue.handle();
+
+        let compositor: WlCompositor = globals.bind(&qh, 4..=6, ())?;
+        let shm: WlShm = globals.bind(&qh, 1..=1, ())?;
+        let layer_shell: ZwlrLayerShellV1 = globals.bind(&qh, 1..=4, ())?;
+        let outputs: Vec<Output> = globals.contents().with_list(|list| {
+            list.iter()
+                .filter(|global| global.interface == WlOutput::interface().name)
+                .map(|global| Output::bind(globals.registry(), global.name, global.version, &qh))
+                .collect()
+        });
+        // without a seat we still draw, there's just nothing to click with
+        let _seat: Option<WlSeat> = globals.bind(&qh, 1..=7, ()).ok();
+        // likewise without foreign-toplevel, fullscreen apps just don't hide the island
+        let _toplevels: Option<ZwlrForeignToplevelManagerV1> = globals.bind(&qh, 1..=3, ()).ok();
+
+        let (surface, layer) = layer_surface(&compositor, &layer_shell, None, (width, height), &qh);
+
+        let shared = Rc::new(Shared {
+            window: RefCell::new(None),
+            surface: RefCell::new(surface),
+            size: (width, height),
+            scale: Cell::new(1),
+            visible: Cell::new(true),
+            keyboard: Cell::new(false),
+            fullscreen: Cell::new(false),
+            input_region: RefCell::new(Vec::new()),
+            monitors: RefCell::new(Vec::new()),
+            monitors_changed: Cell::new(true),
+            current_monitor: RefCell::new(None),
+            output: RefCell::new(None),
+            moved: Cell::new(false),
+            layout: Cell::new(Layout {
+                anchor: Anchor::Top,
+                margin: (0, 0, 0, 0),
+                exclusive_zone: 0,
+            }),
+            relayout: Cell::new(false),
+        });
+
+        let mut state = State {
+            qh,
+            compositor,
+            shm,
+            layer_shell,
+            layer,
+            outputs,
+            surface_outputs: Vec::new(),
+            pointer: None,
+            keyboard: None,
+            pointer_position: LogicalPosition::default(),
+            toplevels: Toplevels::default(),
+            shared: shared.clone(),
+            buffers: None,
+            pixels: Vec::new(),
+            configured: false,
+            awaiting_configure: true,
+            mapped: false,
+            closed: false,
+        };
+        state.apply_layout();
+        shared.surface().commit();
+        queue.roundtrip(&mut state)?;
+        if state.closed {
+            bail!("Compositor closed the layer surface");
+        }
+
+        let (wake, wake_writer) = UnixStream::pair()?;
+        wake.set_nonblocking(true)?;
+
+        let window = LayerShellWindow {
+            conn,
+            compositor: state.compositor.clone(),
+            qh: state.qh.clone(),
+            shared: shared.clone(),
+        };
+        WINDOW.with(|current| *current.borrow_mut() = Some(window));
+
+        Ok(Self {
+            shared,
+            queue: RefCell::new(Some(queue)),
+            state: RefCell::new(Some(state)),
+            proxy: Arc::new(Proxy {
+                events: Mutex::new(Vec::new()),
+                quit: AtomicBool::new(false),
+                wake: wake_writer,
+            }),
+            wake,
+        })
+    }
+
+    fn drain_wake(&self) {
+        let mut buffer = [0u8; 64];
+        while matches!((&self.wake).read(&mut buffer), Ok(n) if n > 0) {}
+    }
+}
+
+impl Platform for LayerShellPlatform {
+    fn create_window_adapter(&self) -> Result<Rc<dyn WindowAdapter>, PlatformError> {
+        let window = MinimalSoftwareWindow::new(RepaintBufferType::NewBuffer);
+
+        let scale = self.shared.scale.get();
+        if scale != 1 {
+            window
+                .window()
+                .dispatch_event(WindowEvent::ScaleFactorChanged { scale_factor: scale as f32 });
+        }
+        window.window().set_size(self.shared.physical_size());
+
+        *self.shared.window.borrow_mut() = Some(window.clone());
+        Ok(window)
+    }
+
+    fn run_event_loop(&self) -> Result<(), PlatformError> {
+        let other = |e: WaylandError| PlatformError::Other(e.to_string());
+
+        let (Some(mut queue), Some(mut state)) =
+            (self.queue.borrow_mut().take(), self.state.borrow_mut().take())
+        else {
+            return Err(PlatformError::Other("The Wayland event loop already ran".into()));
+        };
+
+        loop {
+            slint::platform::update_timers_and_animations();
+
+            let events = std::mem::take(&mut *self.proxy.events.lock().unwrap());
+            for event in events {
+                event();
+            }
+
+            if self.proxy.quit.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+
+            state.draw();
+
+            queue.flush().map_err(other)?;
+            queue.dispatch_pending(&mut state).map_err(|e| PlatformError::Other(e.to_string()))?;
+
+            let Some(guard) = queue.prepare_read() else {
+                continue;
+            };
+
+            let animating =
+                self.shared.slint_window().is_some_and(|w| w.window().has_active_animations());
+            let timeout = if animating {
+                Some(FRAME)
+            } else {
+                slint::platform::duration_until_next_timer_update()
+            };
+
+            let (wayland_ready, woken) =
+                wait_readable(guard.connection_fd().as_raw_fd(), self.wake.as_raw_fd(), timeout);
+
+            if wayland_ready {
+                match guard.read() {
+                    Ok(_) => {}
+                    Err(WaylandError::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+                    Err(e) => return Err(other(e)),
+                }
+            } else {
+                drop(guard);
+            }
+
+            if woken {
+                self.drain_wake();
+            }
+
+            queue.dispatch_pending(&mut state).map_err(|e| PlatformError::Other(e.to_string()))?;
+        }
+    }
+
+    fn new_event_loop_proxy(&self) -> Option<Box<dyn EventLoopProxy>> {
+        Some(Box::new(ProxyHandle(self.proxy.clone())))
+    }
+}
+
+/// A fresh surface with the layer-shell role, on `output` or wherever the compositor puts
+/// it. Still needs its initial commit.
+fn layer_surface(
+    compositor: &WlCompositor,
+    layer_shell: &ZwlrLayerShellV1,
+    output: Option<&WlOutput>,
+    (width, height): (u32, u32),
+    qh: &QueueHandle<State>,
+) -> (WlSurface, ZwlrLayerSurfaceV1) {
+    let surface = compositor.create_surface(qh, ());
+    let layer =
+        layer_shell.get_layer_surface(&surface, output, Layer::Overlay, "lumen".into(), qh, ());
+    layer.set_size(width, height);
+    layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+
+    (surface, layer)
+}
+
+/// Waits until either fd is readable or `timeout` passes.
+fn wait_readable(first: i32, second: i32, timeout: Option<Duration>) -> (bool, bool) {
+    let mut fds = [
+        libc::pollfd { fd: first, events: libc::POLLIN, revents: 0 },
+        libc::pollfd { fd: second, events: libc::POLLIN, revents: 0 },
+    ];
+    let timeout = timeout.map_or(-1, |t| t.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32);
+
+    let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+    if ready <= 0 {
+        return (false, false);
+    }
+
+    (fds[0].revents != 0, fds[1].revents != 0)
+}
+
+struct Proxy {
+    events: Mutex<Vec<Box<dyn FnOnce() + Send>>>,
+    quit: AtomicBool,
+    wake: UnixStream,
+}
+
+impl Proxy {
+    fn wake(&self) {
+        let _ = (&self.wake).write(&[1]);
+    }
+}
+
+struct ProxyHandle(Arc<Proxy>);
+
+impl EventLoopProxy for ProxyHandle {
+    fn quit_event_loop(&self) -> Result<(), EventLoopError> {
+        self.0.quit.store(true, Ordering::Relaxed);
+        self.0.wake();
+        Ok(())
+    }
+
+    fn invoke_from_event_loop(
+        &self,
+        event: Box<dyn FnOnce() + Send>,
+    ) -> Result<(), EventLoopError> {
+        self.0.events.lock().unwrap().push(event);
+        self.0.wake();
+        Ok(())
+    }
+}
+
+/// Handle the overlay code drives the layer surface through.
+#[derive(Clone)]
+pub struct LayerShellWindow {
+    conn: Connection,
+    compositor: WlCompositor,
+    qh: QueueHandle<State>,
+    shared: Rc<Shared>,
+}
+
+impl LayerShellWindow {
+    /// The layer surface, when `LayerShellPlatform` is the active platform.
+    pub fn current() -> Option<Self> {
+        WINDOW.with(|current| current.borrow().clone())
+    }
+}
+
+impl Window for LayerShellWindow {
+    fn set_input_region(&self, rects: &[Rect]) {
+        self.shared.input_region.replace(rects.to_vec());
+        self.shared.apply_input_region(&self.compositor, &self.qh);
+        self.shared.surface().commit();
+        let _ = self.conn.flush();
+    }
+
+    fn set_visible(&self, visible: bool) {
+        self.shared.visible.set(visible);
+        if let Some(window) = self.shared.slint_window() {
+            window.request_redraw();
+        }
+    }
+
+    fn raise(&self) {
+        // the overlay layer is already above every toplevel and fullscreen surface
+    }
+
+    fn fullscreen_app_active(&self) -> bool {
+        self.shared.fullscreen.get()
+    }
+
+    fn set_keyboard_focus(&self, focused: bool) {
+        if self.shared.keyboard.replace(focused) != focused {
+            self.shared.relayout.set(true);
+        }
+    }
+
+    fn place(&self, monitor: Option<&Monitor>, position: &Position) {
+        let output = monitor.map(|monitor| monitor.name.clone());
+        if *self.shared.output.borrow() != output {
+            self.shared.output.replace(output);
+            self.shared.moved.set(true);
+        }
+
+        // margins are in surface coordinates, and only apply to the edges anchored to
+        let scale = self.shared.scale.get() as i32;
+        let (drag_x, drag_y) = (position.drag.0 / scale, position.drag.1 / scale);
+        let offset = position.offset;
+
+        let (mut anchor, mut margin) = if position.anchor.is_bottom() {
+            (Anchor::Bottom, (0, 0, offset.y - drag_y, 0))
+        } else {
+            (Anchor::Top, (offset.y + drag_y, 0, 0, 0))
+        };
+
+        match position.anchor {
+            IslandAnchor::TopLeft | IslandAnchor::BottomLeft => {
+                anchor |= Anchor::Left;
+                margin.3 = offset.x + drag_x;
+            }
+            IslandAnchor::TopRight | IslandAnchor::BottomRight => {
+                anchor |= Anchor::Right;
+                margin.1 = offset.x - drag_x;
+            }
+            IslandAnchor::TopCenter | IslandAnchor::BottomCenter => {
+                // a centered surface can't be nudged sideways, so measure from the left
+                // edge instead; without knowing the output it just stays centered
+                let current = self.shared.current_monitor.borrow();
+                if let Some(monitor) = monitor.or(current.as_ref()) {
+                    let output_width = (monitor.width as f64 / monitor.scale_factor).round() as i32;
+                    anchor |= Anchor::Left;
+                    margin.3 = (output_width - self.shared.size.0 as i32) / 2 + offset.x + drag_x;
+                }
+            }
+        }
+
+        let layout =
+            Layout { anchor, margin, exclusive_zone: if position.cover_panels { -1 } else { 0 } };
+        if self.shared.layout.replace(layout) != layout {
+            self.shared.relayout.set(true);
+        }
+    }
+
+    fn displays_changed(&self) -> bool {
+        self.shared.monitors_changed.replace(false)
+    }
+
+    fn monitors(&self) -> Option<Vec<Monitor>> {
+        Some(self.shared.monitors.borrow().clone())
+    }
+
+    fn current_monitor(&self) -> Option<Monitor> {
+        self.shared.current_monitor.borrow().clone()
+    }
+
+    fn can_mirror(&self) -> bool {
+        // the platform drives a single layer surface
+        false
+    }
+}
+
+/// A `wl_output` and what it has told us about itself so far.
+struct Output {
+    global: u32,
+    output: WlOutput,
+    monitor: Monitor,
+}
+
+impl Output {
+    fn bind(registry: &WlRegistry, global: u32, version: u32, qh: &QueueHandle<State>) -> Self {
+        Self {
+            global,
+            // names only arrived in version 4
+            output: registry.bind(global, version.min(4), qh, global),
+            monitor: Monitor {
+                name: String::new(),
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+                scale_factor: 1.0,
+                primary: false,
+            },
+        }
+    }
+}
+
+/// Two shm buffers in one memfd, so we can draw into one while the compositor reads the other.
+struct Buffers {
+    file: File,
+    pool: WlShmPool,
+    buffers: [(WlBuffer, bool); 2],
+    size: PhysicalSize,
+}
+
+impl Buffers {
+    fn new(shm: &WlShm, qh: &QueueHandle<State>, size: PhysicalSize) -> Result<Self> {
+        let frame_len = size.width as usize * size.height as usize * 4;
+
+        let fd = unsafe { libc::memfd_create(c"lumen-shm".as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            bail!("memfd_create failed: {}", std::io::Error::last_os_error());
+        }
+        let file = unsafe { File::from_raw_fd(fd) };
+        file.set_len((frame_len * 2) as u64)?;
+
+        let pool = shm.create_pool(file.as_fd(), (frame_len * 2) as i32, qh, ());
+        let buffer = |idx: usize| {
+            let buffer = pool.create_buffer(
+                (frame_len * idx) as i32,
+                size.width as i32,
+                size.height as i32,
+                size.width as i32 * 4,
+                wl_shm::Format::Argb8888,
+                qh,
+                idx,
+            );
+            (buffer, false)
+        };
+        let buffers = [buffer(0), buffer(1)];
+
+        Ok(Self { file, pool, buffers, size })
+    }
+}
+
+impl Drop for Buffers {
+    fn drop(&mut self) {
+        for (buffer, _) in &self.buffers {
+            buffer.destroy();
+        }
+        self.pool.destroy();
+    }
+}
+
+struct State {
+    qh: QueueHandle<State>,
+    compositor: WlCompositor,
+    shm: WlShm,
+    layer_shell: ZwlrLayerShellV1,
+    layer: ZwlrLayerSurfaceV1,
+    outputs: Vec<Output>,
+    /// Protocol ids of the outputs the surface is shown on.
+    surface_outputs: Vec<u32>,
+    pointer: Option<WlPointer>,
+    keyboard: Option<WlKeyboard>,
+    pointer_position: LogicalPosition,
+    toplevels: Toplevels,
+    shared: Rc<Shared>,
+    buffers: Option<Buffers>,
+    pixels: Vec<PremultipliedRgbaColor>,
+    configured: bool,
+    awaiting_configure: bool,
+    mapped: bool,
+    closed: bool,
+}
+
+impl State {
+    fn draw(&mut self) {
+        if self.closed || self.shared.moved.take() {
+            self.recreate_surface();
+        } else if self.shared.relayout.take() {
+            self.apply_layout();
+            self.shared.surface().commit();
+        }
+
+        let surface = self.shared.surface();
+
+        if !self.shared.visible.get() {
+            if self.mapped {
+                // a null buffer unmaps the layer surface; mapping it again starts over with
+                // a bufferless commit and a fresh configure
+                surface.attach(None, 0, 0);
+                surface.commit();
+                self.mapped = false;
+                self.configured = false;
+            }
+            return;
+        }
+
+        if !self.configured {
+            if !self.awaiting_configure {
+                surface.commit();
+                self.awaiting_configure = true;
+            }
+            return;
+        }
+
+        let Some(window) = self.shared.slint_window() else {
+            return;
+        };
+
+        let size = self.shared.physical_size();
+        if self.buffers.as_ref().is_none_or(|buffers| buffers.size != size) {
+            self.buffers = None;
+            match Buffers::new(&self.shm, &self.qh, size) {
+                Ok(buffers) => self.buffers = Some(buffers),
+                Err(e) => {
+                    eprintln!("[Window] Failed to allocate shm buffers: {e}");
+                    return;
+                }
+            }
+            window.request_redraw();
+        }
+
+        let Some(buffers) = self.buffers.as_mut() else {
+            return;
+        };
+        let Some(idx) = buffers.buffers.iter().position(|(_, busy)| !busy) else {
+            return;
+        };
+
+        let (width, height) = (size.width as usize, size.height as usize);
+        self.pixels.resize(width * height, PremultipliedRgbaColor::default());
+
+        let pixels = &mut self.pixels;
+        let drawn = window.draw_if_needed(|renderer| {
+            pixels.fill(PremultipliedRgbaColor::default());
+            renderer.render(pixels.as_mut_slice(), width);
+        });
+        if !drawn && self.mapped {
+            return;
+        }
+
+        // ARGB8888 is little-endian BGRA in memory, premultiplied like Slint's output
+        let bytes: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| [pixel.blue, pixel.green, pixel.red, pixel.alpha])
+            .collect();
+        if let Err(e) = buffers.file.write_all_at(&bytes, (bytes.len() * idx) as u64) {
+            eprintln!("[Window] Failed to write the frame: {e}");
+            return;
+        }
+
+        let (buffer, busy) = &mut buffers.buffers[idx];
+        surface.set_buffer_scale(self.shared.scale.get() as i32);
+        surface.attach(Some(&*buffer), 0, 0);
+        surface.damage_buffer(0, 0, i32::MAX, i32::MAX);
+        surface.commit();
+        *busy = true;
+        self.mapped = true;
+    }
+
+    /// Layer surfaces can't change outputs, so moving means starting over with a new one.
+    /// Also how we come back after the compositor closed ours, e.g. when its output went
+    /// away.
+    fn recreate_surface(&mut self) {
+        let output = self
+            .shared
+            .output
+            .borrow()
+            .clone()
+            .and_then(|name| self.outputs.iter().find(|output| output.monitor.name == name));
+
+        self.layer.destroy();
+        self.shared.surface().destroy();
+        self.buffers = None;
+
+        let (surface, layer) = layer_surface(
+            &self.compositor,
+            &self.layer_shell,
+            output.map(|output| &output.output),
+            self.shared.size,
+            &self.qh,
+        );
+        self.shared.surface.replace(surface);
+        self.layer = layer;
+
+        self.apply_layout();
+        self.shared.apply_input_region(&self.compositor, &self.qh);
+        self.shared.surface().commit();
+
+        self.surface_outputs.clear();
+        self.update_current_monitor();
+        self.configured = false;
+        self.awaiting_configure = true;
+        self.mapped = false;
+        self.closed = false;
+    }
+
+    fn apply_layout(&self) {
+        let Layout { anchor, margin: (top, right, bottom, left), exclusive_zone } =
+            self.shared.layout.get();
+
+        self.layer.set_anchor(anchor);
+        self.layer.set_margin(top, right, bottom, left);
+        // 0 keeps clear of panels' exclusive zones, -1 ignores them
+        self.layer.set_exclusive_zone(exclusive_zone);
+
+        // before version 4 the keyboard could only be taken exclusively, away from every app
+        let keyboard = if self.shared.keyboard.get() && self.layer.version() >= 4 {
+            KeyboardInteractivity::OnDemand
+        } else {
+            KeyboardInteractivity::None
+        };
+        self.layer.set_keyboard_interactivity(keyboard);
+    }
+
+    fn update_fullscreen(&self) {
+        self.shared.fullscreen.set(self.toplevels.fullscreen_active(&self.surface_outputs));
+    }
+
+    fn publish_monitors(&self) {
+        let monitors: Vec<_> = self.outputs.iter().map(|output| output.monitor.clone()).collect();
+        if *self.shared.monitors.borrow() != monitors {
+            self.shared.monitors.replace(monitors);
+            self.shared.monitors_changed.set(true);
+        }
+        self.update_current_monitor();
+    }
+
+    fn update_current_monitor(&self) {
+        let current = match self.surface_outputs[..] {
+            [id] => self
+                .outputs
+                .iter()
+                .find(|output| output.output.id().protocol_id() == id)
+                .map(|output| output.monitor.clone()),
+            _ => None,
+        };
+        self.shared.current_monitor.replace(current);
+    }
+
+    fn dispatch(&self, event: WindowEvent) {
+        if let Some(window) = self.shared.slint_window() {
+            window.window().dispatch_event(event);
+        }
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global { name, interface, version }
+                if interface == WlOutput::interface().name =>
+            {
+                state.outputs.push(Output::bind(registry, name, version, qh));
+            }
+            wl_registry::Event::GlobalRemove { name } => {
+                let Some(idx) = state.outputs.iter().position(|output| output.global == name)
+                else {
+                    return;
+                };
+
+                let output = state.outputs.remove(idx);
+                if output.output.version() >= 3 {
+                    output.output.release();
+                }
+                state.publish_monitors();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlOutput, u32> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlOutput,
+        event: wl_output::Event,
+        global: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(output) = state.outputs.iter_mut().find(|output| output.global == *global) else {
+            return;
+        };
+        let monitor = &mut output.monitor;
+
+        match event {
+            wl_output::Event::Geometry { x, y, .. } => (monitor.x, monitor.y) = (x, y),
+            wl_output::Event::Mode { flags: WEnum::Value(flags), width, height, .. }
+                if flags.contains(wl_output::Mode::Current) =>
+            {
+                (monitor.width, monitor.height) = (width.max(0) as u32, height.max(0) as u32);
+            }
+            wl_output::Event::Scale { factor } => monitor.scale_factor = factor.max(1) as f64,
+            wl_output::Event::Name { name } => monitor.name = name,
+            wl_output::Event::Done => state.publish_monitors(),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlSurface, ()> for State {
+    fn event(
+        state: &mut Self,
+        surface: &WlSurface,
+        event: wl_surface::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // events can still be queued for a surface we've since replaced
+        if *surface != state.shared.surface() {
+            return;
+        }
+
+        let factor = match event {
+            wl_surface::Event::Enter { output } => {
+                state.surface_outputs.push(output.id().protocol_id());
+                state.update_fullscreen();
+                state.update_current_monitor();
+                return;
+            }
+            wl_surface::Event::Leave { output } => {
+                let id = output.id().protocol_id();
+                state.surface_outputs.retain(|&output| output != id);
+                state.update_fullscreen();
+                state.update_current_monitor();
+                return;
+            }
+            wl_surface::Event::PreferredBufferScale { factor } => factor,
+            _ => return,
+        };
+
+        let scale = factor.max(1) as u32;
+        if scale == state.shared.scale.get() {
+            return;
+        }
+
+        state.shared.scale.set(scale);
+        if let Some(window) = state.shared.slint_window() {
+            window
+                .window()
+                .dispatch_event(WindowEvent::ScaleFactorChanged { scale_factor: scale as f32 });
+            window.window().set_size(state.shared.physical_size());
+        }
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        layer: &ZwlrLayerSurfaceV1,
+        event: zwlr_layer_surface_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if *layer != state.layer {
+            return;
+        }
+
+        match event {
+            zwlr_layer_surface_v1::Event::Configure { serial, .. } => {
+                layer.ack_configure(serial);
+                state.configured = true;
+                state.awaiting_configure = false;
+                if let Some(window) = state.shared.slint_window() {
+                    window.request_redraw();
+                }
+            }
+            zwlr_layer_surface_v1::Event::Closed => state.closed = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlBuffer, usize> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlBuffer,
+        event: wl_buffer::Event,
+        idx: &usize,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event
+            && let Some(buffers) = state.buffers.as_mut()
+        {
+            buffers.buffers[*idx].1 = false;
+        }
+    }
+}
+
+impl Dispatch<WlSeat, ()> for State {
+    fn event(
+        state: &mut Self,
+        seat: &WlSeat,
+        event: wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let wl_seat::Event::Capabilities { capabilities: WEnum::Value(capabilities) } = event
+        else {
+            return;
+        };
+
+        let has_pointer = capabilities.contains(wl_seat::Capability::Pointer);
+        match (has_pointer, state.pointer.take()) {
+            (true, None) => state.pointer = Some(seat.get_pointer(qh, ())),
+            (true, pointer) => state.pointer = pointer,
+            (false, Some(pointer)) => pointer.release(),
+            (false, None) => {}
+        }
+
+        let has_keyboard = capabilities.contains(wl_seat::Capability::Keyboard);
+        match (has_keyboard, state.keyboard.take()) {
+            (true, None) => state.keyboard = Some(seat.get_keyboard(qh, ())),
+            (true, keyboard) => state.keyboard = keyboard,
+            (false, Some(keyboard)) => keyboard.release(),
+            (false, None) => {}
+        }
+    }
+}
+
+/// What Slint calls the evdev key `code`, for the keys the expanded island reacts to.
+/// Without xkbcommon to read the keymap, they're taken as laid out on a US keyboard.
+fn key_text(code: u32) -> Option<SharedString> {
+    let key = match code {
+        1 => Key::Escape,
+        28 => Key::Return,
+        29 | 97 => Key::Control,
+        42 | 54 => Key::Shift,
+        50 => return Some("m".into()),
+        57 => return Some(" ".into()),
+        103 => Key::UpArrow,
+        105 => Key::LeftArrow,
+        106 => Key::RightArrow,
+        108 => Key::DownArrow,
+        111 => Key::Delete,
+        _ => return None,
+    };
+
+    Some(key.into())
+}
+
+impl Dispatch<WlKeyboard, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlKeyboard,
+        event: wl_keyboard::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_keyboard::Event::Key { key, state: WEnum::Value(pressed), .. } => {
+                let Some(text) = key_text(key) else {
+                    return;
+                };
+
+                state.dispatch(match pressed {
+                    wl_keyboard::KeyState::Pressed => WindowEvent::KeyPressed { text },
+                    _ => WindowEvent::KeyReleased { text },
+                });
+            }
+            wl_keyboard::Event::Enter { .. } => {
+                state.dispatch(WindowEvent::WindowActiveChanged(true))
+            }
+            wl_keyboard::Event::Leave { .. } => {
+                // releases while another surface has the keyboard never reach us
+                for key in [Key::Shift, Key::Control] {
+                    state.dispatch(WindowEvent::KeyReleased { text: key.into() });
+                }
+                state.dispatch(WindowEvent::WindowActiveChanged(false));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlPointer, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlPointer,
+        event: wl_pointer::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_pointer::Event::Enter { surface_x, surface_y, .. }
+            | wl_pointer::Event::Motion { surface_x, surface_y, .. } => {
+                let position = LogicalPosition::new(surface_x as f32, surface_y as f32);
+                state.pointer_position = position;
+                state.dispatch(WindowEvent::PointerMoved { position });
+            }
+            wl_pointer::Event::Leave { .. } => state.dispatch(WindowEvent::PointerExited),
+            wl_pointer::Event::Button { button, state: WEnum::Value(pressed), .. } => {
+                let button = match button {
+                    BTN_LEFT => PointerEventButton::Left,
+                    BTN_RIGHT => PointerEventButton::Right,
+                    BTN_MIDDLE => PointerEventButton::Middle,
+                    _ => PointerEventButton::Other,
+                };
+                let position = state.pointer_position;
+
+                state.dispatch(match pressed {
+                    wl_pointer::ButtonState::Pressed => {
+                        WindowEvent::PointerPressed { position, button }
+                    }
+                    _ => WindowEvent::PointerReleased { position, button },
+                });
+            }
+            wl_pointer::Event::Axis { axis: WEnum::Value(axis), value, .. } => {
+                // Wayland scrolls down with positive values, Slint the other way round
+                let delta = -value as f32;
+                let (delta_x, delta_y) = match axis {
+                    wl_pointer::Axis::HorizontalScroll => (delta, 0.0),
+                    _ => (0.0, delta),
+                };
+
+                state.dispatch(WindowEvent::PointerScrolled {
+                    position: state.pointer_position,
+                    delta_x,
+                    delta_y,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        _: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+
+    event_created_child!(State, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let id = handle.id().protocol_id();
+
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::State { state: states } => {
+                state.toplevels.set_state(id, &states);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::OutputEnter { output } => {
+                state.toplevels.output_enter(id, output.id().protocol_id());
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::OutputLeave { output } => {
+                state.toplevels.output_leave(id, output.id().protocol_id());
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Done => state.toplevels.done(id),
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                state.toplevels.closed(id);
+                handle.destroy();
+            }
+            _ => return,
+        }
+
+        state.update_fullscreen();
+    }
+}
+
+delegate_noop!(State: WlCompositor);
+delegate_noop!(State: WlRegion);
+delegate_noop!(State: WlShmPool);
+delegate_noop!(State: ZwlrLayerShellV1);
+delegate_noop!(State: ignore WlShm);
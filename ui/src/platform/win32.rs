@@ -0,0 +1,346 @@
+use std::cell::{Cell, RefCell};
+
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, POINT, RECT, WPARAM},
+    Graphics::Gdi::{
+        CombineRgn, CreateRectRgn, DeleteObject, GetMonitorInfoW, MONITOR_DEFAULTTONEAREST,
+        MONITORINFO, MonitorFromPoint, RGN_OR, ScreenToClient, SetWindowRgn, UpdateWindow,
+    },
+    UI::{
+        Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass},
+        WindowsAndMessaging::{
+            GWL_EXSTYLE, GWL_STYLE, GetCursorPos, GetForegroundWindow, GetSystemMetrics,
+            GetWindowLongPtrW, GetWindowRect, GetWindowThreadProcessId, HTTRANSPARENT,
+            HWND_TOPMOST, IsWindow, LWA_ALPHA, SM_CXSCREEN, SPI_SETWORKAREA, SW_HIDE,
+            SW_SHOWNOACTIVATE, SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE,
+            SetForegroundWindow, SetLayeredWindowAttributes, SetWindowLongPtrW, SetWindowPos,
+            ShowWindow, WM_DISPLAYCHANGE, WM_DPICHANGED, WM_NCHITTEST, WM_SETTINGCHANGE,
+            WS_EX_APPWINDOW, WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_POPUP,
+        },
+    },
+};
+
+use crate::platform::{
+    Window,
+    clickthrough::set_clickthrough,
+    fullscreen::is_foreground_fullscreen,
+    monitor::{Monitor, Position, usable_area},
+    region::Rect,
+};
+
+const SUBCLASS_ID: usize = 1;
+
+/// The winit window turned into a topmost, layered tool window.
+pub struct Win32Window {
+    hwnd: HWND,
+    width: i32,
+    height: i32,
+    /// The window that had the keyboard before the island took it.
+    previous_focus: Cell<Option<HWND>>,
+    /// Boxed so `subclass_proc` can keep a pointer to it.
+    subclass: Box<Subclass>,
+    overhang: Cell<i32>,
+}
+
+/// What the window procedure shares with the window.
+struct Subclass {
+    /// Where clicks land.
+    input: RefCell<Vec<Rect>>,
+    /// Set when Windows says the displays or the work area changed.
+    displays_changed: Cell<bool>,
+}
+
+impl Win32Window {
+    pub fn new(hwnd: HWND, width: i32, height: i32) -> Self {
+        let subclass = Box::new(Subclass {
+            input: RefCell::new(Vec::new()),
+            displays_changed: Cell::new(true),
+        });
+
+        unsafe {
+            configure_window(hwnd);
+            let _ = SetWindowSubclass(
+                hwnd,
+                Some(subclass_proc),
+                SUBCLASS_ID,
+                &*subclass as *const Subclass as usize,
+            );
+        }
+
+        Self {
+            hwnd,
+            width,
+            height,
+            previous_focus: Cell::new(None),
+            subclass,
+            overhang: Cell::new(0),
+        }
+    }
+
+    /// Shapes the window to the input region grown by the overhang, since a window region
+    /// clips painting as well. Clicks on the overhang are let through by `subclass_proc`.
+    unsafe fn apply_region(&self) {
+        let rects = self.subclass.input.borrow().clone();
+        let overhang = self.overhang.get();
+
+        unsafe {
+            // with nothing to click the window keeps its full shape and lets every click
+            // through instead
+            if rects.is_empty() {
+                set_clickthrough(self.hwnd, true);
+                SetWindowRgn(self.hwnd, None, true);
+                return;
+            }
+
+            let region = CreateRectRgn(0, 0, 0, 0);
+            for rect in &rects {
+                let part = CreateRectRgn(
+                    rect.x - overhang,
+                    rect.y - overhang,
+                    rect.x + rect.width + overhang,
+                    rect.y + rect.height + overhang,
+                );
+                CombineRgn(Some(region), Some(region), Some(part), RGN_OR);
+                let _ = DeleteObject(part.into());
+            }
+
+            // the system owns the region from here on
+            SetWindowRgn(self.hwnd, Some(region), true);
+            set_clickthrough(self.hwnd, false);
+        }
+    }
+}
+
+impl Drop for Win32Window {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = RemoveWindowSubclass(self.hwnd, Some(subclass_proc), SUBCLASS_ID);
+        }
+    }
+}
+
+impl Window for Win32Window {
+    fn set_input_region(&self, rects: &[Rect]) {
+        *self.subclass.input.borrow_mut() = rects.to_vec();
+        unsafe { self.apply_region() };
+    }
+
+    fn set_overhang(&self, overhang: i32) {
+        if self.overhang.replace(overhang) != overhang {
+            unsafe { self.apply_region() };
+        }
+    }
+
+    fn displays_changed(&self) -> bool {
+        self.subclass.displays_changed.replace(false)
+    }
+
+    fn set_visible(&self, visible: bool) {
+        unsafe {
+            if visible {
+                let _ = ShowWindow(self.hwnd, SW_SHOWNOACTIVATE);
+                let _ = UpdateWindow(self.hwnd);
+            } else {
+                let _ = ShowWindow(self.hwnd, SW_HIDE);
+            }
+        }
+    }
+
+    fn raise(&self) {
+        unsafe {
+            let _ = SetWindowPos(
+                self.hwnd,
+                Some(HWND_TOPMOST),
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+            );
+        }
+    }
+
+    fn fullscreen_app_active(&self) -> bool {
+        is_foreground_fullscreen(self.hwnd)
+    }
+
+    fn set_keyboard_focus(&self, focused: bool) {
+        unsafe {
+            let foreground = GetForegroundWindow();
+
+            if focused {
+                // clicking the island already brought one of our windows to the front
+                if is_own_window(foreground) {
+                    return;
+                }
+                self.previous_focus.set(Some(foreground));
+                let _ = SetForegroundWindow(self.hwnd);
+            } else if is_own_window(foreground)
+                && let Some(previous) = self.previous_focus.take()
+                && IsWindow(Some(previous)).as_bool()
+            {
+                let _ = SetForegroundWindow(previous);
+            }
+        }
+    }
+
+    fn place(&self, monitor: Option<&Monitor>, position: &Position) {
+        let Some(monitor) = monitor else {
+            unsafe { position_top_center(self.hwnd, self.width, self.height) };
+            return;
+        };
+
+        let width = (self.width as f64 * monitor.scale_factor).round() as i32;
+        let height = (self.height as f64 * monitor.scale_factor).round() as i32;
+        let area = usable_area(monitor, self, position.cover_panels);
+        let (x, y) = position.origin(area, (width, height), monitor.scale_factor);
+
+        unsafe {
+            SetWindowPos(self.hwnd, Some(HWND_TOPMOST), x, y, width, height, SWP_NOACTIVATE).ok();
+        }
+    }
+
+    fn work_area(&self, monitor: &Monitor) -> Option<Rect> {
+        let center = POINT {
+            x: monitor.x + monitor.width as i32 / 2,
+            y: monitor.y + monitor.height as i32 / 2,
+        };
+        let mut info =
+            MONITORINFO { cbSize: std::mem::size_of::<MONITORINFO>() as u32, ..Default::default() };
+
+        unsafe {
+            let handle = MonitorFromPoint(center, MONITOR_DEFAULTTONEAREST);
+            if !GetMonitorInfoW(handle, &mut info).as_bool() {
+                return None;
+            }
+        }
+
+        let work = info.rcWork;
+        Some(Rect {
+            x: work.left,
+            y: work.top,
+            width: work.right - work.left,
+            height: work.bottom - work.top,
+        })
+    }
+
+    fn cursor_position(&self) -> Option<(i32, i32)> {
+        let mut point = POINT::default();
+        unsafe { GetCursorPos(&mut point) }.ok()?;
+        Some((point.x, point.y))
+    }
+
+    fn focused_position(&self) -> Option<(i32, i32)> {
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.0.is_null() || hwnd == self.hwnd {
+                return None;
+            }
+
+            let mut rect = RECT::default();
+            GetWindowRect(hwnd, &mut rect).ok()?;
+            Some(((rect.left + rect.right) / 2, (rect.top + rect.bottom) / 2))
+        }
+    }
+}
+
+/// Passes clicks outside the input region on, so only the island itself and not the shadow
+/// around it takes them; Windows hands them to the windows below that belong to this
+/// thread, and drops them for any other. Also notes display and work area changes.
+unsafe extern "system" fn subclass_proc(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _id: usize,
+    subclass: usize,
+) -> LRESULT {
+    unsafe {
+        let subclass = &*(subclass as *const Subclass);
+
+        match message {
+            WM_NCHITTEST => {
+                // screen coordinates, packed as two signed 16-bit halves
+                let mut point = POINT {
+                    x: (lparam.0 & 0xffff) as i16 as i32,
+                    y: ((lparam.0 >> 16) & 0xffff) as i16 as i32,
+                };
+                let _ = ScreenToClient(hwnd, &mut point);
+
+                let inside = subclass.input.borrow().iter().any(|rect| {
+                    (rect.x..rect.x + rect.width).contains(&point.x)
+                        && (rect.y..rect.y + rect.height).contains(&point.y)
+                });
+                if !inside {
+                    return LRESULT(HTTRANSPARENT as isize);
+                }
+            }
+            WM_DISPLAYCHANGE | WM_DPICHANGED => subclass.displays_changed.set(true),
+            // a taskbar moved, resized or started hiding itself
+            WM_SETTINGCHANGE if wparam.0 == SPI_SETWORKAREA.0 as usize => {
+                subclass.displays_changed.set(true);
+            }
+            _ => {}
+        }
+
+        DefSubclassProc(hwnd, message, wparam, lparam)
+    }
+}
+
+unsafe fn is_own_window(hwnd: HWND) -> bool {
+    let mut pid = 0;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+
+    !hwnd.0.is_null() && pid == std::process::id()
+}
+
+unsafe fn configure_window(hwnd: HWND) {
+    let style = WS_POPUP.0 as isize;
+
+    unsafe {
+        SetWindowLongPtrW(hwnd, GWL_STYLE, style);
+
+        let mut ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+
+        ex_style &= !(WS_EX_APPWINDOW.0 as isize);
+        ex_style |= WS_EX_TOOLWINDOW.0 as isize;
+        ex_style |= WS_EX_LAYERED.0 as isize;
+
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style);
+
+        let _ = SetLayeredWindowAttributes(
+            hwnd,
+            windows::Win32::Foundation::COLORREF(0),
+            255,
+            LWA_ALPHA,
+        );
+
+        SetWindowPos(
+            hwnd,
+            Some(HWND_TOPMOST),
+            0,
+            0,
+            0,
+            0,
+            SWP_FRAMECHANGED | SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        )
+        .ok();
+    }
+}
+
+unsafe fn position_top_center(hwnd: HWND, width: i32, height: i32) {
+    unsafe {
+        let screen_width = GetSystemMetrics(SM_CXSCREEN);
+
+        SetWindowPos(
+            hwnd,
+            Some(HWND_TOPMOST),
+            (screen_width - width) / 2,
+            0,
+            width,
+            height,
+            SWP_NOACTIVATE,
+        )
+        .ok();
+    }
+}
@@ -1,5 +1,7 @@
 use std::{
-    sync::{Arc, Mutex, OnceLock},
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
@@ -7,231 +9,481 @@ use i_slint_backend_winit::{
     WinitWindowAccessor,
     winit::raw_window_handle::{HasWindowHandle, RawWindowHandle},
 };
+use lumen_core::{Config, MonitorPlacement};
 use slint::ComponentHandle;
-use windows::Win32::{
-    Foundation::{HWND, RECT},
-    Graphics::Gdi::UpdateWindow,
-    UI::{
-        HiDpi::GetDpiForWindow,
-        WindowsAndMessaging::{
-            GWL_EXSTYLE, GWL_STYLE, GetSystemMetrics, GetWindowLongPtrW, GetWindowRect,
-            HWND_TOPMOST, LWA_ALPHA, SM_CXSCREEN, SW_HIDE, SW_SHOWNOACTIVATE, SWP_FRAMECHANGED,
-            SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SetLayeredWindowAttributes, SetWindowLongPtrW,
-            SetWindowPos, ShowWindow, WS_EX_APPWINDOW, WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_POPUP,
-        },
-    },
-};
 
+#[cfg(windows)]
+use crate::platform::win32::Win32Window;
+#[cfg(target_os = "linux")]
+use crate::platform::{wayland::LayerShellWindow, x11::X11Window};
 use crate::{
-    geometry::SHELL_WIDTH,
+    Colors, IslandData, Shell,
     platform::{
-        clickthrough::set_clickthrough,
-        cursor::{cursor_position, point_inside_pill},
-        fullscreen::is_foreground_fullscreen,
+        Window,
+        monitor::{Monitor, Position, select, usable_area},
+        region::{Rect, pill_region},
     },
     state::{ContentState, IslandState},
 };
 
-static WINDOW_HWND: OnceLock<isize> = OnceLock::new();
+/// How long the island takes to animate between two shapes; the input region covers both
+/// until then.
+const RESHAPE_DURATION: Duration = Duration::from_millis(500);
+/// How often to check for fullscreen apps, for monitor changes the windowing system told us
+/// about, and where the cursor or focused window is for the placements that follow them.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Polls between reasserting the window's place above other always-on-top windows.
+const RAISE_EVERY: u8 = 4;
+/// Winit only creates the native window some time after the shell is shown.
+const NATIVE_WINDOW_DELAY: Duration = Duration::from_millis(200);
+
+/// Turns `shell` into the overlay and keeps it where `config` asks for, opening more shells
+/// through `open_mirror` when it's mirrored onto every monitor.
+pub fn initialize_window(
+    shell: &Shell,
+    width: i32,
+    height: i32,
+    config: &Config,
+    state: Arc<Mutex<IslandState>>,
+    set_visualizer_visible: impl Fn(bool) + 'static,
+    open_mirror: impl Fn() -> Option<Shell> + 'static,
+) {
+    let islands = Rc::new(Islands {
+        width,
+        height,
+        placement: config.monitor.clone(),
+        position: Cell::new(Position {
+            anchor: config.anchor,
+            offset: config.offset,
+            cover_panels: config.cover_panels,
+            drag: (0, 0),
+        }),
+        state,
+        set_visualizer_visible: Box::new(set_visualizer_visible),
+        visualizer_visible: Cell::new(None),
+        open_mirror: Box::new(open_mirror),
+        overlays: RefCell::new(Vec::new()),
+        keyboard_focus: Cell::new(false),
+        opening: Cell::new(0),
+    });
+
+    islands.open(shell.clone_strong());
+    start_watch(islands);
+}
+
+/// Wraps the shell's native window in the overlay backend for the running windowing system.
+fn overlay_window(shell: &Shell, width: i32, height: i32) -> Option<Box<dyn Window>> {
+    #[cfg(target_os = "linux")]
+    if let Some(window) = LayerShellWindow::current() {
+        return Some(Box::new(window));
+    }
 
-pub fn initialize_window<T>(
-    component: &T,
+    shell
+        .window()
+        .with_winit_window(|w| {
+            let handle = w.window_handle().ok()?.as_raw();
+
+            match handle {
+                #[cfg(windows)]
+                RawWindowHandle::Win32(h) => {
+                    let hwnd = windows::Win32::Foundation::HWND(h.hwnd.get() as *mut _);
+                    Some(Box::new(Win32Window::new(hwnd, width, height)) as Box<dyn Window>)
+                }
+                #[cfg(target_os = "linux")]
+                RawWindowHandle::Xlib(h) => {
+                    x11_window(h.window as u32, w.scale_factor(), width, height)
+                }
+                #[cfg(target_os = "linux")]
+                RawWindowHandle::Xcb(h) => {
+                    x11_window(h.window.get(), w.scale_factor(), width, height)
+                }
+                _ => None,
+            }
+        })
+        .flatten()
+}
+
+#[cfg(target_os = "linux")]
+fn x11_window(window: u32, scale_factor: f64, width: i32, height: i32) -> Option<Box<dyn Window>> {
+    match X11Window::new(window, scale_factor, width, height) {
+        Ok(window) => Some(Box::new(window)),
+        Err(e) => {
+            eprintln!("[Window] Failed to set up the X11 overlay: {e}");
+            None
+        }
+    }
+}
+
+/// The monitors as winit sees them.
+fn winit_monitors(shell: &Shell) -> Vec<Monitor> {
+    shell
+        .window()
+        .with_winit_window(|w| {
+            let primary = w.primary_monitor();
+
+            w.available_monitors()
+                .map(|monitor| Monitor {
+                    name: monitor.name().unwrap_or_default(),
+                    x: monitor.position().x,
+                    y: monitor.position().y,
+                    width: monitor.size().width,
+                    height: monitor.size().height,
+                    scale_factor: monitor.scale_factor(),
+                    primary: primary.as_ref() == Some(&monitor),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Every overlay showing the island, one per monitor it's placed on. The first wraps the
+/// shell `main` created; the rest are mirrors.
+struct Islands {
     width: i32,
     height: i32,
+    placement: MonitorPlacement,
+    /// Shared by every overlay; only the one being dragged strays from it.
+    position: Cell<Position>,
     state: Arc<Mutex<IslandState>>,
-    get_collapsed: impl Fn() -> bool + Send + 'static,
-) where
-    T: ComponentHandle + 'static,
-{
-    let weak = component.as_weak();
+    set_visualizer_visible: Box<dyn Fn(bool)>,
+    visualizer_visible: Cell<Option<bool>>,
+    open_mirror: Box<dyn Fn() -> Option<Shell>>,
+    overlays: RefCell<Vec<Rc<Overlay>>>,
+    /// Whether the first overlay has the keyboard, which it takes while the island is
+    /// expanded.
+    keyboard_focus: Cell<bool>,
+    /// Shells shown but not wrapped yet, since their native window doesn't exist right away.
+    opening: Cell<usize>,
+}
 
-    slint::Timer::single_shot(Duration::from_millis(200), move || {
-        if let Some(component) = weak.upgrade() {
-            with_hwnd(&component, |hwnd| unsafe {
-                configure_window(hwnd);
-                position_top_center(hwnd, width, height);
+impl Islands {
+    fn open(self: &Rc<Self>, shell: Shell) {
+        self.opening.set(self.opening.get() + 1);
+        let islands = Rc::downgrade(self);
 
-                WINDOW_HWND.set(hwnd.0 as isize).ok();
-                set_clickthrough(hwnd, true);
+        slint::Timer::single_shot(NATIVE_WINDOW_DELAY, move || {
+            let Some(islands) = islands.upgrade() else {
+                return;
+            };
+            islands.opening.set(islands.opening.get() - 1);
 
-                let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
-                let _ = UpdateWindow(hwnd);
+            let Some(window) = overlay_window(&shell, islands.width, islands.height) else {
+                eprintln!("[Window] No overlay support on this windowing system");
+                let _ = shell.hide();
+                return;
+            };
+            window.set_input_region(&[]);
+
+            let overlay = Rc::new(Overlay {
+                islands: Rc::downgrade(&islands),
+                window,
+                shell,
+                monitor: RefCell::new(None),
+                position: Cell::new(None),
+                area: Cell::new(None),
+                drag: Cell::new((0, 0)),
+                scale_factor: Cell::new(0.0),
+                wants_visualizer: Cell::new(false),
+                hidden_for_fullscreen: Cell::new(false),
+                region: RefCell::new(Vec::new()),
+                reshape: Cell::new(0),
+            });
 
-                start_clickthrough_loop(hwnd, state.clone(), get_collapsed);
+            let global = overlay.shell.global::<IslandData>();
+            let handler = Rc::downgrade(&overlay);
+            global.on_layout_changed(move || {
+                if let Some(overlay) = handler.upgrade() {
+                    overlay.layout_changed();
+                }
+            });
+            let handler = Rc::downgrade(&overlay);
+            global.on_drag_moved(move |dx, dy| {
+                if let Some(overlay) = handler.upgrade() {
+                    overlay.drag_moved(dx, dy);
+                }
+            });
+            let handler = Rc::downgrade(&overlay);
+            global.on_drag_ended(move || {
+                if let Some(overlay) = handler.upgrade() {
+                    overlay.drag_ended();
+                }
             });
+
+            islands.overlays.borrow_mut().push(overlay);
+            islands.arrange();
+        });
+    }
+
+    /// Puts every overlay on its monitor, opening or closing mirrors as monitors come and go.
+    fn arrange(self: &Rc<Self>) {
+        let overlays = self.overlays.borrow().clone();
+        let Some(first) = overlays.first() else {
+            return;
+        };
+
+        let monitors = first.window.monitors().unwrap_or_else(|| winit_monitors(&first.shell));
+        let mut targets = select(&self.placement, &monitors, &*first.window);
+        if !first.window.can_mirror() {
+            targets.truncate(1);
         }
-    });
-}
 
-fn with_hwnd<T>(component: &T, f: impl FnOnce(HWND))
-where
-    T: ComponentHandle,
-{
-    component.window().with_winit_window(|w| {
-        if let Ok(handle) = w.window_handle() {
-            if let RawWindowHandle::Win32(h) = handle.as_raw() {
-                let hwnd = HWND(h.hwnd.get() as *mut _);
-                f(hwnd);
+        if self.opening.get() == 0 {
+            for _ in overlays.len()..targets.len() {
+                if let Some(shell) = (self.open_mirror)() {
+                    self.open(shell);
+                }
             }
         }
-    });
-}
 
-unsafe fn configure_window(hwnd: HWND) {
-    let style = WS_POPUP.0 as isize;
-
-    unsafe {
-        SetWindowLongPtrW(hwnd, GWL_STYLE, style);
-
-        let mut ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
-
-        ex_style &= !(WS_EX_APPWINDOW.0 as isize);
-        ex_style |= WS_EX_TOOLWINDOW.0 as isize;
-        ex_style |= WS_EX_LAYERED.0 as isize;
-
-        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style);
-
-        let _ = SetLayeredWindowAttributes(
-            hwnd,
-            windows::Win32::Foundation::COLORREF(0),
-            255,
-            LWA_ALPHA,
-        );
-
-        SetWindowPos(
-            hwnd,
-            Some(HWND_TOPMOST),
-            0,
-            0,
-            0,
-            0,
-            SWP_FRAMECHANGED | SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
-        )
-        .ok();
+        // the first overlay stays even without a monitor to go on
+        let keep = targets.len().max(1);
+        if overlays.len() > keep {
+            for overlay in self.overlays.borrow_mut().drain(keep..) {
+                overlay.window.set_visible(false);
+                let _ = overlay.shell.hide();
+            }
+        }
+
+        for (idx, overlay) in overlays.iter().take(keep).enumerate() {
+            overlay.place(targets.get(idx).copied());
+        }
+
+        self.update_visualizer();
     }
-}
 
-unsafe fn position_top_center(hwnd: HWND, width: i32, height: i32) {
-    unsafe {
-        let screen_width = GetSystemMetrics(SM_CXSCREEN);
-
-        SetWindowPos(
-            hwnd,
-            Some(HWND_TOPMOST),
-            (screen_width - width) / 2,
-            0,
-            width,
-            height,
-            SWP_NOACTIVATE,
-        )
-        .ok();
+    fn update_visualizer(&self) {
+        let visible = self.overlays.borrow().iter().any(|overlay| overlay.wants_visualizer.get());
+
+        if self.visualizer_visible.get() != Some(visible) {
+            (self.set_visualizer_visible)(visible);
+            self.visualizer_visible.set(Some(visible));
+        }
     }
 }
 
-unsafe fn start_clickthrough_loop(
-    hwnd: HWND,
-    state: Arc<Mutex<IslandState>>,
-    get_collapsed: impl Fn() -> bool + Send + 'static,
-) {
-    let timer = Box::leak(Box::new(slint::Timer::default()));
+/// Keeps one overlay's position, input region and visibility in step with the island.
+struct Overlay {
+    islands: Weak<Islands>,
+    window: Box<dyn Window>,
+    shell: Shell,
+    monitor: RefCell<Option<Monitor>>,
+    /// Where the window was last put; `None` until it's been placed once.
+    position: Cell<Option<Position>>,
+    /// What of the monitor panels left free then.
+    area: Cell<Option<Rect>>,
+    drag: Cell<(i32, i32)>,
+    /// What the input region was last computed for.
+    scale_factor: Cell<f32>,
+    wants_visualizer: Cell<bool>,
+    hidden_for_fullscreen: Cell<bool>,
+    region: RefCell<Vec<Rect>>,
+    /// Bumped on every reshape, so a stale settle timer can tell it's been superseded.
+    reshape: Cell<u64>,
+}
 
-    let mut clickthrough_enabled = true;
-    let mut hidden_for_fullscreen = false;
+impl Overlay {
+    fn place(self: &Rc<Self>, monitor: Option<&Monitor>) {
+        let Some(islands) = self.islands.upgrade() else {
+            return;
+        };
+        let position = Position { drag: self.drag.get(), ..islands.position.get() };
 
-    let mut z_order_tick_counter = 0u8;
+        let area =
+            monitor.map(|monitor| usable_area(monitor, &*self.window, position.cover_panels));
 
-    timer.start(slint::TimerMode::Repeated, Duration::from_millis(16), move || {
-        let fullscreen = is_foreground_fullscreen(hwnd);
-        if fullscreen {
-            if !hidden_for_fullscreen {
-                let _ = unsafe { ShowWindow(hwnd, SW_HIDE) };
-                hidden_for_fullscreen = true;
-            }
+        let previous = self.position.replace(Some(position));
+        let previous_area = self.area.replace(area);
+        if previous == Some(position)
+            && previous_area == area
+            && self.monitor.borrow().as_ref() == monitor
+        {
             return;
         }
 
-        if hidden_for_fullscreen {
-            let _ = unsafe { ShowWindow(hwnd, SW_SHOWNOACTIVATE) };
-            hidden_for_fullscreen = false;
-            unsafe {
-                let _ = SetWindowPos(
-                    hwnd,
-                    Some(HWND_TOPMOST),
-                    0,
-                    0,
-                    0,
-                    0,
-                    SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
-                );
-            }
+        self.window.place(monitor, &position);
+        self.monitor.replace(monitor.cloned());
+
+        self.shell.set_island_align(position.anchor.align() as f32);
+        self.shell.set_island_bottom(position.anchor.is_bottom());
+
+        if previous.is_none() {
+            self.window.set_visible(true);
         }
+        self.layout_changed();
+    }
+
+    fn drag_moved(self: &Rc<Self>, dx: f32, dy: f32) {
+        let scale_factor = self.shell.window().scale_factor();
+        let (x, y) = self.drag.get();
+        self.drag
+            .set((x + (dx * scale_factor).round() as i32, y + (dy * scale_factor).round() as i32));
+
+        let monitor = self.monitor.borrow().clone();
+        self.place(monitor.as_ref());
+    }
 
-        z_order_tick_counter = z_order_tick_counter.wrapping_add(1);
-        if z_order_tick_counter >= 60 {
-            z_order_tick_counter = 0;
-            unsafe {
-                let _ = SetWindowPos(
-                    hwnd,
-                    Some(HWND_TOPMOST),
-                    0,
-                    0,
-                    0,
-                    0,
-                    SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
-                );
+    /// Snaps the island to the anchor nearest to where it was dropped, and remembers it.
+    fn drag_ended(self: &Rc<Self>) {
+        let Some(islands) = self.islands.upgrade() else {
+            return;
+        };
+        let drag = self.drag.replace((0, 0));
+        let position = Position { drag, ..islands.position.get() };
+
+        let monitor = self.monitor.borrow().clone().or_else(|| self.window.current_monitor());
+        if let Some(monitor) = monitor {
+            let scale_factor = monitor.scale_factor;
+            let size = (
+                (islands.width as f64 * scale_factor).round() as i32,
+                (islands.height as f64 * scale_factor).round() as i32,
+            );
+            let area = usable_area(&monitor, &*self.window, position.cover_panels);
+            let anchor = position.nearest_anchor(area, size, scale_factor);
+
+            if anchor != position.anchor {
+                islands.position.set(Position { anchor, drag: (0, 0), ..position });
+                if let Err(e) = Config::save_anchor(anchor) {
+                    eprintln!("[Window] Failed to save the anchor: {e}");
+                }
             }
         }
 
-        let state_guard = match state.try_lock() {
-            Ok(lock) => lock,
-            Err(_) => return,
+        islands.arrange();
+    }
+
+    fn layout_changed(self: &Rc<Self>) {
+        let Some(islands) = self.islands.upgrade() else {
+            return;
         };
 
-        let logical_bounds = state_guard.bounds();
-        let has_active =
-            state_guard.mic || state_guard.camera || state_guard.content != ContentState::Idle;
+        let global = self.shell.global::<IslandData>();
+        let collapsed = global.get_collapsed();
+        if !self.hidden_for_fullscreen.get() {
+            self.wants_visualizer.set(!collapsed);
+            islands.update_visualizer();
+        }
 
-        drop(state_guard);
+        let expanded = global.get_expanded();
+        let is_first =
+            islands.overlays.borrow().first().is_some_and(|first| Rc::ptr_eq(first, self));
+        if is_first && islands.keyboard_focus.replace(expanded) != expanded {
+            self.window.set_keyboard_focus(expanded);
+        }
 
-        let collapsed = get_collapsed();
-        let (mx, my) = cursor_position();
-        let mut rect = RECT::default();
+        let state = islands.state.lock().unwrap();
+        let logical_bounds = state.bounds();
+        let has_active = state.has_indicators() || state.content != ContentState::Idle;
+        drop(state);
 
-        unsafe {
-            let _ = GetWindowRect(hwnd, &mut rect);
-        }
+        let scale_factor = self.shell.window().scale_factor();
+        self.scale_factor.set(scale_factor);
 
-        let dpi = unsafe { GetDpiForWindow(hwnd) };
-        let scale_factor = dpi as f64 / 96.0;
-        let bounds = logical_bounds.physical(scale_factor);
+        // the shadow spreads as far as it blurs, plus its offset on one side
+        let shadow = self.shell.global::<Colors>().get_island_shadow_blur() + 2.0;
+        self.window.set_overhang((shadow * scale_factor).ceil() as i32);
 
-        let island_x = (SHELL_WIDTH - bounds.width) / 2;
-        let island_left = rect.left + island_x;
-        let island_top = rect.top
-            + if collapsed {
-                ((-(logical_bounds.height - 10)) as f64 * scale_factor).round() as i32
+        let rects = if !collapsed && !has_active {
+            Vec::new()
+        } else {
+            let anchor = islands.position.get().anchor;
+            let scale_factor = scale_factor as f64;
+            let bounds = logical_bounds.physical(scale_factor);
+            let window_size = self.shell.window().size();
+
+            let left =
+                ((window_size.width as i32 - bounds.width) as f64 * anchor.align()).round() as i32;
+            // the pill sits 8px in from the edge, or hangs off it with only those 8px showing
+            let top = if collapsed {
+                ((8 - logical_bounds.height) as f64 * scale_factor).round() as i32
             } else {
-                0
+                (8.0 * scale_factor).round() as i32
             };
 
-        let px = mx - island_left;
-        let py = my - island_top;
-
-        let inside = if !collapsed && !has_active {
-            false
-        } else {
-            point_inside_pill(px, py, bounds.width, bounds.height, bounds.radius)
+            let mut rects = pill_region(left, top, bounds);
+            if anchor.is_bottom() {
+                let height = window_size.height as i32;
+                for rect in &mut rects {
+                    rect.y = height - rect.y - rect.height;
+                }
+            }
+            rects
         };
 
-        unsafe {
-            if inside && clickthrough_enabled {
-                set_clickthrough(hwnd, false);
-                clickthrough_enabled = false;
+        let previous = self.region.replace(rects.clone());
+        if previous == rects {
+            return;
+        }
+
+        // take input over both shapes while the island animates from one to the other
+        let mut combined = previous;
+        combined.extend_from_slice(&rects);
+        self.window.set_input_region(&combined);
+
+        let reshape = self.reshape.get() + 1;
+        self.reshape.set(reshape);
+
+        let overlay = Rc::downgrade(self);
+        slint::Timer::single_shot(RESHAPE_DURATION, move || {
+            if let Some(overlay) = overlay.upgrade()
+                && overlay.reshape.get() == reshape
+            {
+                overlay.window.set_input_region(&rects);
             }
+        });
+    }
+
+    /// Hides the overlay while a fullscreen app is focused on its monitor.
+    fn check_fullscreen(self: &Rc<Self>) {
+        if self.window.fullscreen_app_active() {
+            if !self.hidden_for_fullscreen.get() {
+                self.window.set_visible(false);
+                self.hidden_for_fullscreen.set(true);
+            }
+            self.wants_visualizer.set(false);
+            return;
+        }
+
+        if self.hidden_for_fullscreen.get() {
+            self.window.set_visible(true);
+            self.hidden_for_fullscreen.set(false);
+            self.window.raise();
+            self.layout_changed();
+        } else if self.shell.window().scale_factor() != self.scale_factor.get() {
+            // moving to a monitor with another DPI rescales the island under the region
+            self.layout_changed();
+        }
+    }
+}
+
+fn start_watch(islands: Rc<Islands>) {
+    let timer = Box::leak(Box::new(slint::Timer::default()));
+    let mut raise_tick_counter = 0u8;
+
+    timer.start(slint::TimerMode::Repeated, POLL_INTERVAL, move || {
+        let overlays = islands.overlays.borrow().clone();
+
+        // each overlay's window may have heard on its own, and each forgets once asked
+        let mut displays_changed = false;
+        for overlay in &overlays {
+            displays_changed |= overlay.window.displays_changed();
+        }
+        let follows_focus =
+            matches!(islands.placement, MonitorPlacement::Cursor | MonitorPlacement::Focused);
+        if displays_changed || follows_focus {
+            islands.arrange();
+        }
+
+        let overlays = islands.overlays.borrow().clone();
+        for overlay in &overlays {
+            overlay.check_fullscreen();
+        }
+        islands.update_visualizer();
 
-            if !inside && !clickthrough_enabled {
-                set_clickthrough(hwnd, true);
-                clickthrough_enabled = true;
+        raise_tick_counter = raise_tick_counter.wrapping_add(1);
+        if raise_tick_counter >= RAISE_EVERY {
+            raise_tick_counter = 0;
+            for overlay in overlays.iter().filter(|overlay| !overlay.hidden_for_fullscreen.get()) {
+                overlay.window.raise();
             }
         }
     });
@@ -0,0 +1,325 @@
+use std::cell::{Cell, RefCell};
+
+use anyhow::Result;
+use x11rb::{
+    connection::{Connection, RequestConnection},
+    protocol::{
+        Event,
+        randr::{self, ConnectionExt as _, NotifyMask},
+        shape::{ConnectionExt as _, SK, SO},
+        xproto::{
+            AtomEnum, ChangeWindowAttributesAux, ClipOrdering, ConfigureWindowAux,
+            ConnectionExt as _, EventMask, InputFocus, PropMode, Rectangle, StackMode,
+        },
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+};
+
+use crate::platform::{
+    Window,
+    fullscreen::{FullscreenAtoms, active_window, fullscreen_active_window},
+    monitor::{Monitor, Position, usable_area},
+    region::Rect,
+};
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_SUPPORTING_WM_CHECK,
+        _NET_CURRENT_DESKTOP,
+        _NET_WORKAREA,
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_WINDOW_TYPE_DOCK,
+        _NET_WM_STATE,
+        _NET_WM_STATE_ABOVE,
+        _NET_WM_STATE_STICKY,
+        _NET_WM_STATE_SKIP_TASKBAR,
+        _NET_WM_STATE_SKIP_PAGER,
+    }
+}
+
+/// The winit window turned into a dock that stays above other windows, or an
+/// override-redirect one when no EWMH window manager is running.
+pub struct X11Window {
+    conn: RustConnection,
+    window: u32,
+    root: u32,
+    atoms: Atoms,
+    screen: Monitor,
+    width: i32,
+    height: i32,
+    monitor: RefCell<Option<Monitor>>,
+    fullscreen_atoms: FullscreenAtoms,
+    /// The window that had the keyboard before the island took it.
+    previous_focus: Cell<Option<u32>>,
+    /// Until the events saying so are read.
+    displays_changed: Cell<bool>,
+}
+
+impl X11Window {
+    pub fn new(window: u32, scale_factor: f64, width: i32, height: i32) -> Result<Self> {
+        let (conn, screen) = RustConnection::connect(None)?;
+        let atoms = Atoms::new(&conn)?.reply()?;
+        let fullscreen_atoms = FullscreenAtoms::new(&conn)?.reply()?;
+
+        let screen = &conn.setup().roots[screen];
+        let root = screen.root;
+        // stands in for the monitor when winit can't list any
+        let screen = Monitor {
+            name: String::new(),
+            x: 0,
+            y: 0,
+            width: screen.width_in_pixels as u32,
+            height: screen.height_in_pixels as u32,
+            scale_factor,
+            primary: true,
+        };
+
+        // window managers only read the type and initial state when a window is mapped
+        conn.unmap_window(window)?;
+
+        let has_wm = conn
+            .get_property(false, root, atoms._NET_SUPPORTING_WM_CHECK, AtomEnum::WINDOW, 0, 1)?
+            .reply()?
+            .value32()
+            .is_some_and(|mut value| value.next().is_some());
+
+        if has_wm {
+            conn.change_property32(
+                PropMode::REPLACE,
+                window,
+                atoms._NET_WM_WINDOW_TYPE,
+                AtomEnum::ATOM,
+                &[atoms._NET_WM_WINDOW_TYPE_DOCK],
+            )?;
+            conn.change_property32(
+                PropMode::REPLACE,
+                window,
+                atoms._NET_WM_STATE,
+                AtomEnum::ATOM,
+                &[
+                    atoms._NET_WM_STATE_ABOVE,
+                    atoms._NET_WM_STATE_STICKY,
+                    atoms._NET_WM_STATE_SKIP_TASKBAR,
+                    atoms._NET_WM_STATE_SKIP_PAGER,
+                ],
+            )?;
+        } else {
+            conn.change_window_attributes(
+                window,
+                &ChangeWindowAttributesAux::new().override_redirect(1),
+            )?;
+        }
+
+        // monitors come and go through RandR, and panels publish the work area on the root
+        conn.change_window_attributes(
+            root,
+            &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+        if conn.extension_information(randr::X11_EXTENSION_NAME)?.is_some() {
+            conn.randr_query_version(1, 2)?.reply()?;
+            conn.randr_select_input(
+                root,
+                NotifyMask::SCREEN_CHANGE | NotifyMask::CRTC_CHANGE | NotifyMask::OUTPUT_CHANGE,
+            )?;
+        }
+
+        conn.flush()?;
+
+        Ok(Self {
+            conn,
+            window,
+            root,
+            atoms,
+            screen,
+            width,
+            height,
+            monitor: RefCell::new(None),
+            fullscreen_atoms,
+            previous_focus: Cell::new(None),
+            displays_changed: Cell::new(true),
+        })
+    }
+
+    /// The center of `window` in root coordinates.
+    fn center_of(&self, window: u32) -> Option<(i32, i32)> {
+        let geometry = self.conn.get_geometry(window).ok()?.reply().ok()?;
+        let origin = self.conn.translate_coordinates(window, self.root, 0, 0).ok()?.reply().ok()?;
+
+        Some((
+            origin.dst_x as i32 + geometry.width as i32 / 2,
+            origin.dst_y as i32 + geometry.height as i32 / 2,
+        ))
+    }
+}
+
+impl Window for X11Window {
+    fn set_input_region(&self, rects: &[Rect]) {
+        let rectangles: Vec<Rectangle> = rects
+            .iter()
+            .map(|rect| Rectangle {
+                x: rect.x.clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+                y: rect.y.clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+                width: rect.width.clamp(0, u16::MAX as i32) as u16,
+                height: rect.height.clamp(0, u16::MAX as i32) as u16,
+            })
+            .collect();
+
+        let result = self.conn.shape_rectangles(
+            SO::SET,
+            SK::INPUT,
+            ClipOrdering::UNSORTED,
+            self.window,
+            0,
+            0,
+            &rectangles,
+        );
+
+        if let Err(e) = result {
+            eprintln!("[Window] Failed to update the input shape: {e}");
+        }
+        let _ = self.conn.flush();
+    }
+
+    fn displays_changed(&self) -> bool {
+        let mut changed = self.displays_changed.replace(false);
+
+        // nothing else reads this connection's events
+        while let Ok(Some(event)) = self.conn.poll_for_event() {
+            changed |= match event {
+                Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_) => true,
+                Event::PropertyNotify(event) => {
+                    event.atom == self.atoms._NET_WORKAREA
+                        || event.atom == self.atoms._NET_CURRENT_DESKTOP
+                }
+                _ => false,
+            };
+        }
+
+        changed
+    }
+
+    fn set_visible(&self, visible: bool) {
+        let _ = if visible {
+            self.conn.map_window(self.window)
+        } else {
+            self.conn.unmap_window(self.window)
+        };
+        let _ = self.conn.flush();
+    }
+
+    fn raise(&self) {
+        let _ = self
+            .conn
+            .configure_window(self.window, &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE));
+        let _ = self.conn.flush();
+    }
+
+    fn fullscreen_app_active(&self) -> bool {
+        let Some(active) =
+            fullscreen_active_window(&self.conn, self.root, self.window, &self.fullscreen_atoms)
+        else {
+            return false;
+        };
+
+        match self.monitor.borrow().as_ref() {
+            Some(monitor) => self.center_of(active).is_none_or(|center| monitor.contains(center)),
+            None => true,
+        }
+    }
+
+    fn set_keyboard_focus(&self, focused: bool) {
+        let Some(current) = self.conn.get_input_focus().ok().and_then(|cookie| cookie.reply().ok())
+        else {
+            return;
+        };
+
+        if focused {
+            if current.focus == self.window {
+                return;
+            }
+            self.previous_focus.set(Some(current.focus));
+            let _ = self.conn.set_input_focus(InputFocus::PARENT, self.window, x11rb::CURRENT_TIME);
+        } else if current.focus == self.window
+            && let Some(previous) = self.previous_focus.take()
+        {
+            // fails harmlessly when that window has gone away in the meantime
+            let _ = self.conn.set_input_focus(InputFocus::PARENT, previous, x11rb::CURRENT_TIME);
+        }
+        let _ = self.conn.flush();
+    }
+
+    fn place(&self, monitor: Option<&Monitor>, position: &Position) {
+        let target = monitor.unwrap_or(&self.screen);
+        let width = (self.width as f64 * target.scale_factor).round() as i32;
+        let height = (self.height as f64 * target.scale_factor).round() as i32;
+        let area = usable_area(target, self, position.cover_panels);
+        let (x, y) = position.origin(area, (width, height), target.scale_factor);
+
+        let _ = self.conn.configure_window(
+            self.window,
+            &ConfigureWindowAux::new().x(x).y(y).width(width as u32).height(height as u32),
+        );
+        let _ = self.conn.flush();
+
+        self.monitor.replace(monitor.cloned());
+    }
+
+    fn work_area(&self, monitor: &Monitor) -> Option<Rect> {
+        let desktop = self
+            .conn
+            .get_property(
+                false,
+                self.root,
+                self.atoms._NET_CURRENT_DESKTOP,
+                AtomEnum::CARDINAL,
+                0,
+                1,
+            )
+            .ok()?
+            .reply()
+            .ok()?
+            .value32()
+            .and_then(|mut value| value.next())
+            .unwrap_or(0);
+
+        // one x, y, width, height per desktop, spanning every monitor
+        let workarea: Vec<u32> = self
+            .conn
+            .get_property(
+                false,
+                self.root,
+                self.atoms._NET_WORKAREA,
+                AtomEnum::CARDINAL,
+                desktop * 4,
+                4,
+            )
+            .ok()?
+            .reply()
+            .ok()?
+            .value32()?
+            .collect();
+        let [x, y, width, height] = workarea[..] else {
+            return None;
+        };
+
+        let (left, top) = (monitor.x.max(x as i32), monitor.y.max(y as i32));
+        let right = (monitor.x + monitor.width as i32).min((x + width) as i32);
+        let bottom = (monitor.y + monitor.height as i32).min((y + height) as i32);
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        Some(Rect { x: left, y: top, width: right - left, height: bottom - top })
+    }
+
+    fn cursor_position(&self) -> Option<(i32, i32)> {
+        let pointer = self.conn.query_pointer(self.root).ok()?.reply().ok()?;
+        Some((pointer.root_x as i32, pointer.root_y as i32))
+    }
+
+    fn focused_position(&self) -> Option<(i32, i32)> {
+        let active = active_window(&self.conn, self.root, self.window, &self.fullscreen_atoms)?;
+        self.center_of(active)
+    }
+}
@@ -1,4 +1,4 @@
-use lumen_core::{MediaState, NotificationState};
+use lumen_core::{MediaState, NotificationState, VolumeState};
 
 use crate::geometry::IslandBounds;
 
@@ -8,35 +8,65 @@ pub struct IslandState {
 
     pub mic: bool,
     pub camera: bool,
+    pub screen_capture: bool,
+    pub location: bool,
 
     pub expanded: bool,
+    /// The media has lyrics, which need room for a line collapsed and a few expanded.
+    pub lyrics: bool,
+
+    /// From the theme, the same for every layout.
+    pub radius: i32,
 }
 
 impl IslandState {
     pub fn new() -> Self {
-        Self { content: ContentState::Idle, mic: false, camera: false, expanded: false }
+        Self {
+            content: ContentState::Idle,
+            mic: false,
+            camera: false,
+            screen_capture: false,
+            location: false,
+            expanded: false,
+            lyrics: false,
+            radius: 24,
+        }
+    }
+
+    pub fn has_indicators(&self) -> bool {
+        self.mic || self.camera || self.screen_capture || self.location
     }
 
     pub fn bounds(&self) -> IslandBounds {
-        let bounds = match (&self.content, self.expanded) {
-            (ContentState::Idle, _) => IslandBounds { y: -48, width: 180, height: 48, radius: 24 },
+        match (&self.content, self.expanded) {
+            (ContentState::Idle, _) => {
+                IslandBounds { y: -48, width: 180, height: 48, radius: self.radius }
+            }
 
+            (ContentState::Media(_), false) if self.lyrics => {
+                IslandBounds { y: 8, width: 340, height: 48, radius: self.radius }
+            }
             (ContentState::Media(_), false) => {
-                IslandBounds { y: 8, width: 240, height: 48, radius: 24 }
+                IslandBounds { y: 8, width: 240, height: 48, radius: self.radius }
+            }
+            (ContentState::Media(_), true) if self.lyrics => {
+                IslandBounds { y: 8, width: 400, height: 280, radius: self.radius }
             }
             (ContentState::Media(_), true) => {
-                IslandBounds { y: 8, width: 400, height: 200, radius: 24 }
+                IslandBounds { y: 8, width: 400, height: 200, radius: self.radius }
             }
 
             (ContentState::Notification(_), false) => {
-                IslandBounds { y: 8, width: 320, height: 80, radius: 24 }
+                IslandBounds { y: 8, width: 320, height: 80, radius: self.radius }
             }
             (ContentState::Notification(_), true) => {
-                IslandBounds { y: 8, width: 400, height: 180, radius: 24 }
+                IslandBounds { y: 8, width: 400, height: 180, radius: self.radius }
             }
-        };
 
-        bounds
+            (ContentState::Volume(_), _) => {
+                IslandBounds { y: 8, width: 280, height: 56, radius: self.radius }
+            }
+        }
     }
 }
 
@@ -45,4 +75,5 @@ pub enum ContentState {
     Idle,
     Media(MediaState),
     Notification(NotificationState),
+    Volume(VolumeState),
 }
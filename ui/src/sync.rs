@@ -1,9 +1,18 @@
-use std::{cell::RefCell, collections::HashMap, path::Path};
-
-use lumen_core::{MediaState, NotificationState};
-use slint::{Image, SharedString};
-
-use crate::{MediaState as SlintMediaState, NotificationState as SlintNotificationState};
+use std::{cell::RefCell, collections::HashMap, fmt::Write, path::Path, time::Duration};
+
+use lumen_core::{
+    AppUsage, AudioLevels, LoudnessState, Lyrics, MediaState, NotificationState, OutputDevice,
+    RepeatMode, Rgba, Sensor, UsageSummary, VisualizerMode, VolumeState,
+};
+use slint::{Color, Image, ModelRc, SharedString, VecModel};
+
+use crate::{
+    AppUsage as SlintAppUsage, AudioLevels as SlintAudioLevels,
+    LoudnessState as SlintLoudnessState, MediaState as SlintMediaState,
+    NotificationState as SlintNotificationState, OutputDevice as SlintOutputDevice,
+    RepeatMode as SlintRepeatMode, Sensor as SlintSensor, UsageSummary as SlintUsageSummary,
+    VisualizerMode as SlintVisualizerMode, VolumeState as SlintVolumeState,
+};
 
 thread_local! {
     static LOCAL_TEXTURE_CACHE: RefCell<HashMap<String, Image>> = RefCell::new(HashMap::new());
@@ -47,13 +56,53 @@ pub fn media_to_slint(
         artist: SharedString::from(&media.artist),
 
         album_art: load_image(media.album_art.as_deref(), fallback_album),
+        has_palette: media.palette.is_some(),
+        tint: media.palette.map(|p| lit(p.vibrant, 255)).unwrap_or_default(),
+        glow: media.palette.map(|p| lit(p.dominant, 150)).unwrap_or_default(),
 
         playing: media.playing,
+        has_shuffle: media.shuffle.is_some(),
+        shuffle: media.shuffle.unwrap_or_default(),
+        has_repeat: media.repeat.is_some(),
+        repeat: match media.repeat.unwrap_or_default() {
+            RepeatMode::Off => SlintRepeatMode::Off,
+            RepeatMode::Track => SlintRepeatMode::Track,
+            RepeatMode::List => SlintRepeatMode::List,
+        },
+        has_rate: media.rate.is_some(),
+        rate: media.rate.unwrap_or(1.0) as f32,
+
+        can_play_pause: media.capabilities.play_pause,
+        can_next: media.capabilities.next,
+        can_previous: media.capabilities.previous,
+        can_seek: media.capabilities.seek,
+        can_shuffle: media.capabilities.shuffle,
+        can_repeat: media.capabilities.repeat,
+        can_rate: media.capabilities.rate,
 
         duration_ms: media.duration_ms as i32,
     }
 }
 
+/// `color` brightened just enough to stand out on the island, keeping its hue and saturation.
+fn lit(color: Rgba, alpha: u8) -> Color {
+    const MIN_VALUE: u8 = 217;
+
+    let max = color.r.max(color.g).max(color.b);
+    let scale = |channel: u8| {
+        if max >= MIN_VALUE {
+            return channel;
+        }
+        // a black cover has no hue to keep
+        if max == 0 {
+            return MIN_VALUE;
+        }
+        (channel as u32 * MIN_VALUE as u32 / max as u32) as u8
+    };
+
+    Color::from_argb_u8(alpha, scale(color.r), scale(color.g), scale(color.b))
+}
+
 pub fn notification_to_slint(
     notif: &NotificationState,
     fallback_app: &Image,
@@ -68,3 +117,118 @@ pub fn notification_to_slint(
         body: SharedString::from(&notif.body),
     }
 }
+
+pub fn volume_to_slint(volume: &VolumeState) -> SlintVolumeState {
+    SlintVolumeState {
+        level: volume.level,
+        muted: volume.muted,
+        device: SharedString::from(&volume.device),
+    }
+}
+
+pub fn apps_to_slint(apps: &[AppUsage], fallback_icon: &Image) -> ModelRc<SlintAppUsage> {
+    let apps: Vec<SlintAppUsage> = apps
+        .iter()
+        .map(|app| SlintAppUsage {
+            name: SharedString::from(&app.name),
+            icon: load_image(app.icon.as_deref(), fallback_icon),
+        })
+        .collect();
+
+    ModelRc::new(VecModel::from(apps))
+}
+
+pub fn usage_to_slint(report: &[UsageSummary]) -> ModelRc<SlintUsageSummary> {
+    let report: Vec<SlintUsageSummary> = report
+        .iter()
+        .map(|usage| SlintUsageSummary {
+            sensor: match usage.sensor {
+                Sensor::Microphone => SlintSensor::Microphone,
+                Sensor::Camera => SlintSensor::Camera,
+                Sensor::ScreenCapture => SlintSensor::ScreenCapture,
+                Sensor::Location => SlintSensor::Location,
+            },
+            app: SharedString::from(&usage.app),
+            total: SharedString::from(format_usage_total(usage.total)),
+        })
+        .collect();
+
+    ModelRc::new(VecModel::from(report))
+}
+
+/// "<1 min", "42 min" or "1 h 5 min".
+pub fn format_usage_total(total: Duration) -> String {
+    let minutes = total.as_secs() / 60;
+
+    match minutes {
+        0 => "<1 min".to_string(),
+        1..60 => format!("{minutes} min"),
+        _ => format!("{} h {} min", minutes / 60, minutes % 60),
+    }
+}
+
+pub fn output_devices_to_slint(devices: &[OutputDevice]) -> ModelRc<SlintOutputDevice> {
+    let devices: Vec<SlintOutputDevice> = devices
+        .iter()
+        .map(|device| SlintOutputDevice {
+            id: SharedString::from(&device.id),
+            name: SharedString::from(&device.name),
+            is_default: device.is_default,
+        })
+        .collect();
+
+    ModelRc::new(VecModel::from(devices))
+}
+
+pub fn visualizer_to_slint(mode: VisualizerMode) -> SlintVisualizerMode {
+    match mode {
+        VisualizerMode::Bars => SlintVisualizerMode::Bars,
+        VisualizerMode::MirroredBars => SlintVisualizerMode::MirroredBars,
+        VisualizerMode::Waveform => SlintVisualizerMode::Waveform,
+        VisualizerMode::Oscilloscope => SlintVisualizerMode::Oscilloscope,
+        VisualizerMode::Vu => SlintVisualizerMode::Vu,
+    }
+}
+
+pub fn levels_to_slint(levels: &AudioLevels) -> SlintAudioLevels {
+    SlintAudioLevels {
+        left_rms: levels.left.rms,
+        left_peak: levels.left.peak,
+        right_rms: levels.right.rms,
+        right_peak: levels.right.peak,
+    }
+}
+
+pub fn loudness_to_slint(loudness: &LoudnessState) -> SlintLoudnessState {
+    let format = |value: f32| {
+        if value.is_finite() { SharedString::from(format!("{value:.1}")) } else { "–".into() }
+    };
+
+    SlintLoudnessState {
+        momentary: format(loudness.momentary),
+        short_term: format(loudness.short_term),
+        integrated: format(loudness.integrated),
+        true_peak: format(loudness.true_peak),
+    }
+}
+
+/// Builds path commands for one channel of the scope, in a viewbox of `points × 2`.
+/// A line of text each, gaps between verses included so the indices match the core's.
+pub fn lyrics_to_slint(lyrics: Option<&Lyrics>) -> ModelRc<SharedString> {
+    let lines = lyrics.map_or_else(Vec::new, |lyrics| {
+        lyrics.lines.iter().map(|line| SharedString::from(&line.text)).collect()
+    });
+
+    ModelRc::new(VecModel::from(lines))
+}
+
+pub fn scope_to_path(scope: &[[f32; 2]], channel: usize) -> SharedString {
+    let mut commands = String::with_capacity(scope.len() * 12);
+
+    for (idx, frame) in scope.iter().enumerate() {
+        let y = 1.0 - frame[channel].clamp(-1.0, 1.0);
+        let _ = write!(commands, "{}{idx} {y:.3} ", if idx == 0 { "M" } else { "L" });
+    }
+
+    SharedString::from(commands)
+}
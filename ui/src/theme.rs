@@ -0,0 +1,55 @@
+use lumen_core::{Curve, Rgba, Theme};
+use slint::{Color, ComponentHandle};
+
+use crate::{Colors, Curve as SlintCurve, Metrics, Motion, Shell};
+
+const PX_PER_PT: f32 = 96.0 / 72.0;
+
+/// Pushes `theme` into the shell's theme globals. The island's corner radius is part of
+/// its bounds, so that one goes through the island state instead.
+pub fn apply_theme(shell: &Shell, theme: &Theme) {
+    let colors = shell.global::<Colors>();
+    let c = &theme.colors;
+
+    let mut island_bg = c.island_bg;
+    island_bg.a = (island_bg.a as f32 * theme.effects.opacity.clamp(0.0, 1.0)).round() as u8;
+
+    colors.set_island_bg(color(island_bg));
+    colors.set_island_border(color(c.island_border));
+    colors.set_island_shadow(color(c.island_shadow));
+    colors.set_island_shadow_blur(theme.effects.shadow_blur);
+    colors.set_text_primary(color(c.text_primary));
+    colors.set_text_secondary(color(c.text_secondary));
+    colors.set_accent(color(c.accent));
+    colors.set_on_accent(color(c.on_accent));
+    colors.set_track(color(c.track));
+    colors.set_track_faint(color(c.track_faint));
+    colors.set_track_strong(color(c.track_strong));
+    colors.set_track_hover(color(c.track_hover));
+    colors.set_hover(color(c.hover));
+    colors.set_danger(color(c.danger));
+    colors.set_mic(color(c.mic));
+    colors.set_camera(color(c.camera));
+    colors.set_screen_capture(color(c.screen_capture));
+    colors.set_location(color(c.location));
+
+    let metrics = shell.global::<Metrics>();
+    metrics.set_border_width(theme.metrics.border_width);
+    metrics.set_font_family(theme.metrics.font_family.as_str().into());
+    metrics.set_fs_100(theme.metrics.font_size * PX_PER_PT);
+    metrics.set_spacing(theme.metrics.spacing);
+
+    let motion = shell.global::<Motion>();
+    motion.set_curve(match theme.motion.curve {
+        Curve::Spring => SlintCurve::Spring,
+        Curve::Smooth => SlintCurve::Smooth,
+        Curve::Linear => SlintCurve::Linear,
+    });
+    motion.set_resize(theme.motion.resize as i64);
+    motion.set_slide(theme.motion.slide as i64);
+    motion.set_fade(theme.motion.fade as i64);
+}
+
+fn color(rgba: Rgba) -> Color {
+    Color::from_argb_u8(rgba.a, rgba.r, rgba.g, rgba.b)
+}
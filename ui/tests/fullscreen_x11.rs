@@ -0,0 +1,91 @@
+//! Runs the EWMH fullscreen detection against a real X server. Meant for Xvfb, e.g.
+//! `xvfb-run cargo test -p lumen --test fullscreen_x11 -- --ignored`.
+
+#![cfg(target_os = "linux")]
+
+use x11rb::{
+    COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT,
+    connection::Connection,
+    protocol::xproto::{AtomEnum, ConnectionExt as _, CreateWindowAux, PropMode, WindowClass},
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+};
+
+#[path = "../src/platform/fullscreen/x11.rs"]
+mod fullscreen;
+
+use fullscreen::{FullscreenAtoms, active_window, fullscreen_active_window};
+
+#[test]
+#[ignore = "needs an X server, run under xvfb-run with --ignored"]
+fn follows_the_active_window_in_and_out_of_fullscreen() {
+    let (conn, screen) = RustConnection::connect(None).unwrap();
+    let root = conn.setup().roots[screen].root;
+    let atoms = FullscreenAtoms::new(&conn).unwrap().reply().unwrap();
+
+    let island = create_window(&conn, root);
+    let app = create_window(&conn, root);
+
+    // Xvfb runs without a window manager, so publish what one would
+    set_active(&conn, root, &atoms, app);
+    set_state(&conn, app, &atoms, &[]);
+    assert_eq!(active_window(&conn, root, island, &atoms), Some(app));
+    assert_eq!(fullscreen_active_window(&conn, root, island, &atoms), None);
+
+    set_state(&conn, app, &atoms, &[atoms._NET_WM_STATE_FULLSCREEN]);
+    assert_eq!(fullscreen_active_window(&conn, root, island, &atoms), Some(app));
+
+    set_state(&conn, app, &atoms, &[]);
+    assert_eq!(fullscreen_active_window(&conn, root, island, &atoms), None);
+
+    // a fullscreen app that lost focus no longer covers the island
+    set_state(&conn, app, &atoms, &[atoms._NET_WM_STATE_FULLSCREEN]);
+    set_active(&conn, root, &atoms, x11rb::NONE);
+    assert_eq!(fullscreen_active_window(&conn, root, island, &atoms), None);
+
+    // nor does the island itself
+    set_state(&conn, island, &atoms, &[atoms._NET_WM_STATE_FULLSCREEN]);
+    set_active(&conn, root, &atoms, island);
+    assert_eq!(fullscreen_active_window(&conn, root, island, &atoms), None);
+}
+
+fn create_window(conn: &RustConnection, root: u32) -> u32 {
+    let window = conn.generate_id().unwrap();
+
+    conn.create_window(
+        COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        100,
+        100,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        COPY_FROM_PARENT,
+        &CreateWindowAux::new(),
+    )
+    .unwrap();
+    conn.map_window(window).unwrap();
+    conn.flush().unwrap();
+
+    window
+}
+
+fn set_active(conn: &RustConnection, root: u32, atoms: &FullscreenAtoms, window: u32) {
+    conn.change_property32(
+        PropMode::REPLACE,
+        root,
+        atoms._NET_ACTIVE_WINDOW,
+        AtomEnum::WINDOW,
+        &[window],
+    )
+    .unwrap();
+    conn.flush().unwrap();
+}
+
+fn set_state(conn: &RustConnection, window: u32, atoms: &FullscreenAtoms, states: &[u32]) {
+    conn.change_property32(PropMode::REPLACE, window, atoms._NET_WM_STATE, AtomEnum::ATOM, states)
+        .unwrap();
+    conn.flush().unwrap();
+}
@@ -0,0 +1,351 @@
+//! Renders `Shell` with Slint's software renderer on a headless platform and compares each
+//! fixture against `tests/golden/<name>.png`.
+//!
+//! A fixture without a golden fails. Set `LUMEN_BLESS=1` to record all of them, after adding
+//! a fixture or an intentional layout change; on a mismatch the actual render and a diff
+//! image are written to the target's tmp directory.
+
+use std::{
+    cell::{Cell, RefCell},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+use image::{Rgba, RgbaImage};
+use lumen_core::MediaCapabilities;
+use slint::{
+    ComponentHandle, PhysicalSize,
+    platform::{
+        Platform, PlatformError, WindowAdapter,
+        software_renderer::{MinimalSoftwareWindow, PremultipliedRgbaColor, RepaintBufferType},
+    },
+};
+
+#[allow(dead_code)]
+#[path = "../src/geometry.rs"]
+mod geometry;
+#[allow(dead_code)]
+#[path = "../src/state.rs"]
+mod state;
+#[allow(dead_code)]
+#[path = "../src/sync.rs"]
+mod sync;
+
+use geometry::{SHELL_HEIGHT, SHELL_WIDTH};
+use state::{ContentState, IslandState};
+use sync::{media_to_slint, notification_to_slint, volume_to_slint};
+
+slint::include_modules!();
+
+/// Per-pixel YIQ distance, as a fraction of the largest possible one, below which two pixels
+/// count as the same. Absorbs antialiasing and hinting differences between machines.
+const PIXEL_THRESHOLD: f32 = 0.1;
+/// Share of pixels allowed to differ before a fixture fails.
+const MAX_DIFF_RATIO: f32 = 0.002;
+
+/// Long enough for the island's resize and content-switch timers (≤ 550ms), short of the
+/// 3s notification and 4s visibility timers that would hide it again.
+const SETTLE: Duration = Duration::from_millis(1500);
+const FRAME: Duration = Duration::from_millis(16);
+
+struct Fixture {
+    name: &'static str,
+    state: IslandState,
+}
+
+#[test]
+fn golden_screenshots() {
+    let platform = HeadlessPlatform::default();
+    let clock = platform.clock.clone();
+    let window = platform.window.clone();
+    slint::platform::set_platform(Box::new(platform)).unwrap();
+
+    let bless = std::env::var_os("LUMEN_BLESS").is_some_and(|value| value != "0");
+    let mut failures = Vec::new();
+
+    for fixture in fixtures() {
+        let actual = render(&fixture, &clock, &window);
+        let golden_path = golden_dir().join(format!("{}.png", fixture.name));
+
+        if bless {
+            std::fs::create_dir_all(golden_dir()).unwrap();
+            actual.save(&golden_path).unwrap();
+            eprintln!("recorded {}", golden_path.display());
+            continue;
+        }
+        if !golden_path.exists() {
+            failures.push(format!("{}: no golden at {}", fixture.name, golden_path.display()));
+            continue;
+        }
+
+        let golden = image::open(&golden_path).unwrap().to_rgba8();
+        if let Err(reason) = compare(&actual, &golden, fixture.name) {
+            failures.push(format!("{}: {reason}", fixture.name));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} fixture(s) don't match their goldens (LUMEN_BLESS=1 re-records them):\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+fn fixtures() -> Vec<Fixture> {
+    // the Slint structs of the same names come from `include_modules!`
+    let media = lumen_core::MediaState {
+        synced_at: SystemTime::UNIX_EPOCH,
+        app_id: "Spotify.exe".into(),
+        app_name: "Spotify".into(),
+        title: "Midnight City".into(),
+        artist: "M83".into(),
+        album: "Hurry Up, We're Dreaming".into(),
+        album_art: None,
+        palette: None,
+        duration_ms: 243_000,
+        position_ms: 61_000,
+        playing: true,
+        shuffle: None,
+        repeat: None,
+        rate: None,
+        capabilities: MediaCapabilities::default(),
+        lyrics: None,
+        app_icon: None,
+    };
+    let long_media = lumen_core::MediaState {
+        title: "A Title So Long That It Can't Possibly Fit Inside The Island Without Eliding \
+                Somewhere Along The Way"
+            .into(),
+        artist: "An Artist With An Equally Unreasonable Name, Featuring Several Guests".into(),
+        ..media.clone()
+    };
+    let rtl_media = lumen_core::MediaState {
+        title: "שיר ארוך מאוד בעברית".into(),
+        artist: "فنان عربي".into(),
+        album: "ألبوم".into(),
+        ..media.clone()
+    };
+    let notification = lumen_core::NotificationState {
+        id: 7,
+        app_name: "Mail".into(),
+        app_icon: Some("/nonexistent/mail.png".into()),
+        title: "Lunch on Friday?".into(),
+        body: "Are we still on for Friday? I can book the place near the office if that works \
+               for everyone."
+            .into(),
+    };
+
+    let state = |content: ContentState, expanded: bool| {
+        let mut state = IslandState::new();
+        state.content = content;
+        state.expanded = expanded;
+        state
+    };
+    let fixture = |name, state| Fixture { name, state };
+
+    let mut indicators = state(ContentState::Idle, false);
+    indicators.mic = true;
+    indicators.camera = true;
+
+    let volume = lumen_core::VolumeState {
+        level: 0.42,
+        muted: false,
+        device: "Headphones (USB Audio)".into(),
+    };
+
+    vec![
+        fixture("idle", state(ContentState::Idle, false)),
+        fixture("media-collapsed", state(ContentState::Media(media.clone()), false)),
+        fixture("media-expanded", state(ContentState::Media(media), true)),
+        fixture("media-long-collapsed", state(ContentState::Media(long_media.clone()), false)),
+        fixture("media-long-expanded", state(ContentState::Media(long_media), true)),
+        fixture("media-rtl-expanded", state(ContentState::Media(rtl_media), true)),
+        fixture(
+            "notification-missing-icon",
+            state(ContentState::Notification(notification.clone()), false),
+        ),
+        fixture(
+            "notification-missing-icon-expanded",
+            state(ContentState::Notification(notification), true),
+        ),
+        fixture("volume", state(ContentState::Volume(volume), false)),
+        fixture("indicators", indicators),
+    ]
+}
+
+/// Feeds `fixture` to a fresh `Shell` the way the app's `sync_shell` does, lets it settle and
+/// returns the frame.
+fn render(
+    fixture: &Fixture,
+    clock: &Cell<Duration>,
+    window: &RefCell<Option<Rc<MinimalSoftwareWindow>>>,
+) -> RgbaImage {
+    let shell = Shell::new().unwrap();
+    let window = window.borrow().clone().expect("Shell didn't create a window");
+    window.window().set_size(PhysicalSize::new(SHELL_WIDTH as u32, SHELL_HEIGHT as u32));
+
+    let global = shell.global::<IslandData>();
+    let assets = shell.global::<Assets>();
+    let state = &fixture.state;
+
+    global.set_expanded(state.expanded);
+    global.set_mic(state.mic);
+    global.set_camera(state.camera);
+
+    match &state.content {
+        ContentState::Idle => global.set_content(IslandContent::Idle),
+        ContentState::Media(media) => {
+            global.set_media(media_to_slint(
+                media,
+                &assets.get_fallback_app(),
+                &assets.get_fallback_media(),
+            ));
+            global.set_media_position(media.position_ms as i32);
+            global.set_content(IslandContent::Media);
+        }
+        ContentState::Notification(notification) => {
+            global
+                .set_notification(notification_to_slint(notification, &assets.get_fallback_app()));
+            global.set_content(IslandContent::Notification);
+        }
+        ContentState::Volume(volume) => {
+            global.set_volume(volume_to_slint(volume));
+            global.set_content(IslandContent::Volume);
+        }
+    }
+
+    let bounds = state.bounds();
+    shell.set_island_width(bounds.width as f32);
+    shell.set_island_height(bounds.height as f32);
+    shell.set_island_radius(bounds.radius as f32);
+    shell.set_island_y(bounds.y as f32);
+
+    shell.show().unwrap();
+
+    let mut elapsed = Duration::ZERO;
+    while elapsed < SETTLE {
+        clock.set(clock.get() + FRAME);
+        elapsed += FRAME;
+        slint::platform::update_timers_and_animations();
+    }
+
+    let (width, height) = (SHELL_WIDTH as usize, SHELL_HEIGHT as usize);
+    let mut buffer = vec![PremultipliedRgbaColor::default(); width * height];
+    window.request_redraw();
+    window.draw_if_needed(|renderer| {
+        renderer.render(&mut buffer, width);
+    });
+
+    shell.hide().unwrap();
+
+    RgbaImage::from_fn(width as u32, height as u32, |x, y| {
+        let pixel = buffer[y as usize * width + x as usize];
+        let unpremultiply = |channel: u8| match pixel.alpha {
+            0 => 0,
+            alpha => (channel as u32 * 255 / alpha as u32).min(255) as u8,
+        };
+
+        Rgba([
+            unpremultiply(pixel.red),
+            unpremultiply(pixel.green),
+            unpremultiply(pixel.blue),
+            pixel.alpha,
+        ])
+    })
+}
+
+fn compare(actual: &RgbaImage, golden: &RgbaImage, name: &str) -> Result<(), String> {
+    if actual.dimensions() != golden.dimensions() {
+        return Err(format!("size {:?}, golden is {:?}", actual.dimensions(), golden.dimensions()));
+    }
+
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    let mut differing = 0usize;
+
+    for (x, y, expected) in golden.enumerate_pixels() {
+        let found = actual.get_pixel(x, y);
+        if color_delta(found.0, expected.0) > PIXEL_THRESHOLD {
+            differing += 1;
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            let faded = 255 - ((255 - luma(expected.0) as u32) / 4) as u8;
+            diff.put_pixel(x, y, Rgba([faded, faded, faded, 255]));
+        }
+    }
+
+    let ratio = differing as f32 / (actual.width() * actual.height()) as f32;
+    if ratio <= MAX_DIFF_RATIO {
+        return Ok(());
+    }
+
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    std::fs::create_dir_all(&out).unwrap();
+    actual.save(out.join(format!("{name}.actual.png"))).unwrap();
+    diff.save(out.join(format!("{name}.diff.png"))).unwrap();
+
+    Err(format!(
+        "{differing} pixels ({:.2}%) differ, see {}",
+        ratio * 100.0,
+        out.join(format!("{name}.diff.png")).display()
+    ))
+}
+
+/// Distance between two pixels in YIQ space after blending both over white, normalized to
+/// 0..=1. Weighs luma over chroma the way the eye does, so faint antialiasing shifts stay
+/// under the threshold while a moved edge or missing glyph doesn't.
+fn color_delta(a: [u8; 4], b: [u8; 4]) -> f32 {
+    if a == b {
+        return 0.0;
+    }
+
+    let yiq = |pixel: [u8; 4]| {
+        let alpha = pixel[3] as f32 / 255.0;
+        let [r, g, b] = [0, 1, 2].map(|idx| 255.0 + (pixel[idx] as f32 - 255.0) * alpha);
+
+        (
+            r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2,
+            r * 0.595_978 - g * 0.274_176_5 - b * 0.321_801_5,
+            r * 0.211_470_2 - g * 0.522_617_5 + b * 0.311_147_3,
+        )
+    };
+
+    let (y1, i1, q1) = yiq(a);
+    let (y2, i2, q2) = yiq(b);
+    let delta = 0.5053 * (y1 - y2).powi(2) + 0.299 * (i1 - i2).powi(2) + 0.1957 * (q1 - q2).powi(2);
+
+    (delta / 35215.0).sqrt()
+}
+
+fn luma(pixel: [u8; 4]) -> u8 {
+    let alpha = pixel[3] as f32 / 255.0;
+    let y = pixel[0] as f32 * 0.299 + pixel[1] as f32 * 0.587 + pixel[2] as f32 * 0.114;
+
+    (255.0 + (y - 255.0) * alpha) as u8
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+/// A platform without a windowing system whose clock only moves when the test says so, so
+/// animations and timers land on the same frame on every run.
+#[derive(Default)]
+struct HeadlessPlatform {
+    clock: Rc<Cell<Duration>>,
+    window: Rc<RefCell<Option<Rc<MinimalSoftwareWindow>>>>,
+}
+
+impl Platform for HeadlessPlatform {
+    fn create_window_adapter(&self) -> Result<Rc<dyn WindowAdapter>, PlatformError> {
+        let window = MinimalSoftwareWindow::new(RepaintBufferType::NewBuffer);
+        *self.window.borrow_mut() = Some(window.clone());
+
+        Ok(window)
+    }
+
+    fn duration_since_start(&self) -> Duration {
+        self.clock.get()
+    }
+}
@@ -0,0 +1,90 @@
+//! Grabs hotkeys on a real X server and presses them through XTEST. Meant for Xvfb, e.g.
+//! `xvfb-run cargo test -p lumen --test hotkeys_x11 -- --ignored`.
+
+#![cfg(target_os = "linux")]
+
+use std::time::{Duration, Instant};
+
+use lumen_core::{Hotkey, IslandAction};
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        Event,
+        xproto::{KEY_PRESS_EVENT, KEY_RELEASE_EVENT},
+        xtest::ConnectionExt as _,
+    },
+    rust_connection::RustConnection,
+};
+
+// the backend's entry points are for the app, only the grabbing is tested here
+#[allow(dead_code)]
+#[path = "../src/platform/hotkeys/x11.rs"]
+mod hotkeys;
+
+use hotkeys::{Grabs, Keymap, keysym};
+
+const XK_CONTROL_L: u32 = 0xffe3;
+const XK_ALT_L: u32 = 0xffe9;
+
+#[test]
+#[ignore = "needs an X server, run under xvfb-run with --ignored"]
+fn grabbed_hotkeys_fire_and_taken_ones_are_reported() {
+    let (conn, screen) = RustConnection::connect(None).unwrap();
+    let root = conn.setup().roots[screen].root;
+    let keymap = Keymap::new(&conn).unwrap();
+
+    let playback: Hotkey = "Ctrl+Alt+P".parse().unwrap();
+    let next: Hotkey = "Ctrl+Alt+Right".parse().unwrap();
+    let grabs = Grabs::new(
+        &conn,
+        root,
+        &[(playback, IslandAction::TogglePlayback), (next, IslandAction::Next)],
+    )
+    .unwrap();
+    assert!(grabs.taken.is_empty());
+
+    let ctrl = keymap.keycode(XK_CONTROL_L).unwrap();
+    let alt = keymap.keycode(XK_ALT_L).unwrap();
+    let p = keymap.keycode(keysym(playback.key)).unwrap();
+    let right = keymap.keycode(keysym(next.key)).unwrap();
+
+    press(&conn, root, &[ctrl, alt, p]);
+    assert_eq!(next_action(&conn, &grabs), Some(IslandAction::TogglePlayback));
+
+    press(&conn, root, &[ctrl, alt, right]);
+    assert_eq!(next_action(&conn, &grabs), Some(IslandAction::Next));
+
+    // without the modifiers it's just a key press for whoever has focus
+    press(&conn, root, &[p]);
+    assert_eq!(next_action(&conn, &grabs), None);
+
+    // a second client can't grab what the first one holds
+    let (other, _) = RustConnection::connect(None).unwrap();
+    let taken = Grabs::new(&other, root, &[(playback, IslandAction::TogglePlayback)]).unwrap();
+    assert_eq!(taken.taken, vec![playback]);
+}
+
+/// Presses `keys` in order, then releases them the other way round.
+fn press(conn: &RustConnection, root: u32, keys: &[u8]) {
+    for &key in keys {
+        conn.xtest_fake_input(KEY_PRESS_EVENT, key, x11rb::CURRENT_TIME, root, 0, 0, 0).unwrap();
+    }
+    for &key in keys.iter().rev() {
+        conn.xtest_fake_input(KEY_RELEASE_EVENT, key, x11rb::CURRENT_TIME, root, 0, 0, 0).unwrap();
+    }
+    conn.flush().unwrap();
+}
+
+/// The action of the first grabbed key press to arrive within a second.
+fn next_action(conn: &RustConnection, grabs: &Grabs) -> Option<IslandAction> {
+    let deadline = Instant::now() + Duration::from_secs(1);
+
+    while Instant::now() < deadline {
+        match conn.poll_for_event().unwrap() {
+            Some(Event::KeyPress(event)) => return grabs.action(&event),
+            Some(_) => {}
+            None => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+    None
+}